@@ -0,0 +1,78 @@
+//! Trit-level circuits versus the word-level `fast-arith` path.
+//!
+//! The adder comparison calls both paths directly. The CPU run uses whichever path
+//! the build selected, so compare
+//! `cargo bench --bench arithmetic` with `cargo bench --bench arithmetic --features fast-arith`.
+
+use std::{hint::black_box, time::Instant};
+
+use eris_bst27i::{
+    arch::{circuits::ErisCircuit, fast_arith, trit::Trit, trit::Tryte},
+    asm::assembler::assemble,
+    core::{address_space::AddressSpace, alu::ArithmeticLogicUnit, registers::Registers},
+    cpu::CentralProcessingUnit,
+};
+
+const ADDS: i128 = 100_000;
+const CYCLES: u64 = 200_000;
+
+fn time(name: &str, operations: u64, run: impl FnOnce()) {
+    let start = Instant::now();
+    run();
+    let elapsed = start.elapsed();
+    let per_op = elapsed.as_nanos() as f64 / operations as f64;
+    println!("{:<36} {:>10.2?}  {:>7.1} ns/op", name, elapsed, per_op);
+}
+
+fn adders() {
+    let circuit = ErisCircuit::default();
+    let operands: Vec<(Tryte, Tryte)> = (0..ADDS)
+        .map(|i| {
+            (
+                Tryte::from_i128(i * 38_127_987_424),
+                Tryte::from_i128(-i * 7919),
+            )
+        })
+        .collect();
+
+    time("ErisCircuit::ripple_add_with_carry", ADDS as u64, || {
+        for (a, b) in &operands {
+            black_box(circuit.ripple_add_with_carry(a, b, Trit::Zero));
+        }
+    });
+    time("fast_arith::add", ADDS as u64, || {
+        for (a, b) in &operands {
+            black_box(fast_arith::add(a, b, Trit::Zero));
+        }
+    });
+}
+
+fn cpu_loop() {
+    let program = assemble(
+        "
+        loop: addi x1, x1, 1
+              sub  x2, x2, x1
+              add  x3, x3, x2
+              jal  x0, loop
+        ",
+    )
+    .unwrap();
+    let mut memory = AddressSpace::default();
+    program.load_into(&mut memory);
+    let mut cpu =
+        CentralProcessingUnit::from(Registers::default(), memory, ArithmeticLogicUnit::default());
+
+    let path = if cfg!(feature = "fast-arith") {
+        "fast-arith"
+    } else {
+        "circuits"
+    };
+    time(&format!("CPU add/sub loop ({})", path), CYCLES, || {
+        black_box(cpu.run(CYCLES));
+    });
+}
+
+fn main() {
+    adders();
+    cpu_loop();
+}
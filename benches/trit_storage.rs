@@ -0,0 +1,147 @@
+//! Memory and speed of the packed `Tryte` versus one `Trit` per element.
+//!
+//! Run with `cargo bench --bench trit_storage`.
+
+use std::{collections::HashMap, hint::black_box, mem::size_of, time::Instant};
+
+use eris_bst27i::{
+    arch::trit::{Trit, Tryte},
+    core::address_space::AddressSpace,
+};
+
+/// Words touched per run; 3^9, one page of a small program
+const WORDS: i128 = 19_683;
+const ROUNDS: u32 = 20;
+
+fn time(name: &str, mut run: impl FnMut()) {
+    run(); // warm-up
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        run();
+    }
+    let per_round = start.elapsed() / ROUNDS;
+    let per_word = per_round.as_nanos() as f64 / WORDS as f64;
+    println!(
+        "{:<40} {:>10.2?}  {:>7.1} ns/word",
+        name, per_round, per_word
+    );
+}
+
+/// Approximate heap bytes of a `HashMap` with `capacity` slots: one key, one value
+/// and one control byte per slot.
+fn map_bytes<K, V>(capacity: usize) -> usize {
+    capacity * (size_of::<K>() + size_of::<V>() + 1)
+}
+
+/// The layout `TritField` had before packing: one `Trit` enum per element
+type Unpacked = [Trit; 27];
+
+/// Value of an unpacked word, one power of three per trit
+fn unpacked_value(word: &Unpacked) -> i128 {
+    word.iter()
+        .rev()
+        .fold(0, |value, trit| value * 3 + trit.to_i8() as i128)
+}
+
+fn memory() {
+    let mut unpacked: HashMap<Unpacked, Unpacked> = HashMap::new();
+    let mut packed: HashMap<Tryte, Tryte> = HashMap::new();
+    for address in 0..WORDS {
+        let word = Tryte::from_i128(address * 7919);
+        unpacked.insert(Tryte::from_i128(address).to_trits(), word.to_trits());
+        packed.insert(Tryte::from_i128(address), word);
+    }
+
+    println!("size_of::<[Trit; 27]>() = {} bytes", size_of::<Unpacked>());
+    println!("size_of::<Tryte>()      = {} bytes", size_of::<Tryte>());
+    println!(
+        "{} words in HashMap<[Trit; 27], [Trit; 27]> ~ {} KiB",
+        WORDS,
+        map_bytes::<Unpacked, Unpacked>(unpacked.capacity()) / 1024
+    );
+    println!(
+        "{} words in HashMap<Tryte, Tryte>           ~ {} KiB",
+        WORDS,
+        map_bytes::<Tryte, Tryte>(packed.capacity()) / 1024
+    );
+    println!();
+}
+
+fn conversions() {
+    let words: Vec<Tryte> = (0..WORDS).map(|v| Tryte::from_i128(v * 7919)).collect();
+    let unpacked: Vec<Unpacked> = words.iter().map(Tryte::to_trits).collect();
+
+    time("[Trit; 27] value", || {
+        for word in &unpacked {
+            black_box(unpacked_value(word));
+        }
+    });
+    time("Tryte::to_i128", || {
+        for word in &words {
+            black_box(word.to_i128());
+        }
+    });
+    time("[Trit; 27] read every trit", || {
+        for word in &unpacked {
+            black_box(word.iter().filter(|trit| **trit == Trit::Positive).count());
+        }
+    });
+    time("Tryte::trit on every trit", || {
+        for word in &words {
+            black_box((0..27).filter(|i| word.trit(*i) == Trit::Positive).count());
+        }
+    });
+    time("Tryte::from_trits", || {
+        for word in &unpacked {
+            black_box(Tryte::from_trits(*word));
+        }
+    });
+    time("Tryte::to_trits", || {
+        for word in &words {
+            black_box(word.to_trits());
+        }
+    });
+}
+
+fn maps() {
+    let addresses: Vec<Tryte> = (0..WORDS).map(Tryte::from_i128).collect();
+    let values: Vec<Tryte> = (0..WORDS).map(|v| Tryte::from_i128(-v)).collect();
+
+    time("HashMap<[Trit; 27], [Trit; 27]> insert + get", || {
+        let mut map = HashMap::new();
+        for (address, value) in addresses.iter().zip(&values) {
+            map.insert(address.to_trits(), value.to_trits());
+        }
+        for address in &addresses {
+            black_box(map.get(&address.to_trits()));
+        }
+    });
+
+    time("HashMap<Tryte, Tryte> insert + get", || {
+        let mut map = HashMap::new();
+        for (address, value) in addresses.iter().zip(&values) {
+            map.insert(*address, *value);
+        }
+        for address in &addresses {
+            black_box(map.get(address));
+        }
+    });
+
+    time("AddressSpace (paged) write + read", || {
+        let mut space = AddressSpace::default();
+        for (address, value) in addresses.iter().zip(&values) {
+            space.write(*address, *value);
+        }
+        for address in &addresses {
+            black_box(space.read(*address));
+        }
+    });
+}
+
+fn main() {
+    println!("trit storage, {} words, mean of {} rounds\n", WORDS, ROUNDS);
+    memory();
+    conversions();
+    maps();
+}
@@ -1,4 +1,4 @@
-use crate::arch::trit::Trit;
+use crate::arch::trit::{Trit, TritField};
 
 #[derive(Default)]
 pub struct ErisCircuit {}
@@ -41,4 +41,164 @@ impl ErisCircuit {
             (Trit::Positive, Trit::Positive) => Trit::Positive,
         }
     }
+
+    /// Maximum function (Equivalent to Kleene Logic OR)
+    pub fn max(&self, input_a: Trit, input_b: Trit) -> Trit {
+        match (input_a, input_b) {
+            (Trit::Positive, _) | (_, Trit::Positive) => Trit::Positive,
+            (Trit::Zero, _) | (_, Trit::Zero) => Trit::Zero,
+            (Trit::Negative, Trit::Negative) => Trit::Negative,
+        }
+    }
+
+    /// Consensus: the common value if both inputs agree, otherwise Zero
+    pub fn consensus(&self, input_a: Trit, input_b: Trit) -> Trit {
+        if input_a == input_b {
+            input_a
+        } else {
+            Trit::Zero
+        }
+    }
+
+    /// Antimin: the inverted minimum (Equivalent to NAND)
+    pub fn antimin(&self, input_a: Trit, input_b: Trit) -> Trit {
+        self.not(self.min(input_a, input_b))
+    }
+
+    /// Inverter: swaps Positive and Negative, Zero stays Zero
+    pub fn not(&self, input: Trit) -> Trit {
+        match input {
+            Trit::Positive => Trit::Negative,
+            Trit::Zero => Trit::Zero,
+            Trit::Negative => Trit::Positive,
+        }
+    }
+
+    /// Sum modulo 3 without carry (the ternary analogue of XOR)
+    pub fn sum(&self, input_a: Trit, input_b: Trit) -> Trit {
+        self.full_trit_adder(input_a, input_b, Trit::Zero).0
+    }
+
+    /// Single-trit multiplier; the product of two trits never carries
+    pub fn mul(&self, input_a: Trit, input_b: Trit) -> Trit {
+        match (input_a, input_b) {
+            (Trit::Zero, _) | (_, Trit::Zero) => Trit::Zero,
+            _ if input_a == input_b => Trit::Positive,
+            _ => Trit::Negative,
+        }
+    }
+}
+
+/// Word-wide circuits built from the single-trit gates above
+impl ErisCircuit {
+    /// Ripple-carry adder: returns (Sum, Carry out of the top trit)
+    pub fn ripple_add<const N: usize>(
+        &self,
+        input_a: &TritField<N>,
+        input_b: &TritField<N>,
+    ) -> (TritField<N>, Trit) {
+        self.ripple_add_with_carry(input_a, input_b, Trit::Zero)
+    }
+
+    /// Ripple-carry adder with a carry fed into the lowest trit
+    pub fn ripple_add_with_carry<const N: usize>(
+        &self,
+        input_a: &TritField<N>,
+        input_b: &TritField<N>,
+        carry_in: Trit,
+    ) -> (TritField<N>, Trit) {
+        let mut sum = TritField::<N>::default();
+        let mut carry = carry_in;
+
+        for i in 0..N {
+            let (s, c) = self.full_trit_adder(input_a.trit(i), input_b.trit(i), carry);
+            sum.set_trit(i, s);
+            carry = c;
+        }
+
+        (sum, carry)
+    }
+
+    /// Negates a word by inverting every trit
+    pub fn negate<const N: usize>(&self, input: &TritField<N>) -> TritField<N> {
+        TritField::from_trits(input.to_trits().map(|trit| self.not(trit)))
+    }
+
+    /// Sign of a word: its most significant non-zero trit, Zero for zero
+    pub fn sign<const N: usize>(&self, input: &TritField<N>) -> Trit {
+        input
+            .trits()
+            .rev()
+            .find(|trit| *trit != Trit::Zero)
+            .unwrap_or(Trit::Zero)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRITS: [Trit; 3] = [Trit::Negative, Trit::Zero, Trit::Positive];
+
+    /// Checks a two-input gate against a 3x3 table indexed by [a][b], -1..=1
+    fn assert_truth_table(gate: fn(&ErisCircuit, Trit, Trit) -> Trit, table: [[i8; 3]; 3]) {
+        let circuit = ErisCircuit::default();
+        for (i, a) in TRITS.iter().enumerate() {
+            for (j, b) in TRITS.iter().enumerate() {
+                assert_eq!(
+                    gate(&circuit, *a, *b).to_i8(),
+                    table[i][j],
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_max_truth_tables() {
+        assert_truth_table(ErisCircuit::min, [[-1, -1, -1], [-1, 0, 0], [-1, 0, 1]]);
+        assert_truth_table(ErisCircuit::max, [[-1, 0, 1], [0, 0, 1], [1, 1, 1]]);
+    }
+
+    #[test]
+    fn test_consensus_antimin_sum_truth_tables() {
+        assert_truth_table(ErisCircuit::consensus, [[-1, 0, 0], [0, 0, 0], [0, 0, 1]]);
+        assert_truth_table(ErisCircuit::antimin, [[1, 1, 1], [1, 0, 0], [1, 0, -1]]);
+        assert_truth_table(ErisCircuit::sum, [[1, -1, 0], [-1, 0, 1], [0, 1, -1]]);
+    }
+
+    #[test]
+    fn test_mul_truth_table() {
+        assert_truth_table(ErisCircuit::mul, [[1, 0, -1], [0, 0, 0], [-1, 0, 1]]);
+    }
+
+    #[test]
+    fn test_word_circuits() {
+        let circuit = ErisCircuit::default();
+        let word = |value| TritField::<6>::from_i128(value);
+
+        assert_eq!(
+            circuit.ripple_add(&word(100), &word(-37)),
+            (word(63), Trit::Zero)
+        );
+        // 364 is the largest 6-trit value
+        assert_eq!(
+            circuit.ripple_add(&word(364), &word(1)),
+            (word(-364), Trit::Positive)
+        );
+        assert_eq!(circuit.negate(&word(-121)), word(121));
+        assert_eq!(circuit.sign(&word(-2)), Trit::Negative);
+        assert_eq!(circuit.sign(&word(0)), Trit::Zero);
+        assert_eq!(circuit.sign(&word(2)), Trit::Positive);
+    }
+
+    #[test]
+    fn test_not_inverts() {
+        let circuit = ErisCircuit::default();
+        for trit in TRITS {
+            assert_eq!(circuit.not(trit).to_i8(), -trit.to_i8());
+        }
+    }
 }
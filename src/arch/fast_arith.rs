@@ -0,0 +1,222 @@
+//! Word-level arithmetic on `TritField`s.
+//!
+//! The trit-level circuits are the reference model; with the `fast-arith` feature the
+//! ALU and the integer conversions of `TritField` use these functions instead. They work
+//! on the integer value of a field rather than trit by trit and give bit-exact results,
+//! which the differential tests below check against the circuits. Both paths are always
+//! compiled, so the tests run whichever feature set is enabled.
+
+use crate::arch::{
+    circuits::ErisCircuit,
+    trit::{Trit, TritField},
+};
+
+/// Widest field whose full range fits in an `i64`: 3^39 < 2^63
+const MAX_FAST_TRITS: usize = 39;
+
+/// 3^n for every field width the fast path handles
+const POW3: [i64; MAX_FAST_TRITS + 1] = {
+    let mut table = [1; MAX_FAST_TRITS + 1];
+    let mut i = 1;
+    while i < table.len() {
+        table[i] = table[i - 1] * 3;
+        i += 1;
+    }
+    table
+};
+
+/// Bit planes (positive, negative) for each group of three ordinary base-3 digits
+/// 0..27, least significant trit in bit 0
+const DIGIT_GROUPS: [(u64, u64); 27] = {
+    let mut table = [(0, 0); 27];
+    let mut value = 0;
+    while value < 27 {
+        let digits = [value % 3, value / 3 % 3, value / 9];
+        let mut planes = (0, 0);
+        let mut i = 0;
+        while i < 3 {
+            // Digit 0 is a Negative trit, 1 Zero and 2 Positive
+            match digits[i] {
+                0 => planes.1 |= 1 << i,
+                2 => planes.0 |= 1 << i,
+                _ => {}
+            }
+            i += 1;
+        }
+        table[value] = planes;
+        value += 1;
+    }
+    table
+};
+
+/// Bytes of a bit plane the fast path reads: 5 * 8 >= MAX_FAST_TRITS
+const PLANE_BYTES: usize = 5;
+
+/// `PLANE_VALUES[k][byte]` is the sum of 3^(8k + j) over the bits j set in `byte`
+const PLANE_VALUES: [[i64; 256]; PLANE_BYTES] = {
+    let mut table = [[0; 256]; PLANE_BYTES];
+    let mut k = 0;
+    while k < PLANE_BYTES {
+        let mut byte = 0;
+        while byte < 256 {
+            let mut j = 0;
+            while j < 8 {
+                if byte >> j & 1 != 0 && 8 * k + j <= MAX_FAST_TRITS {
+                    table[k][byte] += POW3[8 * k + j];
+                }
+                j += 1;
+            }
+            byte += 1;
+        }
+        k += 1;
+    }
+    table
+};
+
+pub fn to_i128<const N: usize>(field: &TritField<N>) -> i128 {
+    if N > MAX_FAST_TRITS {
+        return field.value_by_trits();
+    }
+
+    to_i64(field) as i128
+}
+
+fn to_i64<const N: usize>(field: &TritField<N>) -> i64 {
+    let (positive, negative) = field.planes();
+    plane_value(positive) - plane_value(negative)
+}
+
+/// Sum of 3^i over the bits i set in a bit plane of at most MAX_FAST_TRITS trits
+fn plane_value(plane: u64) -> i64 {
+    PLANE_VALUES
+        .iter()
+        .zip(plane.to_le_bytes())
+        .map(|(values, byte)| values[byte as usize])
+        .sum()
+}
+
+/// Keeps the low N balanced trits of `value`, like `TritField::from_i128`.
+pub fn from_i128<const N: usize>(value: i128) -> TritField<N> {
+    if N > MAX_FAST_TRITS {
+        return TritField::from_i128_by_trits(value);
+    }
+
+    // Adding (3^N - 1) / 2 turns the balanced digits into ordinary base-3 digits
+    // one higher, and reducing mod 3^N drops everything above trit N - 1.
+    let modulus = POW3[N];
+    let reduced = if value.unsigned_abs() <= (modulus / 2) as u128 {
+        value as i64
+    } else {
+        (value % modulus as i128) as i64
+    };
+    field_from_digits((reduced + modulus / 2).rem_euclid(modulus))
+}
+
+/// Balanced field from the ordinary base-3 digits of `value + (3^N - 1) / 2`
+fn field_from_digits<const N: usize>(digits: i64) -> TritField<N> {
+    let mut digits = digits as u64;
+    let (mut positive, mut negative) = (0, 0);
+
+    for start in (0..N).step_by(3) {
+        let (group_positive, group_negative) = DIGIT_GROUPS[(digits % 27) as usize];
+        positive |= group_positive << start;
+        negative |= group_negative << start;
+        digits /= 27;
+    }
+    TritField::from_planes(positive, negative)
+}
+
+/// Adds two words and a carry trit: returns (Sum, Carry out of the top trit)
+pub fn add<const N: usize>(
+    input_a: &TritField<N>,
+    input_b: &TritField<N>,
+    carry_in: Trit,
+) -> (TritField<N>, Trit) {
+    if N > MAX_FAST_TRITS {
+        return ErisCircuit::default().ripple_add_with_carry(input_a, input_b, carry_in);
+    }
+
+    let modulus = POW3[N];
+    let half = modulus / 2;
+    let total = to_i64(input_a) + to_i64(input_b) + carry_in.to_i8() as i64;
+
+    let carry = if total > half {
+        Trit::Positive
+    } else if total < -half {
+        Trit::Negative
+    } else {
+        Trit::Zero
+    };
+
+    let sum = total - carry.to_i8() as i64 * modulus;
+    (field_from_digits(sum + half), carry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Rng;
+
+    const TRITS: [Trit; 3] = [Trit::Negative, Trit::Zero, Trit::Positive];
+
+    #[test]
+    fn test_add_matches_circuit_exhaustively_on_small_fields() {
+        let circuit = ErisCircuit::default();
+
+        for a in -121..=121 {
+            for b in -121..=121 {
+                let (a, b) = (TritField::<5>::from_i128(a), TritField::<5>::from_i128(b));
+                for carry in TRITS {
+                    assert_eq!(
+                        add(&a, &b, carry),
+                        circuit.ripple_add_with_carry(&a, &b, carry)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_matches_circuit_on_random_words() {
+        let circuit = ErisCircuit::default();
+        let mut rng = Rng(0x007e_570f_fa57);
+
+        for _ in 0..20_000 {
+            let (a, b) = (rng.field::<27>(), rng.field::<27>());
+            let carry = rng.trit();
+            assert_eq!(
+                add(&a, &b, carry),
+                circuit.ripple_add_with_carry(&a, &b, carry),
+                "{} + {} + {}",
+                a.to_i128(),
+                b.to_i128(),
+                carry
+            );
+        }
+    }
+
+    #[test]
+    fn test_conversions_match_trit_by_trit() {
+        let mut rng = Rng(1958);
+
+        for _ in 0..20_000 {
+            let word = rng.field::<27>();
+            assert_eq!(to_i128(&word), word.value_by_trits());
+
+            // Values beyond the field are truncated the same way on both paths
+            let value = (rng.next_u64() as i64 as i128) * rng.below(1000) as i128;
+            assert_eq!(
+                from_i128::<27>(value),
+                TritField::<27>::from_i128_by_trits(value)
+            );
+            assert_eq!(
+                from_i128::<13>(value),
+                TritField::<13>::from_i128_by_trits(value)
+            );
+        }
+
+        let wide = TritField::<54>::from_i128_by_trits(-3_i128.pow(50));
+        assert_eq!(to_i128(&wide), -3_i128.pow(50));
+        assert_eq!(from_i128::<54>(-3_i128.pow(50)), wide);
+    }
+}
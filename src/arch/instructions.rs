@@ -1,4 +1,6 @@
-use crate::arch::trit::Tryte;
+use std::fmt::{Display, Formatter};
+
+use crate::arch::trit::{TritField, Tryte};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Instruction {
@@ -6,20 +8,76 @@ pub enum Instruction {
     Add { rd: usize, rs1: usize, rs2: usize },
     Sub { rd: usize, rs1: usize, rs2: usize },
 
+    // Multi-word and Overflow-Checked Arithmetic (R-Format)
+    Addc { rd: usize, rs1: usize, rs2: usize }, // rs1 + rs2 + status carry
+    Subb { rd: usize, rs1: usize, rs2: usize }, // rs1 - rs2 + status carry
+    Addv { rd: usize, rs1: usize, rs2: usize }, // Add, trap on overflow
+    Subv { rd: usize, rs1: usize, rs2: usize }, // Subtract, trap on overflow
+
     // Immediate Arithmetic (I-Format)
     Addi { rd: usize, rs1: usize, imm: i32 },
 
+    // Tritwise Logic (R-Format)
+    Min { rd: usize, rs1: usize, rs2: usize }, // AND analogue
+    Max { rd: usize, rs1: usize, rs2: usize }, // OR analogue
+    Cons { rd: usize, rs1: usize, rs2: usize }, // Consensus
+    Amin { rd: usize, rs1: usize, rs2: usize }, // Antimin (NAND analogue)
+    Tsum { rd: usize, rs1: usize, rs2: usize }, // Sum without carry (XOR analogue)
+    Neg { rd: usize, rs1: usize },             // Tritwise inversion
+
+    // Tritwise Logic (I-Format), trits above the 13-trit immediate are zero
+    Mini { rd: usize, rs1: usize, imm: i32 },
+    Maxi { rd: usize, rs1: usize, imm: i32 },
+    Consi { rd: usize, rs1: usize, imm: i32 },
+    Amini { rd: usize, rs1: usize, imm: i32 },
+    Tsumi { rd: usize, rs1: usize, imm: i32 },
+
+    // Trit Shifts and Rotates (R-Format), count taken from rs2
+    Shl { rd: usize, rs1: usize, rs2: usize },
+    Shr { rd: usize, rs1: usize, rs2: usize },
+    Rol { rd: usize, rs1: usize, rs2: usize },
+    Ror { rd: usize, rs1: usize, rs2: usize },
+
+    // Trit Shifts and Rotates (I-Format)
+    Shli { rd: usize, rs1: usize, imm: i32 },
+    Shri { rd: usize, rs1: usize, imm: i32 },
+    Roli { rd: usize, rs1: usize, imm: i32 },
+    Rori { rd: usize, rs1: usize, imm: i32 },
+
+    // Multiply and Divide (R-Format)
+    Mul { rd: usize, rs1: usize, rs2: usize },
+    Mulh { rd: usize, rs1: usize, rs2: usize },
+    Div { rd: usize, rs1: usize, rs2: usize },
+    Rem { rd: usize, rs1: usize, rs2: usize },
+
     // Memory
     Lw { rd: usize, rs1: usize, imm: i32 },
     Sw { rs1: usize, rs2: usize, imm: i32 },
 
     // Branching & Jumping
     Beq { rs1: usize, rs2: usize, imm: i32 }, // Branch if Equal
+    Bne { rs1: usize, rs2: usize, imm: i32 }, // Branch if Not Equal
+    Blt { rs1: usize, rs2: usize, imm: i32 }, // Branch if Less Than
+    Bge { rs1: usize, rs2: usize, imm: i32 }, // Branch if Greater or Equal
+    Bltm { rs1: usize, rs2: usize, imm: i32 }, // Branch if |rs1| < |rs2|
+    Bgem { rs1: usize, rs2: usize, imm: i32 }, // Branch if |rs1| >= |rs2|
+    Bsgn { rs1: usize, neg: i32, pos: i32 },  // Three-way on sign of rs1, zero falls through
     Jal { rd: usize, imm: i32 },              // Jump and Link
+    Jalr { rd: usize, rs1: usize, imm: i32 }, // Jump to rs1 + imm and Link
 
     // Upper Immediate
     Lui { rd: usize, imm: i32 },
-    // NOP / Invalid
+    // Control and Status Registers: rd = old CSR, CSR = f(old, rs1)
+    Csrrw { rd: usize, rs1: usize, csr: i32 }, // Swap in rs1
+    Csrrs { rd: usize, rs1: usize, csr: i32 }, // Tritwise MAX with rs1 (set)
+    Csrrc { rd: usize, rs1: usize, csr: i32 }, // Tritwise MIN with rs1 (clear)
+
+    // System
+    Halt, // Stop the processor
+    Tret, // Return from trap handler
+    Ei,   // Enable interrupts
+    Di,   // Disable interrupts
+    Iret, // Return from interrupt handler, re-enabling interrupts
     Nop,
 }
 
@@ -32,7 +90,36 @@ pub struct ControlSignals {
     pub mem_write: bool,
     pub mem_to_reg: bool,
     pub branch: bool,
+    pub branch_condition: BranchCondition,
     pub jump: bool,
+    /// Jump to the ALU result instead of PC + immediate
+    pub jump_register: bool,
+    pub halt: bool,
+    pub trap_return: bool,
+    pub enable_interrupts: bool,
+    pub disable_interrupts: bool,
+    pub interrupt_return: bool,
+    /// Read the CSR named by the immediate into rd and write back the ALU
+    /// result of (CSR, rs1)
+    pub csr: bool,
+    /// Latch the ALU flags into the status register
+    pub set_status: bool,
+    /// Feed the status register's carry trit into the ALU
+    pub carry_in: bool,
+    /// Raise `TrapCause::Overflow` instead of writing an overflowed result
+    pub trap_on_overflow: bool,
+}
+
+/// What a branch tests, read from the ALU flags after the comparison
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BranchCondition {
+    #[default]
+    Equal,
+    NotEqual,
+    Less,
+    GreaterOrEqual,
+    /// Target chosen by the sign flag; see `Instruction::Bsgn`
+    Sign,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -40,6 +127,31 @@ pub enum AluOp {
     Add,
     Sub,
     PassB,
+    Min,
+    Max,
+    Consensus,
+    Antimin,
+    Sum,
+    /// Tritwise inversion of input A
+    Neg,
+    /// Shift A towards the most significant trit by B trits (multiply by 3^B)
+    ShiftLeft,
+    /// Shift A towards the least significant trit by B trits (divide by 3^B, truncating toward zero)
+    ShiftRight,
+    RotateLeft,
+    RotateRight,
+    /// B shifted into the upper half of the word, for LUI
+    LoadUpper,
+    /// Low 27 trits of A * B
+    Mul,
+    /// High 27 trits of the 54-trit product A * B
+    MulHigh,
+    /// A / B rounded to the nearest integer
+    Div,
+    /// A - B * (A / B), so that |remainder| <= |B| / 2
+    Rem,
+    /// |A| - |B|, the balanced-ternary counterpart of an unsigned comparison
+    SubMagnitude,
     #[default]
     None,
 }
@@ -57,11 +169,72 @@ impl Instruction {
                 signals.alu_op = AluOp::Add;
                 signals.reg_write = true;
                 signals.alu_src = false; // Use Register rs2
+                signals.set_status = true;
             }
             Sub { .. } => {
                 signals.alu_op = AluOp::Sub;
                 signals.reg_write = true;
                 signals.alu_src = false;
+                signals.set_status = true;
+            }
+
+            // --- R-Type: Carry and Overflow-Checked Arithmetic ---
+            Addc { .. } | Subb { .. } | Addv { .. } | Subv { .. } => {
+                signals.alu_op = match self {
+                    Addc { .. } | Addv { .. } => AluOp::Add,
+                    _ => AluOp::Sub,
+                };
+                signals.reg_write = true;
+                signals.alu_src = false;
+                signals.set_status = true;
+                signals.carry_in = matches!(self, Addc { .. } | Subb { .. });
+                signals.trap_on_overflow = matches!(self, Addv { .. } | Subv { .. });
+            }
+
+            // --- R-Type: Tritwise Logic ---
+            Min { .. } | Max { .. } | Cons { .. } | Amin { .. } | Tsum { .. } | Neg { .. } => {
+                signals.alu_op = self.logic_op();
+                signals.reg_write = true;
+                signals.alu_src = false;
+            }
+
+            // --- I-Type: Tritwise Logic ---
+            Mini { imm, .. }
+            | Maxi { imm, .. }
+            | Consi { imm, .. }
+            | Amini { imm, .. }
+            | Tsumi { imm, .. } => {
+                signals.alu_op = self.logic_op();
+                signals.reg_write = true;
+                signals.alu_src = true;
+                immediate = *imm;
+            }
+
+            // --- R-Type: Shifts and Rotates ---
+            Shl { .. } | Shr { .. } | Rol { .. } | Ror { .. } => {
+                signals.alu_op = self.shift_op();
+                signals.reg_write = true;
+                signals.alu_src = false;
+            }
+
+            // --- R-Type: Multiply and Divide ---
+            Mul { .. } | Mulh { .. } | Div { .. } | Rem { .. } => {
+                signals.alu_op = match self {
+                    Mul { .. } => AluOp::Mul,
+                    Mulh { .. } => AluOp::MulHigh,
+                    Div { .. } => AluOp::Div,
+                    _ => AluOp::Rem,
+                };
+                signals.reg_write = true;
+                signals.alu_src = false;
+            }
+
+            // --- I-Type: Shifts and Rotates ---
+            Shli { imm, .. } | Shri { imm, .. } | Roli { imm, .. } | Rori { imm, .. } => {
+                signals.alu_op = self.shift_op();
+                signals.reg_write = true;
+                signals.alu_src = true;
+                immediate = *imm;
             }
 
             // --- I-Type: ADDI ---
@@ -69,6 +242,7 @@ impl Instruction {
                 signals.alu_op = AluOp::Add;
                 signals.reg_write = true;
                 signals.alu_src = true; // Use Immediate
+                signals.set_status = true;
                 immediate = *imm;
             }
 
@@ -97,6 +271,31 @@ impl Instruction {
                 signals.alu_src = false; // Compare two registers
                 immediate = *imm;
             }
+            Bne { imm, .. } | Blt { imm, .. } | Bge { imm, .. } => {
+                signals.alu_op = AluOp::Sub;
+                signals.branch = true;
+                signals.branch_condition = match self {
+                    Bne { .. } => BranchCondition::NotEqual,
+                    Blt { .. } => BranchCondition::Less,
+                    _ => BranchCondition::GreaterOrEqual,
+                };
+                immediate = *imm;
+            }
+            Bltm { imm, .. } | Bgem { imm, .. } => {
+                signals.alu_op = AluOp::SubMagnitude;
+                signals.branch = true;
+                signals.branch_condition = match self {
+                    Bltm { .. } => BranchCondition::Less,
+                    _ => BranchCondition::GreaterOrEqual,
+                };
+                immediate = *imm;
+            }
+            Bsgn { neg, pos, .. } => {
+                signals.alu_op = AluOp::Sub; // rs1 - x0
+                signals.branch = true;
+                signals.branch_condition = BranchCondition::Sign;
+                immediate = pack_sign_offsets(*neg, *pos);
+            }
 
             // --- Jump: JAL ---
             Jal { imm, .. } => {
@@ -107,62 +306,317 @@ impl Instruction {
                 immediate = *imm;
             }
 
+            // --- Jump: JALR ---
+            Jalr { imm, .. } => {
+                signals.alu_op = AluOp::Add; // Target = rs1 + imm
+                signals.alu_src = true;
+                signals.jump = true;
+                signals.jump_register = true;
+                signals.reg_write = true; // Save PC+1 to rd
+                immediate = *imm;
+            }
+
             // --- Upper Immediate: LUI ---
             Lui { imm, .. } => {
-                signals.alu_op = AluOp::PassB; // Pass immediate through ALU
+                signals.alu_op = AluOp::LoadUpper; // ALU shifts the immediate up by UPPER_SHIFT
                 signals.reg_write = true;
                 signals.alu_src = true;
-                immediate = *imm; // Ensure this is shifted correctly (<< 12) beforehand or here
+                immediate = *imm;
             }
 
+            // --- CSR: ALU combines the old value (A) with rs1 (B) ---
+            Csrrw { csr, .. } | Csrrs { csr, .. } | Csrrc { csr, .. } => {
+                signals.alu_op = match self {
+                    Csrrw { .. } => AluOp::PassB,
+                    Csrrs { .. } => AluOp::Max,
+                    _ => AluOp::Min,
+                };
+                signals.csr = true;
+                immediate = *csr;
+            }
+
+            // --- System: HALT ---
+            Halt => {
+                signals.halt = true;
+            }
+
+            // --- System: TRET ---
+            Tret => {
+                signals.trap_return = true;
+            }
+
+            // --- System: interrupts ---
+            Ei => signals.enable_interrupts = true,
+            Di => signals.disable_interrupts = true,
+            Iret => signals.interrupt_return = true,
+
             Nop => {}
         }
 
         (signals, immediate)
     }
+
+    /// ALU operation of a tritwise logic instruction
+    fn logic_op(&self) -> AluOp {
+        use Instruction::*;
+
+        match self {
+            Min { .. } | Mini { .. } => AluOp::Min,
+            Max { .. } | Maxi { .. } => AluOp::Max,
+            Cons { .. } | Consi { .. } => AluOp::Consensus,
+            Amin { .. } | Amini { .. } => AluOp::Antimin,
+            Tsum { .. } | Tsumi { .. } => AluOp::Sum,
+            Neg { .. } => AluOp::Neg,
+            _ => AluOp::None,
+        }
+    }
+
+    /// ALU operation of a shift or rotate instruction
+    fn shift_op(&self) -> AluOp {
+        use Instruction::*;
+
+        match self {
+            Shl { .. } | Shli { .. } => AluOp::ShiftLeft,
+            Shr { .. } | Shri { .. } => AluOp::ShiftRight,
+            Rol { .. } | Roli { .. } => AluOp::RotateLeft,
+            Ror { .. } | Rori { .. } => AluOp::RotateRight,
+            _ => AluOp::None,
+        }
+    }
 }
 
 // Constants for Opcode Mapping
-const OP_ADD: i128 = 1;
-const OP_SUB: i128 = 2;
-const OP_ADDI: i128 = 3;
-const OP_LW: i128 = 4;
-const OP_SW: i128 = 5;
-const OP_BEQ: i128 = 6;
-const OP_JAL: i128 = 7;
-const OP_LUI: i128 = 8;
-// const OP_HALT: i128 = 0; // standard zero is usually NOP or HALT
+pub(crate) const OP_ADD: i128 = 1;
+pub(crate) const OP_SUB: i128 = 2;
+pub(crate) const OP_ADDI: i128 = 3;
+pub(crate) const OP_LW: i128 = 4;
+pub(crate) const OP_SW: i128 = 5;
+pub(crate) const OP_BEQ: i128 = 6;
+pub(crate) const OP_JAL: i128 = 7;
+pub(crate) const OP_LUI: i128 = 8;
+pub(crate) const OP_NOP: i128 = 9;
+pub(crate) const OP_TRET: i128 = 10;
+pub(crate) const OP_MIN: i128 = 11;
+pub(crate) const OP_MAX: i128 = 12;
+pub(crate) const OP_CONS: i128 = 13;
+pub(crate) const OP_AMIN: i128 = 14;
+pub(crate) const OP_TSUM: i128 = 15;
+pub(crate) const OP_NEG: i128 = 16;
+pub(crate) const OP_MINI: i128 = 17;
+pub(crate) const OP_MAXI: i128 = 18;
+pub(crate) const OP_CONSI: i128 = 19;
+pub(crate) const OP_AMINI: i128 = 20;
+pub(crate) const OP_TSUMI: i128 = 21;
+pub(crate) const OP_SHL: i128 = 22;
+pub(crate) const OP_SHR: i128 = 23;
+pub(crate) const OP_ROL: i128 = 24;
+pub(crate) const OP_ROR: i128 = 25;
+pub(crate) const OP_SHLI: i128 = 26;
+pub(crate) const OP_SHRI: i128 = 27;
+pub(crate) const OP_ROLI: i128 = 28;
+pub(crate) const OP_RORI: i128 = 29;
+pub(crate) const OP_MUL: i128 = 30;
+pub(crate) const OP_MULH: i128 = 31;
+pub(crate) const OP_DIV: i128 = 32;
+pub(crate) const OP_REM: i128 = 33;
+pub(crate) const OP_BNE: i128 = 34;
+pub(crate) const OP_BLT: i128 = 35;
+pub(crate) const OP_BGE: i128 = 36;
+pub(crate) const OP_BLTM: i128 = 37;
+pub(crate) const OP_BGEM: i128 = 38;
+pub(crate) const OP_BSGN: i128 = 39;
+pub(crate) const OP_JALR: i128 = 40;
+pub(crate) const OP_ADDC: i128 = 41;
+pub(crate) const OP_SUBB: i128 = 42;
+pub(crate) const OP_ADDV: i128 = 43;
+pub(crate) const OP_SUBV: i128 = 44;
+pub(crate) const OP_EI: i128 = 45;
+pub(crate) const OP_DI: i128 = 46;
+pub(crate) const OP_IRET: i128 = 47;
+pub(crate) const OP_CSRRW: i128 = 48;
+pub(crate) const OP_CSRRS: i128 = 49;
+pub(crate) const OP_CSRRC: i128 = 50;
+// Zeroed memory halts instead of sliding through as NOPs
+pub(crate) const OP_HALT: i128 = 0;
+
+/// Number of general purpose registers a 3-trit register field can address
+pub const REGISTER_COUNT: usize = 27;
+/// Largest magnitude the 13-trit immediate field can hold: (3^13 - 1) / 2
+pub const IMM_MAX: i32 = 797_161;
+/// Largest offsets of BSGN, which splits its immediate into a 6-trit
+/// negative-target offset and a 7-trit positive-target offset
+pub const SIGN_BRANCH_NEG_MAX: i32 = 364;
+pub const SIGN_BRANCH_POS_MAX: i32 = 1093;
+/// Trits LUI shifts its immediate by, so that LUI + ADDI can build any 26-trit value
+pub const UPPER_SHIFT: usize = 13;
 
 impl From<Tryte> for Instruction {
+    /// Lenient decoding: unknown opcodes map to NOP.
+    /// Use `Instruction::from_machine_code` to have them reported instead.
     fn from(machine_code: Tryte) -> Self {
+        Instruction::from_machine_code(machine_code).unwrap_or(Instruction::Nop)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The low five trits do not name any instruction
+    UnknownOpcode(i128),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Instruction {
+    /// Strict decoding of a machine code word
+    pub fn from_machine_code(machine_code: Tryte) -> Result<Self, DecodeError> {
         let opcode = extract_value(&machine_code, 0, 5);
-        let rd = extract_value(&machine_code, 5, 8) as usize;
-        let rs1 = extract_value(&machine_code, 8, 11) as usize;
-        let rs2 = extract_value(&machine_code, 11, 14) as usize;
+        let rd = register_index(extract_value(&machine_code, 5, 8));
+        let rs1 = register_index(extract_value(&machine_code, 8, 11));
+        let rs2 = register_index(extract_value(&machine_code, 11, 14));
 
         // Immediate covers the upper part.
         let imm_long = extract_value(&machine_code, 14, 27);
         let imm = imm_long as i32;
 
         // Match Opcode to Instruction Variant
-        match opcode {
+        let instruction = match opcode {
+            OP_HALT => Instruction::Halt,
+            OP_NOP => Instruction::Nop,
+            OP_TRET => Instruction::Tret,
+            OP_EI => Instruction::Ei,
+            OP_DI => Instruction::Di,
+            OP_IRET => Instruction::Iret,
+
             OP_ADD => Instruction::Add { rd, rs1, rs2 },
             OP_SUB => Instruction::Sub { rd, rs1, rs2 },
 
             OP_ADDI => Instruction::Addi { rd, rs1, imm },
 
+            OP_MIN => Instruction::Min { rd, rs1, rs2 },
+            OP_MAX => Instruction::Max { rd, rs1, rs2 },
+            OP_CONS => Instruction::Cons { rd, rs1, rs2 },
+            OP_AMIN => Instruction::Amin { rd, rs1, rs2 },
+            OP_TSUM => Instruction::Tsum { rd, rs1, rs2 },
+            OP_NEG => Instruction::Neg { rd, rs1 },
+
+            OP_MINI => Instruction::Mini { rd, rs1, imm },
+            OP_MAXI => Instruction::Maxi { rd, rs1, imm },
+            OP_CONSI => Instruction::Consi { rd, rs1, imm },
+            OP_AMINI => Instruction::Amini { rd, rs1, imm },
+            OP_TSUMI => Instruction::Tsumi { rd, rs1, imm },
+
+            OP_SHL => Instruction::Shl { rd, rs1, rs2 },
+            OP_SHR => Instruction::Shr { rd, rs1, rs2 },
+            OP_ROL => Instruction::Rol { rd, rs1, rs2 },
+            OP_ROR => Instruction::Ror { rd, rs1, rs2 },
+            OP_SHLI => Instruction::Shli { rd, rs1, imm },
+            OP_SHRI => Instruction::Shri { rd, rs1, imm },
+            OP_ROLI => Instruction::Roli { rd, rs1, imm },
+            OP_RORI => Instruction::Rori { rd, rs1, imm },
+
+            OP_MUL => Instruction::Mul { rd, rs1, rs2 },
+            OP_MULH => Instruction::Mulh { rd, rs1, rs2 },
+            OP_DIV => Instruction::Div { rd, rs1, rs2 },
+            OP_REM => Instruction::Rem { rd, rs1, rs2 },
+
+            OP_ADDC => Instruction::Addc { rd, rs1, rs2 },
+            OP_SUBB => Instruction::Subb { rd, rs1, rs2 },
+            OP_ADDV => Instruction::Addv { rd, rs1, rs2 },
+            OP_SUBV => Instruction::Subv { rd, rs1, rs2 },
+
+            OP_CSRRW => Instruction::Csrrw { rd, rs1, csr: imm },
+            OP_CSRRS => Instruction::Csrrs { rd, rs1, csr: imm },
+            OP_CSRRC => Instruction::Csrrc { rd, rs1, csr: imm },
+
             // For Stores, 'rd' is essentially irrelevant in destination logic,
             // but standard encoding often keeps the field layout consistent.
             OP_LW => Instruction::Lw { rd, rs1, imm },
             OP_SW => Instruction::Sw { rs1, rs2, imm },
 
             OP_BEQ => Instruction::Beq { rs1, rs2, imm },
+            OP_BNE => Instruction::Bne { rs1, rs2, imm },
+            OP_BLT => Instruction::Blt { rs1, rs2, imm },
+            OP_BGE => Instruction::Bge { rs1, rs2, imm },
+            OP_BLTM => Instruction::Bltm { rs1, rs2, imm },
+            OP_BGEM => Instruction::Bgem { rs1, rs2, imm },
+            OP_BSGN => {
+                let (neg, pos) = split_sign_offsets(imm);
+                Instruction::Bsgn { rs1, neg, pos }
+            }
 
             OP_JAL => Instruction::Jal { rd, imm },
+            OP_JALR => Instruction::Jalr { rd, rs1, imm },
 
             OP_LUI => Instruction::Lui { rd, imm },
 
-            _ => Instruction::Nop, // Unknown opcode maps to NOP
+            _ => return Err(DecodeError::UnknownOpcode(opcode)),
+        };
+
+        Ok(instruction)
+    }
+
+    /// Assembly mnemonic of the instruction, upper case
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add { .. } => "ADD",
+            Instruction::Sub { .. } => "SUB",
+            Instruction::Addi { .. } => "ADDI",
+            Instruction::Min { .. } => "MIN",
+            Instruction::Max { .. } => "MAX",
+            Instruction::Cons { .. } => "CONS",
+            Instruction::Amin { .. } => "AMIN",
+            Instruction::Tsum { .. } => "TSUM",
+            Instruction::Neg { .. } => "NEG",
+            Instruction::Mini { .. } => "MINI",
+            Instruction::Maxi { .. } => "MAXI",
+            Instruction::Consi { .. } => "CONSI",
+            Instruction::Amini { .. } => "AMINI",
+            Instruction::Tsumi { .. } => "TSUMI",
+            Instruction::Shl { .. } => "SHL",
+            Instruction::Shr { .. } => "SHR",
+            Instruction::Rol { .. } => "ROL",
+            Instruction::Ror { .. } => "ROR",
+            Instruction::Shli { .. } => "SHLI",
+            Instruction::Shri { .. } => "SHRI",
+            Instruction::Roli { .. } => "ROLI",
+            Instruction::Rori { .. } => "RORI",
+            Instruction::Mul { .. } => "MUL",
+            Instruction::Mulh { .. } => "MULH",
+            Instruction::Div { .. } => "DIV",
+            Instruction::Rem { .. } => "REM",
+            Instruction::Addc { .. } => "ADDC",
+            Instruction::Subb { .. } => "SUBB",
+            Instruction::Addv { .. } => "ADDV",
+            Instruction::Subv { .. } => "SUBV",
+            Instruction::Lw { .. } => "LW",
+            Instruction::Sw { .. } => "SW",
+            Instruction::Beq { .. } => "BEQ",
+            Instruction::Bne { .. } => "BNE",
+            Instruction::Blt { .. } => "BLT",
+            Instruction::Bge { .. } => "BGE",
+            Instruction::Bltm { .. } => "BLTM",
+            Instruction::Bgem { .. } => "BGEM",
+            Instruction::Bsgn { .. } => "BSGN",
+            Instruction::Jal { .. } => "JAL",
+            Instruction::Jalr { .. } => "JALR",
+            Instruction::Lui { .. } => "LUI",
+            Instruction::Csrrw { .. } => "CSRRW",
+            Instruction::Csrrs { .. } => "CSRRS",
+            Instruction::Csrrc { .. } => "CSRRC",
+            Instruction::Halt => "HALT",
+            Instruction::Tret => "TRET",
+            Instruction::Ei => "EI",
+            Instruction::Di => "DI",
+            Instruction::Iret => "IRET",
+            Instruction::Nop => "NOP",
         }
     }
 }
@@ -176,7 +630,7 @@ fn extract_value(tryte: &Tryte, start: usize, end: usize) -> i128 {
             break;
         }
 
-        let trit_val = tryte.0[i].to_i8() as i128;
+        let trit_val = tryte.trit(i).to_i8() as i128;
         value += trit_val * power;
         power *= 3;
     }
@@ -184,15 +638,208 @@ fn extract_value(tryte: &Tryte, start: usize, end: usize) -> i128 {
     value
 }
 
+/// Writes `value` into trits `start..end` of `tryte` (inverse of `extract_value`).
+/// Trits that do not fit in the field are dropped, so callers must range-check first.
+pub(crate) fn insert_value(tryte: &mut Tryte, start: usize, end: usize, value: i128) {
+    let field = TritField::<27>::from_i128(value);
+
+    for (offset, i) in (start..end.min(27)).enumerate() {
+        tryte.set_trit(i, field.trit(offset));
+    }
+}
+
+/// BSGN immediate: `neg` in the low 6 trits, `pos` in the high 7
+pub(crate) fn pack_sign_offsets(neg: i32, pos: i32) -> i32 {
+    neg + pos * 729
+}
+
+/// Inverse of `pack_sign_offsets`, returns (neg, pos)
+pub(crate) fn split_sign_offsets(imm: i32) -> (i32, i32) {
+    let neg = (imm + SIGN_BRANCH_NEG_MAX).rem_euclid(729) - SIGN_BRANCH_NEG_MAX;
+    (neg, (imm - neg) / 729)
+}
+
+/// Register fields are balanced, so field values -13..=-1 name registers 14..=26.
+fn register_index(field: i128) -> usize {
+    if field < 0 {
+        (field + REGISTER_COUNT as i128) as usize
+    } else {
+        field as usize
+    }
+}
+
+/// Inverse of `register_index`
+fn register_field(index: usize) -> i128 {
+    if index > REGISTER_COUNT / 2 {
+        index as i128 - REGISTER_COUNT as i128
+    } else {
+        index as i128
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A register index is not below `REGISTER_COUNT`
+    RegisterOutOfRange { field: &'static str, index: usize },
+    /// An immediate does not fit in the 13-trit field
+    ImmediateOutOfRange { value: i32 },
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::RegisterOutOfRange { field, index } => write!(
+                f,
+                "register x{} in field {} is out of range (0..{})",
+                index, field, REGISTER_COUNT
+            ),
+            EncodeError::ImmediateOutOfRange { value } => write!(
+                f,
+                "immediate {} does not fit in 13 trits (-{}..={})",
+                value, IMM_MAX, IMM_MAX
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl Instruction {
+    /// Encodes the instruction into machine code, the inverse of `Instruction::from(Tryte)`.
+    /// Fields the instruction does not use are left zero, so the encoding is canonical.
+    pub fn encode(&self) -> Result<Tryte, EncodeError> {
+        use Instruction::*;
+
+        let (opcode, rd, rs1, rs2, imm) = match *self {
+            Add { rd, rs1, rs2 } => (OP_ADD, rd, rs1, rs2, 0),
+            Sub { rd, rs1, rs2 } => (OP_SUB, rd, rs1, rs2, 0),
+            Addi { rd, rs1, imm } => (OP_ADDI, rd, rs1, 0, imm),
+            Min { rd, rs1, rs2 } => (OP_MIN, rd, rs1, rs2, 0),
+            Max { rd, rs1, rs2 } => (OP_MAX, rd, rs1, rs2, 0),
+            Cons { rd, rs1, rs2 } => (OP_CONS, rd, rs1, rs2, 0),
+            Amin { rd, rs1, rs2 } => (OP_AMIN, rd, rs1, rs2, 0),
+            Tsum { rd, rs1, rs2 } => (OP_TSUM, rd, rs1, rs2, 0),
+            Neg { rd, rs1 } => (OP_NEG, rd, rs1, 0, 0),
+            Mini { rd, rs1, imm } => (OP_MINI, rd, rs1, 0, imm),
+            Maxi { rd, rs1, imm } => (OP_MAXI, rd, rs1, 0, imm),
+            Consi { rd, rs1, imm } => (OP_CONSI, rd, rs1, 0, imm),
+            Amini { rd, rs1, imm } => (OP_AMINI, rd, rs1, 0, imm),
+            Tsumi { rd, rs1, imm } => (OP_TSUMI, rd, rs1, 0, imm),
+            Shl { rd, rs1, rs2 } => (OP_SHL, rd, rs1, rs2, 0),
+            Shr { rd, rs1, rs2 } => (OP_SHR, rd, rs1, rs2, 0),
+            Rol { rd, rs1, rs2 } => (OP_ROL, rd, rs1, rs2, 0),
+            Ror { rd, rs1, rs2 } => (OP_ROR, rd, rs1, rs2, 0),
+            Shli { rd, rs1, imm } => (OP_SHLI, rd, rs1, 0, imm),
+            Shri { rd, rs1, imm } => (OP_SHRI, rd, rs1, 0, imm),
+            Roli { rd, rs1, imm } => (OP_ROLI, rd, rs1, 0, imm),
+            Rori { rd, rs1, imm } => (OP_RORI, rd, rs1, 0, imm),
+            Mul { rd, rs1, rs2 } => (OP_MUL, rd, rs1, rs2, 0),
+            Mulh { rd, rs1, rs2 } => (OP_MULH, rd, rs1, rs2, 0),
+            Div { rd, rs1, rs2 } => (OP_DIV, rd, rs1, rs2, 0),
+            Rem { rd, rs1, rs2 } => (OP_REM, rd, rs1, rs2, 0),
+            Addc { rd, rs1, rs2 } => (OP_ADDC, rd, rs1, rs2, 0),
+            Subb { rd, rs1, rs2 } => (OP_SUBB, rd, rs1, rs2, 0),
+            Addv { rd, rs1, rs2 } => (OP_ADDV, rd, rs1, rs2, 0),
+            Subv { rd, rs1, rs2 } => (OP_SUBV, rd, rs1, rs2, 0),
+            Lw { rd, rs1, imm } => (OP_LW, rd, rs1, 0, imm),
+            Sw { rs1, rs2, imm } => (OP_SW, 0, rs1, rs2, imm),
+            Beq { rs1, rs2, imm } => (OP_BEQ, 0, rs1, rs2, imm),
+            Bne { rs1, rs2, imm } => (OP_BNE, 0, rs1, rs2, imm),
+            Blt { rs1, rs2, imm } => (OP_BLT, 0, rs1, rs2, imm),
+            Bge { rs1, rs2, imm } => (OP_BGE, 0, rs1, rs2, imm),
+            Bltm { rs1, rs2, imm } => (OP_BLTM, 0, rs1, rs2, imm),
+            Bgem { rs1, rs2, imm } => (OP_BGEM, 0, rs1, rs2, imm),
+            Bsgn { rs1, neg, pos } => {
+                if !(-SIGN_BRANCH_NEG_MAX..=SIGN_BRANCH_NEG_MAX).contains(&neg) {
+                    return Err(EncodeError::ImmediateOutOfRange { value: neg });
+                }
+                if !(-SIGN_BRANCH_POS_MAX..=SIGN_BRANCH_POS_MAX).contains(&pos) {
+                    return Err(EncodeError::ImmediateOutOfRange { value: pos });
+                }
+                (OP_BSGN, 0, rs1, 0, pack_sign_offsets(neg, pos))
+            }
+            Jal { rd, imm } => (OP_JAL, rd, 0, 0, imm),
+            Jalr { rd, rs1, imm } => (OP_JALR, rd, rs1, 0, imm),
+            Lui { rd, imm } => (OP_LUI, rd, 0, 0, imm),
+            Csrrw { rd, rs1, csr } => (OP_CSRRW, rd, rs1, 0, csr),
+            Csrrs { rd, rs1, csr } => (OP_CSRRS, rd, rs1, 0, csr),
+            Csrrc { rd, rs1, csr } => (OP_CSRRC, rd, rs1, 0, csr),
+            Halt => (OP_HALT, 0, 0, 0, 0),
+            Tret => (OP_TRET, 0, 0, 0, 0),
+            Ei => (OP_EI, 0, 0, 0, 0),
+            Di => (OP_DI, 0, 0, 0, 0),
+            Iret => (OP_IRET, 0, 0, 0, 0),
+            Nop => (OP_NOP, 0, 0, 0, 0),
+        };
+
+        for (field, index) in [("rd", rd), ("rs1", rs1), ("rs2", rs2)] {
+            if index >= REGISTER_COUNT {
+                return Err(EncodeError::RegisterOutOfRange { field, index });
+            }
+        }
+
+        if !(-IMM_MAX..=IMM_MAX).contains(&imm) {
+            return Err(EncodeError::ImmediateOutOfRange { value: imm });
+        }
+
+        // Layout: [Op:0..5] [Rd:5..8] [Rs1:8..11] [Rs2:11..14] [Imm:14..27]
+        let mut machine_code = Tryte::default();
+        insert_value(&mut machine_code, 0, 5, opcode);
+        insert_value(&mut machine_code, 5, 8, register_field(rd));
+        insert_value(&mut machine_code, 8, 11, register_field(rs1));
+        insert_value(&mut machine_code, 11, 14, register_field(rs2));
+        insert_value(&mut machine_code, 14, 27, imm as i128);
+
+        Ok(machine_code)
+    }
+}
+
 impl Instruction {
     pub fn rs1(&self) -> usize {
         match self {
             Instruction::Add { rs1, .. }
             | Instruction::Sub { rs1, .. }
             | Instruction::Addi { rs1, .. }
+            | Instruction::Min { rs1, .. }
+            | Instruction::Max { rs1, .. }
+            | Instruction::Cons { rs1, .. }
+            | Instruction::Amin { rs1, .. }
+            | Instruction::Tsum { rs1, .. }
+            | Instruction::Neg { rs1, .. }
+            | Instruction::Mini { rs1, .. }
+            | Instruction::Maxi { rs1, .. }
+            | Instruction::Consi { rs1, .. }
+            | Instruction::Amini { rs1, .. }
+            | Instruction::Tsumi { rs1, .. }
+            | Instruction::Shl { rs1, .. }
+            | Instruction::Shr { rs1, .. }
+            | Instruction::Rol { rs1, .. }
+            | Instruction::Ror { rs1, .. }
+            | Instruction::Shli { rs1, .. }
+            | Instruction::Shri { rs1, .. }
+            | Instruction::Roli { rs1, .. }
+            | Instruction::Rori { rs1, .. }
+            | Instruction::Mul { rs1, .. }
+            | Instruction::Mulh { rs1, .. }
+            | Instruction::Div { rs1, .. }
+            | Instruction::Rem { rs1, .. }
+            | Instruction::Addc { rs1, .. }
+            | Instruction::Subb { rs1, .. }
+            | Instruction::Addv { rs1, .. }
+            | Instruction::Subv { rs1, .. }
             | Instruction::Lw { rs1, .. }
             | Instruction::Sw { rs1, .. }
-            | Instruction::Beq { rs1, .. } => *rs1,
+            | Instruction::Beq { rs1, .. }
+            | Instruction::Bne { rs1, .. }
+            | Instruction::Blt { rs1, .. }
+            | Instruction::Bge { rs1, .. }
+            | Instruction::Bltm { rs1, .. }
+            | Instruction::Bgem { rs1, .. }
+            | Instruction::Bsgn { rs1, .. }
+            | Instruction::Jalr { rs1, .. }
+            | Instruction::Csrrw { rs1, .. }
+            | Instruction::Csrrs { rs1, .. }
+            | Instruction::Csrrc { rs1, .. } => *rs1,
             _ => 0,
         }
     }
@@ -201,8 +848,30 @@ impl Instruction {
         match self {
             Instruction::Add { rs2, .. }
             | Instruction::Sub { rs2, .. }
+            | Instruction::Min { rs2, .. }
+            | Instruction::Max { rs2, .. }
+            | Instruction::Cons { rs2, .. }
+            | Instruction::Amin { rs2, .. }
+            | Instruction::Tsum { rs2, .. }
+            | Instruction::Shl { rs2, .. }
+            | Instruction::Shr { rs2, .. }
+            | Instruction::Rol { rs2, .. }
+            | Instruction::Ror { rs2, .. }
+            | Instruction::Mul { rs2, .. }
+            | Instruction::Mulh { rs2, .. }
+            | Instruction::Div { rs2, .. }
+            | Instruction::Rem { rs2, .. }
+            | Instruction::Addc { rs2, .. }
+            | Instruction::Subb { rs2, .. }
+            | Instruction::Addv { rs2, .. }
+            | Instruction::Subv { rs2, .. }
             | Instruction::Sw { rs2, .. }
-            | Instruction::Beq { rs2, .. } => *rs2,
+            | Instruction::Beq { rs2, .. }
+            | Instruction::Bne { rs2, .. }
+            | Instruction::Blt { rs2, .. }
+            | Instruction::Bge { rs2, .. }
+            | Instruction::Bltm { rs2, .. }
+            | Instruction::Bgem { rs2, .. } => *rs2,
             _ => 0,
         }
     }
@@ -212,10 +881,274 @@ impl Instruction {
             Instruction::Add { rd, .. }
             | Instruction::Sub { rd, .. }
             | Instruction::Addi { rd, .. }
+            | Instruction::Min { rd, .. }
+            | Instruction::Max { rd, .. }
+            | Instruction::Cons { rd, .. }
+            | Instruction::Amin { rd, .. }
+            | Instruction::Tsum { rd, .. }
+            | Instruction::Neg { rd, .. }
+            | Instruction::Mini { rd, .. }
+            | Instruction::Maxi { rd, .. }
+            | Instruction::Consi { rd, .. }
+            | Instruction::Amini { rd, .. }
+            | Instruction::Tsumi { rd, .. }
+            | Instruction::Shl { rd, .. }
+            | Instruction::Shr { rd, .. }
+            | Instruction::Rol { rd, .. }
+            | Instruction::Ror { rd, .. }
+            | Instruction::Shli { rd, .. }
+            | Instruction::Shri { rd, .. }
+            | Instruction::Roli { rd, .. }
+            | Instruction::Rori { rd, .. }
+            | Instruction::Mul { rd, .. }
+            | Instruction::Mulh { rd, .. }
+            | Instruction::Div { rd, .. }
+            | Instruction::Rem { rd, .. }
+            | Instruction::Addc { rd, .. }
+            | Instruction::Subb { rd, .. }
+            | Instruction::Addv { rd, .. }
+            | Instruction::Subv { rd, .. }
             | Instruction::Lw { rd, .. }
             | Instruction::Jal { rd, .. }
+            | Instruction::Jalr { rd, .. }
+            | Instruction::Csrrw { rd, .. }
+            | Instruction::Csrrs { rd, .. }
+            | Instruction::Csrrc { rd, .. }
             | Instruction::Lui { rd, .. } => *rd,
             _ => 0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::Rng;
+
+    fn random_register(rng: &mut Rng) -> usize {
+        rng.below(REGISTER_COUNT as u64) as usize
+    }
+
+    fn random_immediate(rng: &mut Rng) -> i32 {
+        rng.below(2 * IMM_MAX as u64 + 1) as i32 - IMM_MAX
+    }
+
+    fn random_instruction(rng: &mut Rng) -> Instruction {
+        use Instruction::*;
+
+        let (rd, rs1, rs2, imm) = (
+            random_register(rng),
+            random_register(rng),
+            random_register(rng),
+            random_immediate(rng),
+        );
+        let all = [
+            Add { rd, rs1, rs2 },
+            Sub { rd, rs1, rs2 },
+            Addi { rd, rs1, imm },
+            Min { rd, rs1, rs2 },
+            Max { rd, rs1, rs2 },
+            Cons { rd, rs1, rs2 },
+            Amin { rd, rs1, rs2 },
+            Tsum { rd, rs1, rs2 },
+            Neg { rd, rs1 },
+            Mini { rd, rs1, imm },
+            Maxi { rd, rs1, imm },
+            Consi { rd, rs1, imm },
+            Amini { rd, rs1, imm },
+            Tsumi { rd, rs1, imm },
+            Shl { rd, rs1, rs2 },
+            Shr { rd, rs1, rs2 },
+            Rol { rd, rs1, rs2 },
+            Ror { rd, rs1, rs2 },
+            Shli { rd, rs1, imm },
+            Shri { rd, rs1, imm },
+            Roli { rd, rs1, imm },
+            Rori { rd, rs1, imm },
+            Mul { rd, rs1, rs2 },
+            Mulh { rd, rs1, rs2 },
+            Div { rd, rs1, rs2 },
+            Rem { rd, rs1, rs2 },
+            Addc { rd, rs1, rs2 },
+            Subb { rd, rs1, rs2 },
+            Addv { rd, rs1, rs2 },
+            Subv { rd, rs1, rs2 },
+            Lw { rd, rs1, imm },
+            Sw { rs1, rs2, imm },
+            Beq { rs1, rs2, imm },
+            Bne { rs1, rs2, imm },
+            Blt { rs1, rs2, imm },
+            Bge { rs1, rs2, imm },
+            Bltm { rs1, rs2, imm },
+            Bgem { rs1, rs2, imm },
+            Bsgn {
+                rs1,
+                neg: imm % (SIGN_BRANCH_NEG_MAX + 1),
+                pos: imm % (SIGN_BRANCH_POS_MAX + 1),
+            },
+            Jal { rd, imm },
+            Jalr { rd, rs1, imm },
+            Lui { rd, imm },
+            Csrrw { rd, rs1, csr: imm },
+            Csrrs { rd, rs1, csr: imm },
+            Csrrc { rd, rs1, csr: imm },
+            Halt,
+            Tret,
+            Ei,
+            Di,
+            Iret,
+            Nop,
+        ];
+        all[rng.below(all.len() as u64) as usize]
+    }
+
+    #[test]
+    fn test_decode_encode_round_trip_random() {
+        let mut rng = Rng(0x5e7u64 << 32 | 1958);
+
+        for _ in 0..10_000 {
+            let instruction = random_instruction(&mut rng);
+            let machine_code = instruction.encode().unwrap();
+            assert_eq!(Instruction::from(machine_code), instruction);
+        }
+    }
+
+    #[test]
+    fn test_decode_encode_round_trip_field_extremes() {
+        for reg in 0..REGISTER_COUNT {
+            for imm in [-IMM_MAX, -1, 0, 1, IMM_MAX] {
+                for instruction in [
+                    Instruction::Add {
+                        rd: reg,
+                        rs1: reg,
+                        rs2: reg,
+                    },
+                    Instruction::Addi {
+                        rd: reg,
+                        rs1: reg,
+                        imm,
+                    },
+                    Instruction::Sw {
+                        rs1: reg,
+                        rs2: reg,
+                        imm,
+                    },
+                    Instruction::Jal { rd: reg, imm },
+                    Instruction::Bsgn {
+                        rs1: reg,
+                        neg: imm.clamp(-SIGN_BRANCH_NEG_MAX, SIGN_BRANCH_NEG_MAX),
+                        pos: imm.clamp(-SIGN_BRANCH_POS_MAX, SIGN_BRANCH_POS_MAX),
+                    },
+                ] {
+                    assert_eq!(
+                        Instruction::from(instruction.encode().unwrap()),
+                        instruction
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_is_canonical() {
+        assert_eq!(Instruction::Halt.encode().unwrap(), Tryte::default());
+        assert_eq!(Instruction::Nop.encode().unwrap().to_i128(), OP_NOP);
+
+        // Unused fields stay zero: only the opcode and rd trits of LUI x0, 0 are set
+        let lui = Instruction::Lui { rd: 0, imm: 0 }.encode().unwrap();
+        assert_eq!(lui.to_i128(), OP_LUI);
+    }
+
+    #[test]
+    fn test_encode_rejects_out_of_range_fields() {
+        assert_eq!(
+            Instruction::Add {
+                rd: 27,
+                rs1: 0,
+                rs2: 0
+            }
+            .encode(),
+            Err(EncodeError::RegisterOutOfRange {
+                field: "rd",
+                index: 27
+            })
+        );
+        assert_eq!(
+            Instruction::Sw {
+                rs1: 1,
+                rs2: 100,
+                imm: 0
+            }
+            .encode(),
+            Err(EncodeError::RegisterOutOfRange {
+                field: "rs2",
+                index: 100
+            })
+        );
+        assert_eq!(
+            Instruction::Beq {
+                rs1: 0,
+                rs2: 0,
+                imm: IMM_MAX + 1
+            }
+            .encode(),
+            Err(EncodeError::ImmediateOutOfRange { value: IMM_MAX + 1 })
+        );
+        assert_eq!(
+            Instruction::Lui {
+                rd: 0,
+                imm: -IMM_MAX - 1
+            }
+            .encode(),
+            Err(EncodeError::ImmediateOutOfRange {
+                value: -IMM_MAX - 1
+            })
+        );
+        assert_eq!(
+            Instruction::Bsgn {
+                rs1: 0,
+                neg: 0,
+                pos: SIGN_BRANCH_POS_MAX + 1
+            }
+            .encode(),
+            Err(EncodeError::ImmediateOutOfRange {
+                value: SIGN_BRANCH_POS_MAX + 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_strict_decode_reports_unknown_opcodes() {
+        let mut machine_code = Tryte::default();
+        insert_value(&mut machine_code, 0, 5, -42);
+
+        assert_eq!(
+            Instruction::from_machine_code(machine_code),
+            Err(DecodeError::UnknownOpcode(-42))
+        );
+        assert_eq!(Instruction::from(machine_code), Instruction::Nop);
+        assert_eq!(
+            Instruction::from_machine_code(Tryte::default()),
+            Ok(Instruction::Halt)
+        );
+    }
+
+    #[test]
+    fn test_negative_register_fields_name_upper_registers() {
+        // rd field = -1, rs1 field = -13
+        let mut machine_code = Tryte::default();
+        insert_value(&mut machine_code, 0, 5, OP_ADD);
+        insert_value(&mut machine_code, 5, 8, -1);
+        insert_value(&mut machine_code, 8, 11, -13);
+
+        assert_eq!(
+            Instruction::from(machine_code),
+            Instruction::Add {
+                rd: 26,
+                rs1: 14,
+                rs2: 0
+            }
+        );
+    }
+}
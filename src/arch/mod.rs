@@ -1,3 +1,4 @@
 pub mod circuits;
+pub mod fast_arith;
 pub mod instructions;
 pub mod trit;
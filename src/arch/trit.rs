@@ -1,4 +1,6 @@
-use std::fmt::{Display, Formatter, Result};
+use std::fmt::{Debug, Display, Formatter, Result};
+
+use crate::arch::fast_arith;
 
 // TRIT
 #[derive(PartialEq, Debug, Copy, Clone, Default, Hash, Eq)]
@@ -24,6 +26,15 @@ impl Trit {
         }
     }
 
+    /// Single character form: `T` for -1, `0` and `1`
+    pub fn to_char(self) -> char {
+        match self {
+            Trit::Negative => 'T',
+            Trit::Zero => '0',
+            Trit::Positive => '1',
+        }
+    }
+
     pub fn from_i8(val: i8) -> Self {
         match val {
             -1 => Trit::Negative,
@@ -35,18 +46,103 @@ impl Trit {
 }
 
 // TRITFIELD
-#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq)]
-pub struct TritField<const N: usize>(pub [Trit; N]);
+/// N trits packed one bit per trit into two bit planes, trit 0 (least significant)
+/// in bit 0: a trit is Positive if its bit is set in `positive`, Negative if set in
+/// `negative`, and Zero if set in neither.
+///
+/// Holds up to 64 trits; a `Tryte` takes 16 bytes instead of 27.
+#[derive(PartialEq, Copy, Clone, Hash, Eq)]
+pub struct TritField<const N: usize> {
+    positive: u64,
+    negative: u64,
+}
 
 /// 7,625,597,484,987 = 7TB
 pub type Tryte = TritField<27>;
 
+/// Largest magnitude a `Tryte` can hold: (3^27 - 1) / 2
+pub const TRYTE_MAX: i128 = 3_812_798_742_493;
+
 impl<const N: usize> TritField<N> {
+    const FITS: () = assert!(N <= 64, "a TritField holds at most 64 trits");
+
+    pub fn from_trits(trits: [Trit; N]) -> Self {
+        let mut field = Self::default();
+        for (i, trit) in trits.into_iter().enumerate() {
+            field.set_trit(i, trit);
+        }
+        field
+    }
+
+    /// One `Trit` per element, least significant first
+    pub fn to_trits(&self) -> [Trit; N] {
+        std::array::from_fn(|i| self.trit(i))
+    }
+
+    pub fn trit(&self, index: usize) -> Trit {
+        debug_assert!(index < N, "trit {} out of range for {} trits", index, N);
+        // Indexed by the positive bit plus twice the negative bit; 3 never occurs
+        const PLANES: [Trit; 4] = [Trit::Zero, Trit::Positive, Trit::Negative, Trit::Zero];
+        let positive = self.positive >> index & 1;
+        let negative = self.negative >> index & 1;
+        PLANES[(positive | negative << 1) as usize]
+    }
+
+    pub fn set_trit(&mut self, index: usize, trit: Trit) {
+        debug_assert!(index < N, "trit {} out of range for {} trits", index, N);
+        let bit = 1 << index;
+        self.positive = self.positive & !bit | ((trit == Trit::Positive) as u64) << index;
+        self.negative = self.negative & !bit | ((trit == Trit::Negative) as u64) << index;
+    }
+
+    /// Every trit, least significant first
+    pub fn trits(&self) -> impl DoubleEndedIterator<Item = Trit> + ExactSizeIterator {
+        let field = *self;
+        (0..N).map(move |i| field.trit(i))
+    }
+
+    /// The `positive` and `negative` bit planes, for word-level code
+    pub(crate) fn planes(&self) -> (u64, u64) {
+        (self.positive, self.negative)
+    }
+
+    /// Field from bit planes; bits above trit N - 1 are dropped and a bit set in
+    /// both planes reads as Positive.
+    pub(crate) fn from_planes(positive: u64, negative: u64) -> Self {
+        let mask = u64::MAX >> (64 - N);
+        Self {
+            positive: positive & mask,
+            negative: negative & !positive & mask,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.positive | self.negative == 0
+    }
+
     pub fn to_i128(&self) -> i128 {
+        if cfg!(feature = "fast-arith") {
+            fast_arith::to_i128(self)
+        } else {
+            self.value_by_trits()
+        }
+    }
+
+    /// Keeps the low N trits of `value`
+    pub fn from_i128(value: i128) -> TritField<N> {
+        if cfg!(feature = "fast-arith") {
+            fast_arith::from_i128(value)
+        } else {
+            Self::from_i128_by_trits(value)
+        }
+    }
+
+    /// Reference conversion, one power of three per trit
+    pub(crate) fn value_by_trits(&self) -> i128 {
         let mut value: i128 = 0;
         let mut power: i128 = 1;
 
-        self.0.iter().for_each(|trit| {
+        self.trits().for_each(|trit| {
             value += trit.to_i8() as i128 * power;
             power *= 3;
         });
@@ -54,7 +150,8 @@ impl<const N: usize> TritField<N> {
         value
     }
 
-    pub fn from_i128(value: i128) -> TritField<N> {
+    /// Reference conversion, one balanced digit per step
+    pub(crate) fn from_i128_by_trits(value: i128) -> TritField<N> {
         let mut result = TritField::default();
         let mut n = value;
 
@@ -75,26 +172,64 @@ impl<const N: usize> TritField<N> {
                 n -= 1;
             }
 
-            result.0[i] = match rem {
-                -1 => Trit::Negative,
-                0 => Trit::Zero,
-                1 => Trit::Positive,
-                _ => unreachable!(),
-            };
+            result.set_trit(
+                i,
+                match rem {
+                    -1 => Trit::Negative,
+                    0 => Trit::Zero,
+                    1 => Trit::Positive,
+                    _ => unreachable!(),
+                },
+            );
         }
         result
     }
 }
 
+/// Balanced heptavintimal digits for -13..=13: `N`..`Z` are negative, `A`..`M` positive
+const HEPTAVINTIMAL_DIGITS: &[u8; 27] = b"NOPQRSTUVWXYZ0ABCDEFGHIJKLM";
+
+impl<const N: usize> TritField<N> {
+    /// Trits written most significant first as `T`, `0` and `1`
+    pub fn to_trit_string(&self) -> String {
+        self.trits().rev().map(|trit| trit.to_char()).collect()
+    }
+
+    /// Balanced base-27 digits, one per group of three trits, most significant first
+    pub fn to_heptavintimal(&self) -> String {
+        self.to_trits()
+            .chunks(3)
+            .rev()
+            .map(|group| {
+                let value = group
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, trit| acc * 3 + trit.to_i8() as i32);
+                HEPTAVINTIMAL_DIGITS[(value + 13) as usize] as char
+            })
+            .collect()
+    }
+}
+
 impl<const N: usize> Default for TritField<N> {
     fn default() -> Self {
-        Self([Trit::default(); N])
+        let () = Self::FITS;
+        Self {
+            positive: 0,
+            negative: 0,
+        }
+    }
+}
+
+impl<const N: usize> Debug for TritField<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_tuple("TritField").field(&self.to_trits()).finish()
     }
 }
 
 impl<const N: usize> Display for TritField<N> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        for trit in self.0.iter() {
+        for trit in self.trits() {
             write!(f, "{},", trit)?;
         }
         Ok(())
@@ -105,14 +240,14 @@ impl<const N: usize> Display for TritField<N> {
 mod test {
     use env_logger;
 
-    use crate::arch::trit::{Trit, TritField};
+    use crate::arch::trit::{TRYTE_MAX, Trit, TritField};
 
     #[test]
     fn test_trit_conversion() {
         let _ = env_logger::try_init();
 
         // 1. Test Negative ( -1 -3 -9 = -13 )
-        let source = TritField([Trit::Negative, Trit::Negative, Trit::Negative]);
+        let source = TritField::from_trits([Trit::Negative, Trit::Negative, Trit::Negative]);
         let decimal = source.to_i128();
         assert_eq!(decimal, -13);
 
@@ -122,13 +257,62 @@ mod test {
         println!("TritField {}", wider);
 
         // Check if the first 3 trits match the source
-        assert_eq!(wider.0[0], Trit::Negative);
-        assert_eq!(wider.0[1], Trit::Negative);
-        assert_eq!(wider.0[2], Trit::Negative);
+        assert_eq!(wider.trit(0), Trit::Negative);
+        assert_eq!(wider.trit(1), Trit::Negative);
+        assert_eq!(wider.trit(2), Trit::Negative);
         // Check if padding is Zero
-        assert_eq!(wider.0[3], Trit::Zero);
-        assert_eq!(wider.0[4], Trit::Zero);
+        assert_eq!(wider.trit(3), Trit::Zero);
+        assert_eq!(wider.trit(4), Trit::Zero);
 
         println!("Decimal: {}, Wider Field: {:?}", decimal, wider);
     }
+
+    #[test]
+    fn test_trit_and_heptavintimal_notation() {
+        // 1T0 = 9 - 3 = 6
+        let six = TritField::<3>::from_i128(6);
+        assert_eq!(six.to_trit_string(), "1T0");
+        assert_eq!(six.to_heptavintimal(), "F");
+
+        let word = TritField::<27>::from_i128(-13 * 27 + 13);
+        assert_eq!(word.to_trit_string().len(), 27);
+        assert_eq!(word.to_heptavintimal(), "0000000NM");
+
+        // A partial top group is padded as if its missing trits were zero
+        let two = TritField::<4>::from_i128(-27);
+        assert_eq!(two.to_trit_string(), "T000");
+        assert_eq!(two.to_heptavintimal(), "Z0");
+    }
+
+    #[test]
+    fn test_packed_trits_round_trip() {
+        for value in [0, 1, -1, 6, -13, 797_161, TRYTE_MAX, -TRYTE_MAX] {
+            let word = TritField::<27>::from_i128(value);
+            let unpacked = word.to_trits();
+
+            assert_eq!(TritField::from_trits(unpacked), word);
+            assert_eq!(word.trits().collect::<Vec<_>>(), unpacked);
+            assert_eq!(word.is_zero(), value == 0);
+        }
+        assert_eq!(std::mem::size_of::<TritField<27>>(), 16);
+
+        let wide = TritField::<54>::from_i128(-3_i128.pow(53));
+        assert_eq!(wide.trit(53), Trit::Negative);
+        assert_eq!(wide.to_i128(), -3_i128.pow(53));
+    }
+
+    #[test]
+    fn test_set_trit_overwrites_in_place() {
+        let mut field = TritField::<5>::from_i128(-13); // 00TTT
+        field.set_trit(1, Trit::Positive);
+        field.set_trit(4, Trit::Negative);
+
+        assert_eq!(field.to_trit_string(), "T0T1T");
+        assert_eq!(field.trit(0), Trit::Negative);
+        assert_eq!(field.trit(3), Trit::Zero);
+        assert_eq!(
+            format!("{:?}", TritField::<2>::from_i128(-2)),
+            "TritField([Positive, Negative])"
+        );
+    }
 }
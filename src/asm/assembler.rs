@@ -0,0 +1,1399 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    arch::{
+        instructions::{EncodeError, IMM_MAX, Instruction, REGISTER_COUNT, UPPER_SHIFT},
+        trit::{TRYTE_MAX, Tryte},
+    },
+    asm::lexer::{Token, TokenKind, tokenize},
+    core::{address_space::AddressSpace, csr::Csr},
+};
+
+// PROGRAM
+/// Assembled machine code, keyed by word address, plus the resolved symbol table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    words: BTreeMap<i128, Tryte>,
+    symbols: HashMap<String, i128>,
+}
+
+impl Program {
+    pub fn words(&self) -> impl Iterator<Item = (i128, Tryte)> + '_ {
+        self.words.iter().map(|(address, word)| (*address, *word))
+    }
+
+    pub fn word_at(&self, address: i128) -> Option<Tryte> {
+        self.words.get(&address).copied()
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<i128> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, i128)> + '_ {
+        self.symbols
+            .iter()
+            .map(|(name, address)| (name.as_str(), *address))
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Copies every assembled word into the address space at its address.
+    pub fn load_into(&self, address_space: &mut AddressSpace) {
+        for (address, word) in self.words() {
+            address_space.write(Tryte::from_i128(address), word);
+        }
+    }
+}
+
+// ERRORS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based source line
+    pub line: usize,
+    /// 1-based source column
+    pub column: usize,
+    pub kind: AsmErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnexpectedCharacter(char),
+    InvalidNumber(String),
+    UnexpectedToken { expected: &'static str },
+    UnexpectedEndOfLine { expected: &'static str },
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    InvalidRegister(String),
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+    ValueOutOfRange { value: i128, min: i128, max: i128 },
+    OverlappingWord(i128),
+    AddressOutOfRange(i128),
+    Encode(EncodeError),
+}
+
+impl Display for AsmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            AsmErrorKind::InvalidNumber(text) => write!(f, "invalid number '{}'", text),
+            AsmErrorKind::UnexpectedToken { expected } => write!(f, "expected {}", expected),
+            AsmErrorKind::UnexpectedEndOfLine { expected } => {
+                write!(f, "expected {} before end of line", expected)
+            }
+            AsmErrorKind::UnknownMnemonic(name) => write!(f, "unknown mnemonic '{}'", name),
+            AsmErrorKind::UnknownDirective(name) => write!(f, "unknown directive '{}'", name),
+            AsmErrorKind::InvalidRegister(name) => write!(f, "invalid register '{}'", name),
+            AsmErrorKind::DuplicateLabel(name) => write!(f, "label '{}' is already defined", name),
+            AsmErrorKind::UndefinedLabel(name) => write!(f, "undefined label '{}'", name),
+            AsmErrorKind::ValueOutOfRange { value, min, max } => {
+                write!(f, "value {} does not fit in range {}..={}", value, min, max)
+            }
+            AsmErrorKind::OverlappingWord(address) => {
+                write!(f, "address {} is assembled more than once", address)
+            }
+            AsmErrorKind::AddressOutOfRange(address) => {
+                write!(
+                    f,
+                    "address {} is outside the 27-trit address space",
+                    address
+                )
+            }
+            AsmErrorKind::Encode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// PARSED SOURCE
+#[derive(Debug, Clone)]
+enum Value {
+    Number(i128),
+    Label(String),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Register(usize),
+    Value(Value),
+    /// `offset(base)` addressing used by loads and stores
+    Memory {
+        offset: Value,
+        base: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Located<T> {
+    value: T,
+    column: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Instruction {
+        mnemonic: Located<String>,
+        format: Format,
+        build: Builder,
+        operands: Vec<Located<Operand>>,
+    },
+    Pseudo {
+        mnemonic: Located<String>,
+        op: PseudoOp,
+        operands: Vec<Located<Operand>>,
+    },
+    Word(Vec<Located<Value>>),
+}
+
+/// Assembler shorthands that expand to one or more real instructions
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PseudoOp {
+    /// `call target`: `lui ra, hi` + `jalr ra, ra, lo` to an absolute address
+    Call,
+    /// `ret`: `jalr x0, ra, 0`
+    Ret,
+    /// `j target`: `jal x0, target`
+    J,
+}
+
+#[derive(Debug, Clone)]
+struct SourceLine {
+    number: usize,
+    address: i128,
+    statement: Statement,
+}
+
+/// Operand shapes, one per instruction format of the BST-27I
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    /// `op rd, rs1, rs2`
+    Register,
+    /// `op rd, rs1`
+    Unary,
+    /// `op rd, rs1, imm`
+    Immediate,
+    /// `op rd, imm(rs1)`
+    Load,
+    /// `op rs2, imm(rs1)`
+    Store,
+    /// `op rs1, rs2, target` (PC-relative)
+    Branch,
+    /// `op rs1, negative target, positive target` (PC-relative)
+    SignBranch,
+    /// `op rd, target` (PC-relative)
+    Jump,
+    /// `op rd, imm`
+    Upper,
+    /// `op rd, csr, rs1`, the CSR given by name or index
+    Csr,
+    /// `op` with no operands
+    Bare,
+}
+
+/// Operand values resolved from the source, before they become an `Instruction`
+#[derive(Debug, Clone, Copy, Default)]
+struct Fields {
+    rd: usize,
+    rs1: usize,
+    rs2: usize,
+    imm: i32,
+    /// Second immediate, only used by BSGN's positive target
+    imm2: i32,
+}
+
+type Builder = fn(Fields) -> Instruction;
+
+fn lookup_mnemonic(mnemonic: &str) -> Option<(Format, Builder)> {
+    use Instruction::*;
+
+    let entry: (Format, Builder) = match mnemonic {
+        "add" => (Format::Register, |f| Add {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "sub" => (Format::Register, |f| Sub {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "addi" => (Format::Immediate, |f| Addi {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "min" => (Format::Register, |f| Min {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "max" => (Format::Register, |f| Max {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "cons" => (Format::Register, |f| Cons {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "amin" => (Format::Register, |f| Amin {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "tsum" => (Format::Register, |f| Tsum {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "neg" => (Format::Unary, |f| Neg {
+            rd: f.rd,
+            rs1: f.rs1,
+        }),
+        "mini" => (Format::Immediate, |f| Mini {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "maxi" => (Format::Immediate, |f| Maxi {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "consi" => (Format::Immediate, |f| Consi {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "amini" => (Format::Immediate, |f| Amini {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "tsumi" => (Format::Immediate, |f| Tsumi {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "shl" => (Format::Register, |f| Shl {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "shr" => (Format::Register, |f| Shr {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "rol" => (Format::Register, |f| Rol {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "ror" => (Format::Register, |f| Ror {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "shli" => (Format::Immediate, |f| Shli {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "shri" => (Format::Immediate, |f| Shri {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "roli" => (Format::Immediate, |f| Roli {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "rori" => (Format::Immediate, |f| Rori {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "mul" => (Format::Register, |f| Mul {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "mulh" => (Format::Register, |f| Mulh {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "div" => (Format::Register, |f| Div {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "rem" => (Format::Register, |f| Rem {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "addc" => (Format::Register, |f| Addc {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "subb" => (Format::Register, |f| Subb {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "addv" => (Format::Register, |f| Addv {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "subv" => (Format::Register, |f| Subv {
+            rd: f.rd,
+            rs1: f.rs1,
+            rs2: f.rs2,
+        }),
+        "lw" => (Format::Load, |f| Lw {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "sw" => (Format::Store, |f| Sw {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "beq" => (Format::Branch, |f| Beq {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "bne" => (Format::Branch, |f| Bne {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "blt" => (Format::Branch, |f| Blt {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "bge" => (Format::Branch, |f| Bge {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "bltm" => (Format::Branch, |f| Bltm {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "bgem" => (Format::Branch, |f| Bgem {
+            rs1: f.rs1,
+            rs2: f.rs2,
+            imm: f.imm,
+        }),
+        "bsgn" => (Format::SignBranch, |f| Bsgn {
+            rs1: f.rs1,
+            neg: f.imm,
+            pos: f.imm2,
+        }),
+        "jal" => (Format::Jump, |f| Jal {
+            rd: f.rd,
+            imm: f.imm,
+        }),
+        "jalr" => (Format::Immediate, |f| Jalr {
+            rd: f.rd,
+            rs1: f.rs1,
+            imm: f.imm,
+        }),
+        "lui" => (Format::Upper, |f| Lui {
+            rd: f.rd,
+            imm: f.imm,
+        }),
+        "csrrw" => (Format::Csr, |f| Csrrw {
+            rd: f.rd,
+            rs1: f.rs1,
+            csr: f.imm,
+        }),
+        "csrrs" => (Format::Csr, |f| Csrrs {
+            rd: f.rd,
+            rs1: f.rs1,
+            csr: f.imm,
+        }),
+        "csrrc" => (Format::Csr, |f| Csrrc {
+            rd: f.rd,
+            rs1: f.rs1,
+            csr: f.imm,
+        }),
+        "nop" => (Format::Bare, |_| Nop),
+        "halt" => (Format::Bare, |_| Halt),
+        "tret" => (Format::Bare, |_| Tret),
+        "ei" => (Format::Bare, |_| Ei),
+        "di" => (Format::Bare, |_| Di),
+        "iret" => (Format::Bare, |_| Iret),
+        _ => return None,
+    };
+
+    Some(entry)
+}
+
+fn lookup_pseudo(mnemonic: &str) -> Option<PseudoOp> {
+    match mnemonic {
+        "call" => Some(PseudoOp::Call),
+        "ret" => Some(PseudoOp::Ret),
+        "j" => Some(PseudoOp::J),
+        _ => None,
+    }
+}
+
+// ASSEMBLER
+/// Assembles BST-27I source text into machine code.
+///
+/// The first pass parses every line and assigns addresses to labels, the second pass
+/// resolves label references (including forward ones) and encodes each word.
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    let mut symbols = HashMap::new();
+    let lines = first_pass(source, &mut symbols)?;
+
+    let mut words = BTreeMap::new();
+    for line in &lines {
+        for (offset, word) in second_pass(line, &symbols)?.into_iter().enumerate() {
+            let address = line.address + offset as i128;
+            if address > TRYTE_MAX {
+                return Err(AsmError {
+                    line: line.number,
+                    column: 1,
+                    kind: AsmErrorKind::AddressOutOfRange(address),
+                });
+            }
+            if words.insert(address, word).is_some() {
+                return Err(AsmError {
+                    line: line.number,
+                    column: 1,
+                    kind: AsmErrorKind::OverlappingWord(address),
+                });
+            }
+        }
+    }
+
+    Ok(Program { words, symbols })
+}
+
+fn first_pass(
+    source: &str,
+    symbols: &mut HashMap<String, i128>,
+) -> Result<Vec<SourceLine>, AsmError> {
+    let mut lines = Vec::new();
+    let mut address: i128 = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let number = index + 1;
+        let tokens = tokenize(text, number)?;
+        let mut parser = LineParser::new(&tokens, number, text.len());
+
+        // Labels: any number of `name:` prefixes
+        while let Some(name) = parser.peek_label() {
+            if symbols.insert(name.value.clone(), address).is_some() {
+                return Err(parser.error_at(name.column, AsmErrorKind::DuplicateLabel(name.value)));
+            }
+        }
+
+        let Some(head) = parser.next() else {
+            continue;
+        };
+
+        match &head.kind {
+            TokenKind::Directive(name) => match name.to_lowercase().as_str() {
+                ".org" => {
+                    let origin = parser.number()?;
+                    if !(-TRYTE_MAX..=TRYTE_MAX).contains(&origin.value) {
+                        return Err(parser.error_at(
+                            origin.column,
+                            AsmErrorKind::AddressOutOfRange(origin.value),
+                        ));
+                    }
+                    address = origin.value;
+                    parser.finish()?;
+                }
+                ".word" => {
+                    let values = parser.value_list()?;
+                    let statement = Statement::Word(values);
+                    let size = statement_size(&statement);
+                    lines.push(SourceLine {
+                        number,
+                        address,
+                        statement,
+                    });
+                    address += size;
+                }
+                _ => {
+                    return Err(
+                        parser.error_at(head.column, AsmErrorKind::UnknownDirective(name.clone()))
+                    );
+                }
+            },
+            TokenKind::Identifier(name) => {
+                let mnemonic = Located {
+                    value: name.to_lowercase(),
+                    column: head.column,
+                };
+                let statement = if let Some(op) = lookup_pseudo(&mnemonic.value) {
+                    Statement::Pseudo {
+                        mnemonic,
+                        op,
+                        operands: parser.operand_list()?,
+                    }
+                } else {
+                    let (format, build) = lookup_mnemonic(&mnemonic.value).ok_or(
+                        parser.error_at(head.column, AsmErrorKind::UnknownMnemonic(name.clone())),
+                    )?;
+                    Statement::Instruction {
+                        mnemonic,
+                        format,
+                        build,
+                        operands: parser.operand_list()?,
+                    }
+                };
+                let size = statement_size(&statement);
+                lines.push(SourceLine {
+                    number,
+                    address,
+                    statement,
+                });
+                address += size;
+            }
+            _ => {
+                return Err(parser.error_at(
+                    head.column,
+                    AsmErrorKind::UnexpectedToken {
+                        expected: "mnemonic, directive or label",
+                    },
+                ));
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Number of words a statement occupies in memory
+fn statement_size(statement: &Statement) -> i128 {
+    match statement {
+        Statement::Instruction { .. } => 1,
+        Statement::Pseudo { op, .. } => match op {
+            PseudoOp::Call => 2,
+            PseudoOp::Ret | PseudoOp::J => 1,
+        },
+        Statement::Word(values) => values.len() as i128,
+    }
+}
+
+fn second_pass(line: &SourceLine, symbols: &HashMap<String, i128>) -> Result<Vec<Tryte>, AsmError> {
+    let resolver = Resolver {
+        line: line.number,
+        symbols,
+    };
+
+    match &line.statement {
+        Statement::Word(values) => values
+            .iter()
+            .map(|value| {
+                let resolved = resolver.value(value)?;
+                resolver.check_range(resolved, value.column, -TRYTE_MAX, TRYTE_MAX)?;
+                Ok(Tryte::from_i128(resolved))
+            })
+            .collect(),
+        Statement::Instruction {
+            mnemonic,
+            format,
+            build,
+            operands,
+        } => {
+            let word =
+                encode_instruction(&resolver, line.address, mnemonic, *format, *build, operands)?;
+            Ok(vec![word])
+        }
+        Statement::Pseudo {
+            mnemonic,
+            op,
+            operands,
+        } => expand_pseudo(&resolver, line.address, mnemonic, *op, operands),
+    }
+}
+
+fn encode_instruction(
+    resolver: &Resolver,
+    address: i128,
+    mnemonic: &Located<String>,
+    format: Format,
+    build: Builder,
+    operands: &[Located<Operand>],
+) -> Result<Tryte, AsmError> {
+    let expected = match format {
+        Format::Register
+        | Format::Immediate
+        | Format::Branch
+        | Format::SignBranch
+        | Format::Csr => 3,
+        Format::Unary | Format::Load | Format::Store | Format::Jump | Format::Upper => 2,
+        Format::Bare => 0,
+    };
+
+    if operands.len() != expected {
+        // Loads and stores also accept the three-operand `op r, base, imm` spelling
+        let three_operand_memory =
+            matches!(format, Format::Load | Format::Store) && operands.len() == 3;
+
+        if !three_operand_memory {
+            let column = operands
+                .get(expected)
+                .map_or(mnemonic.column, |operand| operand.column);
+            return Err(AsmError {
+                line: resolver.line,
+                column,
+                kind: AsmErrorKind::UnexpectedToken {
+                    expected: match expected {
+                        0 => "no operands",
+                        2 => "2 operands",
+                        _ => "3 operands",
+                    },
+                },
+            });
+        }
+    }
+
+    let mut fields = Fields::default();
+
+    match format {
+        Format::Register => {
+            fields.rd = resolver.register(&operands[0])?;
+            fields.rs1 = resolver.register(&operands[1])?;
+            fields.rs2 = resolver.register(&operands[2])?;
+        }
+        Format::Unary => {
+            fields.rd = resolver.register(&operands[0])?;
+            fields.rs1 = resolver.register(&operands[1])?;
+        }
+        Format::Immediate => {
+            fields.rd = resolver.register(&operands[0])?;
+            fields.rs1 = resolver.register(&operands[1])?;
+            fields.imm = resolver.immediate(&operands[2], None)?;
+        }
+        Format::Load | Format::Store => {
+            let data = resolver.register(&operands[0])?;
+            let (base, offset) = resolver.memory(&operands[1..])?;
+            fields.rs1 = base;
+            fields.imm = offset;
+            if format == Format::Load {
+                fields.rd = data;
+            } else {
+                fields.rs2 = data;
+            }
+        }
+        Format::Branch => {
+            fields.rs1 = resolver.register(&operands[0])?;
+            fields.rs2 = resolver.register(&operands[1])?;
+            fields.imm = resolver.immediate(&operands[2], Some(address))?;
+        }
+        Format::SignBranch => {
+            fields.rs1 = resolver.register(&operands[0])?;
+            fields.imm = resolver.immediate(&operands[1], Some(address))?;
+            fields.imm2 = resolver.immediate(&operands[2], Some(address))?;
+        }
+        Format::Jump => {
+            fields.rd = resolver.register(&operands[0])?;
+            fields.imm = resolver.immediate(&operands[1], Some(address))?;
+        }
+        Format::Upper => {
+            fields.rd = resolver.register(&operands[0])?;
+            fields.imm = resolver.immediate(&operands[1], None)?;
+        }
+        Format::Csr => {
+            fields.rd = resolver.register(&operands[0])?;
+            fields.imm = resolver.csr(&operands[1])?;
+            fields.rs1 = resolver.register(&operands[2])?;
+        }
+        Format::Bare => {}
+    }
+
+    build(fields).encode().map_err(|err| AsmError {
+        line: resolver.line,
+        column: mnemonic.column,
+        kind: AsmErrorKind::Encode(err),
+    })
+}
+
+/// Link register used by `call` and `ret`
+const RETURN_ADDRESS: usize = 1;
+
+/// Largest address `call` reaches with LUI + JALR: (3^26 - 1) / 2
+const CALL_MAX: i128 = 1_270_932_914_164;
+
+fn expand_pseudo(
+    resolver: &Resolver,
+    address: i128,
+    mnemonic: &Located<String>,
+    op: PseudoOp,
+    operands: &[Located<Operand>],
+) -> Result<Vec<Tryte>, AsmError> {
+    let expected = match op {
+        PseudoOp::Ret => 0,
+        PseudoOp::Call | PseudoOp::J => 1,
+    };
+    if operands.len() != expected {
+        let column = operands
+            .get(expected)
+            .map_or(mnemonic.column, |operand| operand.column);
+        return Err(resolver.error(
+            column,
+            AsmErrorKind::UnexpectedToken {
+                expected: if expected == 0 {
+                    "no operands"
+                } else {
+                    "1 operand"
+                },
+            },
+        ));
+    }
+
+    let instructions = match op {
+        PseudoOp::Call => {
+            let target = resolver.absolute(&operands[0])?;
+            resolver.check_range(target, operands[0].column, -CALL_MAX, CALL_MAX)?;
+
+            // Balanced split: lo is the low 13 trits, hi the rest
+            let scale = 3_i128.pow(UPPER_SHIFT as u32);
+            let lo = (target + IMM_MAX as i128).rem_euclid(scale) - IMM_MAX as i128;
+            let hi = (target - lo) / scale;
+            vec![
+                Instruction::Lui {
+                    rd: RETURN_ADDRESS,
+                    imm: hi as i32,
+                },
+                Instruction::Jalr {
+                    rd: RETURN_ADDRESS,
+                    rs1: RETURN_ADDRESS,
+                    imm: lo as i32,
+                },
+            ]
+        }
+        PseudoOp::Ret => vec![Instruction::Jalr {
+            rd: 0,
+            rs1: RETURN_ADDRESS,
+            imm: 0,
+        }],
+        PseudoOp::J => vec![Instruction::Jal {
+            rd: 0,
+            imm: resolver.immediate(&operands[0], Some(address))?,
+        }],
+    };
+
+    instructions
+        .iter()
+        .map(|instruction| {
+            instruction.encode().map_err(|err| AsmError {
+                line: resolver.line,
+                column: mnemonic.column,
+                kind: AsmErrorKind::Encode(err),
+            })
+        })
+        .collect()
+}
+
+struct Resolver<'a> {
+    line: usize,
+    symbols: &'a HashMap<String, i128>,
+}
+
+impl Resolver<'_> {
+    fn error(&self, column: usize, kind: AsmErrorKind) -> AsmError {
+        AsmError {
+            line: self.line,
+            column,
+            kind,
+        }
+    }
+
+    fn value(&self, value: &Located<Value>) -> Result<i128, AsmError> {
+        match &value.value {
+            Value::Number(number) => Ok(*number),
+            Value::Label(name) => self
+                .symbols
+                .get(name)
+                .copied()
+                .ok_or(self.error(value.column, AsmErrorKind::UndefinedLabel(name.clone()))),
+        }
+    }
+
+    fn check_range(
+        &self,
+        value: i128,
+        column: usize,
+        min: i128,
+        max: i128,
+    ) -> Result<(), AsmError> {
+        if value < min || value > max {
+            return Err(self.error(column, AsmErrorKind::ValueOutOfRange { value, min, max }));
+        }
+        Ok(())
+    }
+
+    fn check_immediate(&self, value: i128, column: usize) -> Result<i32, AsmError> {
+        let max = IMM_MAX as i128;
+        self.check_range(value, column, -max, max)?;
+        Ok(value as i32)
+    }
+
+    fn register(&self, operand: &Located<Operand>) -> Result<usize, AsmError> {
+        match operand.value {
+            Operand::Register(index) => Ok(index),
+            _ => Err(self.error(
+                operand.column,
+                AsmErrorKind::UnexpectedToken {
+                    expected: "register",
+                },
+            )),
+        }
+    }
+
+    /// Resolves a CSR operand: a register name such as `cycle`, which takes
+    /// precedence over labels, or an index
+    fn csr(&self, operand: &Located<Operand>) -> Result<i32, AsmError> {
+        if let Operand::Value(Value::Label(name)) = &operand.value
+            && let Some(csr) = Csr::from_name(name)
+        {
+            return Ok(csr.index());
+        }
+        self.immediate(operand, None)
+    }
+
+    /// Resolves an immediate operand. When `relative_to` is set, labels become an
+    /// offset from that address while plain numbers are taken as the offset itself.
+    fn immediate(
+        &self,
+        operand: &Located<Operand>,
+        relative_to: Option<i128>,
+    ) -> Result<i32, AsmError> {
+        let Operand::Value(value) = &operand.value else {
+            return Err(self.error(
+                operand.column,
+                AsmErrorKind::UnexpectedToken {
+                    expected: "immediate or label",
+                },
+            ));
+        };
+
+        let located = Located {
+            value: value.clone(),
+            column: operand.column,
+        };
+        let mut resolved = self.value(&located)?;
+        if let (Value::Label(_), Some(origin)) = (value, relative_to) {
+            resolved -= origin;
+        }
+
+        self.check_immediate(resolved, operand.column)
+    }
+
+    /// Resolves a number or label operand to its full value.
+    fn absolute(&self, operand: &Located<Operand>) -> Result<i128, AsmError> {
+        let Operand::Value(value) = &operand.value else {
+            return Err(self.error(
+                operand.column,
+                AsmErrorKind::UnexpectedToken {
+                    expected: "address or label",
+                },
+            ));
+        };
+
+        self.value(&Located {
+            value: value.clone(),
+            column: operand.column,
+        })
+    }
+
+    /// Accepts either `imm(base)` or the `base, imm` operand pair.
+    fn memory(&self, operands: &[Located<Operand>]) -> Result<(usize, i32), AsmError> {
+        match operands {
+            [
+                Located {
+                    value: Operand::Memory { offset, base },
+                    column,
+                },
+            ] => {
+                let offset = self.value(&Located {
+                    value: offset.clone(),
+                    column: *column,
+                })?;
+                Ok((*base, self.check_immediate(offset, *column)?))
+            }
+            [base, offset] => Ok((self.register(base)?, self.immediate(offset, None)?)),
+            _ => Err(self.error(
+                operands.first().map_or(1, |operand| operand.column),
+                AsmErrorKind::UnexpectedToken {
+                    expected: "memory operand",
+                },
+            )),
+        }
+    }
+}
+
+// LINE PARSER
+struct LineParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    line: usize,
+    end_column: usize,
+}
+
+impl<'a> LineParser<'a> {
+    fn new(tokens: &'a [Token], line: usize, line_length: usize) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            line,
+            end_column: line_length + 1,
+        }
+    }
+
+    fn error_at(&self, column: usize, kind: AsmErrorKind) -> AsmError {
+        AsmError {
+            line: self.line,
+            column,
+            kind,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn expect_next(&mut self, expected: &'static str) -> Result<&'a Token, AsmError> {
+        let column = self.end_column;
+        self.next()
+            .ok_or(self.error_at(column, AsmErrorKind::UnexpectedEndOfLine { expected }))
+    }
+
+    /// Consumes `name:` if it is next in the line.
+    fn peek_label(&mut self) -> Option<Located<String>> {
+        match (self.peek(), self.tokens.get(self.position + 1)) {
+            (
+                Some(Token {
+                    kind: TokenKind::Identifier(name),
+                    column,
+                }),
+                Some(Token {
+                    kind: TokenKind::Colon,
+                    ..
+                }),
+            ) => {
+                self.position += 2;
+                Some(Located {
+                    value: name.clone(),
+                    column: *column,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn finish(&self) -> Result<(), AsmError> {
+        match self.peek() {
+            Some(token) => Err(self.error_at(
+                token.column,
+                AsmErrorKind::UnexpectedToken {
+                    expected: "end of line",
+                },
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Located<i128>, AsmError> {
+        let token = self.expect_next("number")?;
+        match token.kind {
+            TokenKind::Number(value) => Ok(Located {
+                value,
+                column: token.column,
+            }),
+            _ => Err(self.error_at(
+                token.column,
+                AsmErrorKind::UnexpectedToken { expected: "number" },
+            )),
+        }
+    }
+
+    fn value(&mut self) -> Result<Located<Value>, AsmError> {
+        let token = self.expect_next("number or label")?;
+        let value = match &token.kind {
+            TokenKind::Number(value) => Value::Number(*value),
+            TokenKind::Identifier(name) => Value::Label(name.clone()),
+            _ => {
+                return Err(self.error_at(
+                    token.column,
+                    AsmErrorKind::UnexpectedToken {
+                        expected: "number or label",
+                    },
+                ));
+            }
+        };
+
+        Ok(Located {
+            value,
+            column: token.column,
+        })
+    }
+
+    fn value_list(&mut self) -> Result<Vec<Located<Value>>, AsmError> {
+        let mut values = vec![self.value()?];
+        while self.peek().is_some() {
+            self.comma()?;
+            values.push(self.value()?);
+        }
+        Ok(values)
+    }
+
+    fn comma(&mut self) -> Result<(), AsmError> {
+        let token = self.expect_next("','")?;
+        match token.kind {
+            TokenKind::Comma => Ok(()),
+            _ => Err(self.error_at(
+                token.column,
+                AsmErrorKind::UnexpectedToken { expected: "','" },
+            )),
+        }
+    }
+
+    fn operand_list(&mut self) -> Result<Vec<Located<Operand>>, AsmError> {
+        let mut operands = Vec::new();
+        if self.peek().is_none() {
+            return Ok(operands);
+        }
+
+        operands.push(self.operand()?);
+        while self.peek().is_some() {
+            self.comma()?;
+            operands.push(self.operand()?);
+        }
+        Ok(operands)
+    }
+
+    fn operand(&mut self) -> Result<Located<Operand>, AsmError> {
+        let value = self.value()?;
+        let column = value.column;
+
+        if let Value::Label(name) = &value.value
+            && let Some(index) = parse_register(name)
+        {
+            let index =
+                index.ok_or(self.error_at(column, AsmErrorKind::InvalidRegister(name.clone())))?;
+            return Ok(Located {
+                value: Operand::Register(index),
+                column,
+            });
+        }
+
+        // `offset(base)`
+        if let Some(Token {
+            kind: TokenKind::LeftParen,
+            ..
+        }) = self.peek()
+        {
+            self.position += 1;
+            let token = self.expect_next("register")?;
+            let base = match &token.kind {
+                TokenKind::Identifier(name) => match parse_register(name) {
+                    Some(Some(index)) => index,
+                    _ => {
+                        return Err(self
+                            .error_at(token.column, AsmErrorKind::InvalidRegister(name.clone())));
+                    }
+                },
+                _ => {
+                    return Err(self.error_at(
+                        token.column,
+                        AsmErrorKind::UnexpectedToken {
+                            expected: "register",
+                        },
+                    ));
+                }
+            };
+
+            let close = self.expect_next("')'")?;
+            if close.kind != TokenKind::RightParen {
+                return Err(self.error_at(
+                    close.column,
+                    AsmErrorKind::UnexpectedToken { expected: "')'" },
+                ));
+            }
+
+            return Ok(Located {
+                value: Operand::Memory {
+                    offset: value.value,
+                    base,
+                },
+                column,
+            });
+        }
+
+        Ok(Located {
+            value: Operand::Value(value.value),
+            column,
+        })
+    }
+}
+
+/// Returns `None` if `name` is not register syntax at all, and `Some(None)` if it looks
+/// like a register but names one the architecture does not have.
+pub(crate) fn parse_register(name: &str) -> Option<Option<usize>> {
+    let lower = name.to_lowercase();
+    match lower.as_str() {
+        "zero" => return Some(Some(0)),
+        "ra" => return Some(Some(RETURN_ADDRESS)),
+        _ => {}
+    }
+
+    let digits = lower.strip_prefix('x')?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    match digits.parse::<usize>() {
+        Ok(index) if index < REGISTER_COUNT => Some(Some(index)),
+        _ => Some(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(program: &Program) -> Vec<Instruction> {
+        program
+            .words()
+            .map(|(_, word)| Instruction::from(word))
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble_round_trips_through_decoder() {
+        let program = assemble(
+            "
+            addi x1, x0, 5
+            add  x3, x1, x2
+            sub  x4, x3, x1
+            lw   x2, 100(x0)
+            sw   x1, -7(x3)
+            lui  x5, 42
+            add  x26, x14, x13
+            neg  x6, x1
+            consi x7, x1, -4
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decode_all(&program),
+            vec![
+                Instruction::Addi {
+                    rd: 1,
+                    rs1: 0,
+                    imm: 5
+                },
+                Instruction::Add {
+                    rd: 3,
+                    rs1: 1,
+                    rs2: 2
+                },
+                Instruction::Sub {
+                    rd: 4,
+                    rs1: 3,
+                    rs2: 1
+                },
+                Instruction::Lw {
+                    rd: 2,
+                    rs1: 0,
+                    imm: 100
+                },
+                Instruction::Sw {
+                    rs1: 3,
+                    rs2: 1,
+                    imm: -7
+                },
+                Instruction::Lui { rd: 5, imm: 42 },
+                Instruction::Add {
+                    rd: 26,
+                    rs1: 14,
+                    rs2: 13
+                },
+                Instruction::Neg { rd: 6, rs1: 1 },
+                Instruction::Consi {
+                    rd: 7,
+                    rs1: 1,
+                    imm: -4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pseudo_ops_expand() {
+        let program = assemble(
+            "
+                  call func
+                  j    end
+            end:  halt
+            .org 3000000
+            func: ret
+            ",
+        )
+        .unwrap();
+
+        // 3000000 = 2 * 3^13 - 188646
+        assert_eq!(
+            decode_all(&program),
+            vec![
+                Instruction::Lui { rd: 1, imm: 2 },
+                Instruction::Jalr {
+                    rd: 1,
+                    rs1: 1,
+                    imm: -188_646
+                },
+                Instruction::Jal { rd: 0, imm: 1 },
+                Instruction::Halt,
+                Instruction::Jalr {
+                    rd: 0,
+                    rs1: 1,
+                    imm: 0
+                },
+            ]
+        );
+
+        let err = assemble("ret x1").unwrap_err();
+        assert_eq!(err.column, 5);
+    }
+
+    #[test]
+    fn test_labels_resolve_forward_and_backward() {
+        let program = assemble(
+            "
+            start:  beq x1, x2, done   ; forward reference
+                    addi x1, x1, 1
+                    jal x0, start      ; backward reference
+            done:   .word start, done, -3
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(program.symbol("start"), Some(0));
+        assert_eq!(program.symbol("done"), Some(3));
+        assert_eq!(
+            Instruction::from(program.word_at(0).unwrap()),
+            Instruction::Beq {
+                rs1: 1,
+                rs2: 2,
+                imm: 3
+            }
+        );
+        assert_eq!(
+            Instruction::from(program.word_at(2).unwrap()),
+            Instruction::Jal { rd: 0, imm: -2 }
+        );
+        assert_eq!(program.word_at(3).unwrap().to_i128(), 0);
+        assert_eq!(program.word_at(4).unwrap().to_i128(), 3);
+        assert_eq!(program.word_at(5).unwrap().to_i128(), -3);
+    }
+
+    #[test]
+    fn test_org_places_code_at_address() {
+        let program = assemble(
+            "
+            .org 0t1T0   ; 6
+            entry: addi x1, x0, 1
+            .org -10
+            .word entry
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(program.symbol("entry"), Some(6));
+        assert_eq!(
+            Instruction::from(program.word_at(6).unwrap()),
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 1
+            }
+        );
+        assert_eq!(program.word_at(-10).unwrap().to_i128(), 6);
+        assert_eq!(program.len(), 2);
+    }
+
+    #[test]
+    fn test_errors_report_line_and_column() {
+        let err = assemble("addi x1, x0, 1\n  bogus x1, x2").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+        assert_eq!(err.kind, AsmErrorKind::UnknownMnemonic("bogus".into()));
+
+        let err = assemble("beq x1, x2, nowhere").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 13));
+        assert_eq!(err.kind, AsmErrorKind::UndefinedLabel("nowhere".into()));
+
+        let err = assemble("addi x1, x0, 797162").unwrap_err();
+        assert_eq!(err.column, 14);
+        assert!(matches!(err.kind, AsmErrorKind::ValueOutOfRange { .. }));
+
+        let err = assemble("loop: nop_free:\nloop: add x1, x1, x1").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 1));
+        assert_eq!(err.kind, AsmErrorKind::DuplicateLabel("loop".into()));
+
+        let err = assemble("add x1, x99, x2").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 9));
+        assert_eq!(err.kind, AsmErrorKind::InvalidRegister("x99".into()));
+    }
+
+    #[test]
+    fn test_overlapping_org_is_rejected() {
+        let err = assemble(".word 1, 2\n.org 1\n.word 3").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(err.kind, AsmErrorKind::OverlappingWord(1));
+    }
+
+    #[test]
+    fn test_addresses_must_fit_in_a_word() {
+        let err = assemble(".word 0\n.org 3812798742494\n.word 0").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 6));
+        assert_eq!(err.kind, AsmErrorKind::AddressOutOfRange(TRYTE_MAX + 1));
+
+        // The last word fits, the one after it does not
+        let err = assemble(".org 3812798742493\n.word 0\n.word 0").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(err.kind, AsmErrorKind::AddressOutOfRange(TRYTE_MAX + 1));
+        assert!(assemble(".org -3812798742493\n.word 0").is_ok());
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use crate::{
+    arch::{
+        instructions::{DecodeError, Instruction},
+        trit::Tryte,
+    },
+    asm::assembler::Program,
+    core::{address_space::AddressSpace, csr::Csr},
+};
+
+/// How immediates are written in the disassembly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OperandRadix {
+    #[default]
+    Decimal,
+    /// `0t`-prefixed balanced ternary, as accepted by the assembler
+    BalancedTernary,
+}
+
+/// One disassembled memory word
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisassembledWord {
+    pub address: i128,
+    pub raw: Tryte,
+    pub instruction: Result<Instruction, DecodeError>,
+    /// Label defined at this address, if any
+    pub label: Option<String>,
+    /// Instruction text, e.g. `ADDI x1, x0, 5`
+    pub text: String,
+    /// Absolute branch or jump target, with its label if one is known
+    pub target: Option<(i128, Option<String>)>,
+}
+
+impl Display for DisassembledWord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{}:", label)?;
+        }
+
+        write!(
+            f,
+            "{:>10}  {}  {}  {}",
+            self.address,
+            self.raw.to_trit_string(),
+            self.raw.to_heptavintimal(),
+            self.text
+        )?;
+
+        match (&self.instruction, &self.target) {
+            (Err(err), _) => write!(f, "  ; {}", err),
+            (Ok(_), Some((address, Some(label)))) => write!(f, "  ; -> {} <{}>", address, label),
+            (Ok(_), Some((address, None))) => write!(f, "  ; -> {}", address),
+            (Ok(_), None) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Disassembler {
+    labels: BTreeMap<i128, String>,
+    radix: OperandRadix,
+}
+
+impl Disassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_radix(mut self, radix: OperandRadix) -> Self {
+        self.radix = radix;
+        self
+    }
+
+    pub fn with_label(mut self, address: i128, name: &str) -> Self {
+        self.labels.insert(address, name.to_string());
+        self
+    }
+
+    /// Uses the symbol table of an assembled program to name addresses.
+    pub fn with_symbols(mut self, program: &Program) -> Self {
+        for (name, address) in program.symbols() {
+            // Keep the alphabetically first name when several labels share an address
+            let entry = self
+                .labels
+                .entry(address)
+                .or_insert_with(|| name.to_string());
+            if name < entry.as_str() {
+                *entry = name.to_string();
+            }
+        }
+        self
+    }
+
+    pub fn disassemble_word(&self, address: i128, raw: Tryte) -> DisassembledWord {
+        let instruction = Instruction::from_machine_code(raw);
+
+        let (text, target) = match &instruction {
+            Ok(instr) => (
+                self.format_instruction(instr),
+                branch_offset(instr).map(|offset| {
+                    let destination = address + offset as i128;
+                    (destination, self.labels.get(&destination).cloned())
+                }),
+            ),
+            // Unknown opcodes are shown as data so the listing can be reassembled
+            Err(_) => (format!(".word {}", raw.to_i128()), None),
+        };
+
+        DisassembledWord {
+            address,
+            raw,
+            instruction,
+            label: self.labels.get(&address).cloned(),
+            text,
+            target,
+        }
+    }
+
+    pub fn disassemble_range(
+        &self,
+        address_space: &AddressSpace,
+        range: Range<i128>,
+    ) -> Vec<DisassembledWord> {
+        range
+            .map(|address| {
+                let raw = address_space.read(Tryte::from_i128(address));
+                self.disassemble_word(address, raw)
+            })
+            .collect()
+    }
+
+    pub fn format_instruction(&self, instr: &Instruction) -> String {
+        use Instruction::*;
+
+        let mnemonic = instr.mnemonic();
+        match *instr {
+            Add { rd, rs1, rs2 }
+            | Sub { rd, rs1, rs2 }
+            | Min { rd, rs1, rs2 }
+            | Max { rd, rs1, rs2 }
+            | Cons { rd, rs1, rs2 }
+            | Amin { rd, rs1, rs2 }
+            | Tsum { rd, rs1, rs2 }
+            | Shl { rd, rs1, rs2 }
+            | Shr { rd, rs1, rs2 }
+            | Rol { rd, rs1, rs2 }
+            | Ror { rd, rs1, rs2 }
+            | Mul { rd, rs1, rs2 }
+            | Mulh { rd, rs1, rs2 }
+            | Div { rd, rs1, rs2 }
+            | Rem { rd, rs1, rs2 }
+            | Addc { rd, rs1, rs2 }
+            | Subb { rd, rs1, rs2 }
+            | Addv { rd, rs1, rs2 }
+            | Subv { rd, rs1, rs2 } => {
+                format!("{} x{}, x{}, x{}", mnemonic, rd, rs1, rs2)
+            }
+            Neg { rd, rs1 } => format!("{} x{}, x{}", mnemonic, rd, rs1),
+            Addi { rd, rs1, imm }
+            | Mini { rd, rs1, imm }
+            | Maxi { rd, rs1, imm }
+            | Consi { rd, rs1, imm }
+            | Amini { rd, rs1, imm }
+            | Tsumi { rd, rs1, imm }
+            | Shli { rd, rs1, imm }
+            | Shri { rd, rs1, imm }
+            | Roli { rd, rs1, imm }
+            | Rori { rd, rs1, imm }
+            | Jalr { rd, rs1, imm } => {
+                format!("{} x{}, x{}, {}", mnemonic, rd, rs1, self.immediate(imm))
+            }
+            Lw { rd, rs1, imm } => {
+                format!("{} x{}, {}(x{})", mnemonic, rd, self.immediate(imm), rs1)
+            }
+            Sw { rs1, rs2, imm } => {
+                format!("{} x{}, {}(x{})", mnemonic, rs2, self.immediate(imm), rs1)
+            }
+            Bsgn { rs1, neg, pos } => format!(
+                "{} x{}, {}, {}",
+                mnemonic,
+                rs1,
+                self.immediate(neg),
+                self.immediate(pos)
+            ),
+            Beq { rs1, rs2, imm }
+            | Bne { rs1, rs2, imm }
+            | Blt { rs1, rs2, imm }
+            | Bge { rs1, rs2, imm }
+            | Bltm { rs1, rs2, imm }
+            | Bgem { rs1, rs2, imm } => {
+                format!("{} x{}, x{}, {}", mnemonic, rs1, rs2, self.immediate(imm))
+            }
+            Jal { rd, imm } | Lui { rd, imm } => {
+                format!("{} x{}, {}", mnemonic, rd, self.immediate(imm))
+            }
+            Csrrw { rd, rs1, csr } | Csrrs { rd, rs1, csr } | Csrrc { rd, rs1, csr } => {
+                let csr = match Csr::from_index(csr) {
+                    Some(known) => known.name().to_string(),
+                    None => self.immediate(csr),
+                };
+                format!("{} x{}, {}, x{}", mnemonic, rd, csr, rs1)
+            }
+            Halt | Tret | Ei | Di | Iret | Nop => mnemonic.to_string(),
+        }
+    }
+
+    fn immediate(&self, value: i32) -> String {
+        match self.radix {
+            OperandRadix::Decimal => value.to_string(),
+            OperandRadix::BalancedTernary => {
+                let trits = Tryte::from_i128(value as i128).to_trit_string();
+                let digits = trits.trim_start_matches('0');
+                format!("0t{}", if digits.is_empty() { "0" } else { digits })
+            }
+        }
+    }
+}
+
+/// PC-relative offset of control-flow instructions
+/// PC-relative offset of single-target branches and jumps. BSGN has two
+/// targets and is left unannotated.
+fn branch_offset(instr: &Instruction) -> Option<i32> {
+    match *instr {
+        Instruction::Beq { imm, .. }
+        | Instruction::Bne { imm, .. }
+        | Instruction::Blt { imm, .. }
+        | Instruction::Bge { imm, .. }
+        | Instruction::Bltm { imm, .. }
+        | Instruction::Bgem { imm, .. }
+        | Instruction::Jal { imm, .. } => Some(imm),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assembler::assemble;
+
+    #[test]
+    fn test_disassemble_word_renders_mnemonics() {
+        let disassembler = Disassembler::new();
+        let word = Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 5,
+        }
+        .encode()
+        .unwrap();
+
+        let line = disassembler.disassemble_word(0, word);
+        assert_eq!(line.text, "ADDI x1, x0, 5");
+        assert_eq!(line.target, None);
+
+        let store = Instruction::Sw {
+            rs1: 3,
+            rs2: 1,
+            imm: -7,
+        };
+        assert_eq!(disassembler.format_instruction(&store), "SW x1, -7(x3)");
+    }
+
+    #[test]
+    fn test_balanced_ternary_operands() {
+        let disassembler = Disassembler::new().with_radix(OperandRadix::BalancedTernary);
+        let instr = Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: -6,
+        };
+        assert_eq!(
+            disassembler.format_instruction(&instr),
+            "ADDI x1, x0, 0tT10"
+        );
+
+        let zero = Instruction::Lui { rd: 2, imm: 0 };
+        assert_eq!(disassembler.format_instruction(&zero), "LUI x2, 0t0");
+    }
+
+    #[test]
+    fn test_csr_operands_use_register_names() {
+        let disassembler = Disassembler::new();
+        let read = Instruction::Csrrs {
+            rd: 3,
+            rs1: 0,
+            csr: Csr::Cycle.index(),
+        };
+        assert_eq!(
+            disassembler.format_instruction(&read),
+            "CSRRS x3, cycle, x0"
+        );
+
+        let unknown = Instruction::Csrrw {
+            rd: 0,
+            rs1: 4,
+            csr: 500,
+        };
+        assert_eq!(
+            disassembler.format_instruction(&unknown),
+            "CSRRW x0, 500, x4"
+        );
+        assert_eq!(
+            assemble("csrrw x0, 500, x4\ncsrrs x3, cycle, x0")
+                .unwrap()
+                .word_at(1),
+            Some(read.encode().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_unknown_opcode_is_marked_not_nop() {
+        // Opcode field holds -1, which no instruction uses
+        let raw = Tryte::from_i128(-1);
+        let line = Disassembler::new().disassemble_word(7, raw);
+
+        assert_eq!(line.instruction, Err(DecodeError::UnknownOpcode(-1)));
+        assert_eq!(line.text, ".word -1");
+        assert!(line.to_string().ends_with("; unknown opcode -1"));
+    }
+
+    #[test]
+    fn test_range_annotates_branch_targets_with_labels() {
+        let program = assemble(
+            "
+            loop: addi x1, x1, -1
+                  beq  x1, x0, done
+                  jal  x0, loop
+            done: .word 0
+            ",
+        )
+        .unwrap();
+
+        let mut space = AddressSpace::default();
+        program.load_into(&mut space);
+
+        let lines = Disassembler::new()
+            .with_symbols(&program)
+            .disassemble_range(&space, 0..4);
+
+        assert_eq!(lines[0].label.as_deref(), Some("loop"));
+        assert_eq!(lines[1].text, "BEQ x1, x0, 2");
+        assert_eq!(lines[1].target, Some((3, Some("done".to_string()))));
+        assert_eq!(lines[2].target, Some((0, Some("loop".to_string()))));
+        assert_eq!(lines[3].text, "HALT");
+
+        // The listing text assembles back to the same words
+        let source: Vec<String> = lines.iter().map(|line| line.text.clone()).collect();
+        let reassembled = assemble(&source.join("\n")).unwrap();
+        assert!(
+            program
+                .words()
+                .all(|(address, word)| reassembled.word_at(address) == Some(word))
+        );
+
+        let rendered = lines[2].to_string();
+        assert!(rendered.contains(&lines[2].raw.to_trit_string()));
+        assert!(rendered.contains(&lines[2].raw.to_heptavintimal()));
+        assert!(rendered.ends_with("JAL x0, -2  ; -> 0 <loop>"));
+    }
+}
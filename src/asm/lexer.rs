@@ -0,0 +1,172 @@
+use crate::asm::assembler::{AsmError, AsmErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    Directive(String),
+    Number(i128),
+    Comma,
+    Colon,
+    LeftParen,
+    RightParen,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// 1-based column of the first character of the token
+    pub column: usize,
+}
+
+/// Splits a single source line into tokens. Everything after `;` or `#` is a comment.
+pub fn tokenize(line: &str, line_number: usize) -> Result<Vec<Token>, AsmError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        match c {
+            ';' | '#' => break,
+            c if c.is_whitespace() => {
+                i += 1;
+            }
+            ',' | ':' | '(' | ')' => {
+                let kind = match c {
+                    ',' => TokenKind::Comma,
+                    ':' => TokenKind::Colon,
+                    '(' => TokenKind::LeftParen,
+                    _ => TokenKind::RightParen,
+                };
+                tokens.push(Token { kind, column });
+                i += 1;
+            }
+            '-' | '+' | '0'..='9' => {
+                let start = i;
+                if c == '-' || c == '+' {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+
+                let text: String = chars[start..i].iter().collect();
+                let value = parse_number(&text).ok_or(AsmError {
+                    line: line_number,
+                    column,
+                    kind: AsmErrorKind::InvalidNumber(text),
+                })?;
+                tokens.push(Token {
+                    kind: TokenKind::Number(value),
+                    column,
+                });
+            }
+            c if c == '.' || c == '_' || c.is_ascii_alphabetic() => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i] == '_' || chars[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+
+                let text: String = chars[start..i].iter().collect();
+                let kind = if c == '.' {
+                    TokenKind::Directive(text)
+                } else {
+                    TokenKind::Identifier(text)
+                };
+                tokens.push(Token { kind, column });
+            }
+            other => {
+                return Err(AsmError {
+                    line: line_number,
+                    column,
+                    kind: AsmErrorKind::UnexpectedCharacter(other),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a decimal literal or a balanced-ternary literal written as `0t` followed by
+/// `T`, `0` and `1` digits (most significant first), with an optional leading sign.
+pub fn parse_number(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+
+    let magnitude = if let Some(trits) = digits.strip_prefix("0t") {
+        if trits.is_empty() {
+            return None;
+        }
+
+        let mut value: i128 = 0;
+        for digit in trits.chars() {
+            let trit = match digit {
+                'T' => -1,
+                '0' => 0,
+                '1' => 1,
+                _ => return None,
+            };
+            value = value.checked_mul(3)?.checked_add(trit)?;
+        }
+        value
+    } else {
+        digits.parse::<i128>().ok()?
+    };
+
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(line: &str) -> Vec<TokenKind> {
+        tokenize(line, 1)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_instruction_with_label_and_comment() {
+        assert_eq!(
+            kinds("loop: addi x1, x0, -5 ; decrement"),
+            vec![
+                TokenKind::Identifier("loop".into()),
+                TokenKind::Colon,
+                TokenKind::Identifier("addi".into()),
+                TokenKind::Identifier("x1".into()),
+                TokenKind::Comma,
+                TokenKind::Identifier("x0".into()),
+                TokenKind::Comma,
+                TokenKind::Number(-5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_balanced_ternary_literal() {
+        // 1T0 = 9 - 3 + 0 = 6
+        assert_eq!(
+            kinds(".word 0t1T0"),
+            vec![TokenKind::Directive(".word".into()), TokenKind::Number(6)]
+        );
+        assert_eq!(kinds("-0t1T"), vec![TokenKind::Number(-2)]);
+    }
+
+    #[test]
+    fn test_tokenize_reports_column_of_bad_character() {
+        let err = tokenize("  add x1, x2, $3", 7).unwrap_err();
+        assert_eq!(err.line, 7);
+        assert_eq!(err.column, 15);
+        assert_eq!(err.kind, AsmErrorKind::UnexpectedCharacter('$'));
+    }
+}
@@ -0,0 +1,517 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    net::TcpListener,
+    path::Path,
+    process::ExitCode,
+    sync::mpsc,
+};
+
+use eris_bst27i::{
+    asm::{assembler::assemble, lexer::parse_number},
+    core::{address_space::AddressSpace, alu::ArithmeticLogicUnit, bus::Bus, registers::Registers},
+    cpu::{CentralProcessingUnit, StopReason},
+    debugger::{Command, Debugger, format_memory, format_registers, parse_command},
+    devices::{
+        console::{CONSOLE_BASE, CONSOLE_LEN, Console},
+        timer::{TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer},
+    },
+    gdb::GdbStub,
+    image::Image,
+    snapshot::Snapshot,
+    trace::{TraceFormat, TraceWriter},
+};
+use log::{LevelFilter, info};
+
+const USAGE: &str = "\
+Usage:
+  eris run <program.tasm | image.bst> [options]
+  eris asm <program.tasm> [-o <image.bst>]
+  eris debug <program.tasm | image.bst> [--history <N>]
+  eris gdb <program.tasm | image.bst> [-p, --port <PORT>]
+
+Run options:
+  -c, --cycles <N>            Stop after N cycles (default 1000000)
+  -t, --trace                 Print every retired instruction to stderr
+      --trace-format <FORMAT> text or json (JSON lines; implies --trace)
+  -l, --log-level <LEVEL>     off, error, warn, info, debug or trace (default: RUST_LOG or warn)
+  -d, --dump <ADDR[:COUNT]>   Print COUNT memory words from ADDR after the run (repeatable)
+      --restore <FILE>        Resume from a machine snapshot instead of the program's start
+      --save <FILE>           Write a machine snapshot when the run stops
+
+Programs stop at a HALT instruction or when the cycle budget runs out.
+`eris debug` starts an interactive debugger; type `help` at its prompt.
+It keeps the last N cycles (default 100000) so they can be stepped back through.
+`eris gdb` waits for GDB on localhost (default port 1234) and serves one session.
+A console is mapped at address -729 (data) and -728 (status); each tryte
+written to it is printed as the Unicode character with that code point.
+A timer is mapped at -720..-717 (count, compare, control, pending).
+Addresses accept decimal or balanced ternary (`0t1T0`).";
+
+const DEFAULT_CYCLES: u64 = 1_000_000;
+const DEFAULT_GDB_PORT: u16 = 1234;
+const DEFAULT_HISTORY: usize = 100_000;
+
+enum CliError {
+    /// Bad command line: reported together with the usage text
+    Usage(String),
+    Failed(String),
+}
+
+struct RunOptions {
+    path: String,
+    cycles: u64,
+    trace: Option<TraceFormat>,
+    log_level: Option<LevelFilter>,
+    dumps: Vec<(i128, usize)>,
+    /// Snapshot to resume from
+    restore: Option<String>,
+    /// Where to write a snapshot after the run
+    save: Option<String>,
+}
+
+struct AsmOptions {
+    path: String,
+    output: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("run") => parse_run_options(&args[1..]).and_then(|options| run(&options)),
+        Some("asm") => parse_asm_options(&args[1..]).and_then(|options| asm(&options)),
+        Some("debug") => {
+            parse_debug_options(&args[1..]).and_then(|(path, history)| debug(&path, history))
+        }
+        Some("gdb") => parse_gdb_options(&args[1..]).and_then(|(path, port)| gdb(&path, port)),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(CliError::Usage(format!("unknown command '{}'", other))),
+        None => Err(CliError::Usage("missing command".to_string())),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(message)) => {
+            eprintln!("eris: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("eris: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_run_options(args: &[String]) -> Result<RunOptions, CliError> {
+    let mut path = None;
+    let mut options = RunOptions {
+        path: String::new(),
+        cycles: DEFAULT_CYCLES,
+        trace: None,
+        log_level: None,
+        dumps: Vec::new(),
+        restore: None,
+        save: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| CliError::Usage(format!("{} needs a value", name)))
+        };
+
+        match arg.as_str() {
+            "-c" | "--cycles" => {
+                let text = value(arg)?;
+                options.cycles = text
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("invalid cycle count '{}'", text)))?;
+            }
+            "-t" | "--trace" => {
+                options.trace.get_or_insert(TraceFormat::Text);
+            }
+            "--trace-format" => {
+                let text = value(arg)?;
+                options.trace = Some(match text.as_str() {
+                    "text" => TraceFormat::Text,
+                    "json" => TraceFormat::JsonLines,
+                    _ => {
+                        return Err(CliError::Usage(format!("invalid trace format '{}'", text)));
+                    }
+                });
+            }
+            "-l" | "--log-level" => {
+                let text = value(arg)?;
+                options.log_level = Some(
+                    text.parse()
+                        .map_err(|_| CliError::Usage(format!("invalid log level '{}'", text)))?,
+                );
+            }
+            "-d" | "--dump" => options.dumps.push(parse_dump(value(arg)?)?),
+            "--restore" => options.restore = Some(value(arg)?.clone()),
+            "--save" => options.save = Some(value(arg)?.clone()),
+            flag if flag.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option '{}'", flag)));
+            }
+            file if path.is_none() => path = Some(file.to_string()),
+            extra => return Err(CliError::Usage(format!("unexpected argument '{}'", extra))),
+        }
+    }
+
+    options.path = path.ok_or_else(|| CliError::Usage("missing program path".to_string()))?;
+    Ok(options)
+}
+
+fn parse_asm_options(args: &[String]) -> Result<AsmOptions, CliError> {
+    let mut options = AsmOptions {
+        path: String::new(),
+        output: None,
+    };
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let output = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{} needs a value", arg)))?;
+                options.output = Some(output.clone());
+            }
+            flag if flag.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option '{}'", flag)));
+            }
+            file if path.is_none() => path = Some(file.to_string()),
+            extra => return Err(CliError::Usage(format!("unexpected argument '{}'", extra))),
+        }
+    }
+
+    options.path = path.ok_or_else(|| CliError::Usage("missing source path".to_string()))?;
+    Ok(options)
+}
+
+/// Program path and history depth
+fn parse_debug_options(args: &[String]) -> Result<(String, usize), CliError> {
+    let mut path = None;
+    let mut history = DEFAULT_HISTORY;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--history" => {
+                let text = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{} needs a value", arg)))?;
+                history = text
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("invalid history depth '{}'", text)))?;
+            }
+            flag if flag.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option '{}'", flag)));
+            }
+            file if path.is_none() => path = Some(file.to_string()),
+            extra => return Err(CliError::Usage(format!("unexpected argument '{}'", extra))),
+        }
+    }
+
+    let path = path.ok_or_else(|| CliError::Usage("missing program path".to_string()))?;
+    Ok((path, history))
+}
+
+/// Program path and port
+fn parse_gdb_options(args: &[String]) -> Result<(String, u16), CliError> {
+    let mut path = None;
+    let mut port = DEFAULT_GDB_PORT;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" | "--port" => {
+                let text = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{} needs a value", arg)))?;
+                port = text
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("invalid port '{}'", text)))?;
+            }
+            flag if flag.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option '{}'", flag)));
+            }
+            file if path.is_none() => path = Some(file.to_string()),
+            extra => return Err(CliError::Usage(format!("unexpected argument '{}'", extra))),
+        }
+    }
+
+    let path = path.ok_or_else(|| CliError::Usage("missing program path".to_string()))?;
+    Ok((path, port))
+}
+
+/// Parses `ADDR` or `ADDR:COUNT`
+fn parse_dump(text: &str) -> Result<(i128, usize), CliError> {
+    let invalid = || CliError::Usage(format!("invalid dump range '{}'", text));
+
+    let (address, count) = match text.split_once(':') {
+        Some((address, count)) => (address, count.parse().map_err(|_| invalid())?),
+        None => (text, 1),
+    };
+
+    Ok((parse_number(address).ok_or_else(invalid)?, count))
+}
+
+/// Reads either a `.bst` image or assembly source, telling them apart by the image signature.
+fn load_image(path: &str) -> Result<Image, CliError> {
+    let bytes =
+        fs::read(path).map_err(|err| CliError::Failed(format!("cannot read {}: {}", path, err)))?;
+
+    if Image::is_image(&bytes) {
+        return Image::from_bytes(&bytes)
+            .map_err(|err| CliError::Failed(format!("{}: {}", path, err)));
+    }
+
+    let source = String::from_utf8(bytes)
+        .map_err(|_| CliError::Failed(format!("{}: neither an image nor UTF-8 source", path)))?;
+    let program = assemble(&source).map_err(|err| CliError::Failed(format!("{}:{}", path, err)))?;
+
+    Ok(Image::from(&program))
+}
+
+fn init_logging(level: Option<LevelFilter>) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"));
+    if let Some(level) = level {
+        builder.filter_level(level);
+    }
+    builder.init();
+}
+
+fn run(options: &RunOptions) -> Result<(), CliError> {
+    init_logging(options.log_level);
+
+    let image = load_image(&options.path)?;
+    info!("loaded {} words from {}", image.len(), options.path);
+    let mut cpu = build_cpu(&image, Console::stdio())?;
+    if let Some(path) = &options.restore {
+        let bytes = fs::read(path)
+            .map_err(|err| CliError::Failed(format!("cannot read {}: {}", path, err)))?;
+        Snapshot::from_bytes(&bytes)
+            .and_then(|snapshot| snapshot.restore(&mut cpu))
+            .map_err(|err| CliError::Failed(format!("{}: {}", path, err)))?;
+        info!("resumed from {}", path);
+    }
+
+    if let Some(format) = options.trace {
+        cpu.set_tracer(TraceWriter::new(io::stderr(), format));
+    }
+    let outcome = cpu.run(options.cycles);
+
+    match outcome.reason {
+        StopReason::Halted => println!("halted after {} instructions", outcome.retired),
+        StopReason::CycleLimit => println!("cycle limit of {} reached", options.cycles),
+        StopReason::Breakpoint(address) => println!("breakpoint at {}", address.to_i128()),
+        StopReason::Fault(trap) => println!("unhandled trap: {}", trap),
+    }
+
+    println!("{}", format_registers(cpu.registers()));
+    for &(address, count) in &options.dumps {
+        println!("{}", format_memory(cpu.bus(), address, count));
+    }
+    if let Some(path) = &options.save {
+        fs::write(path, Snapshot::capture(&cpu).to_bytes())
+            .map_err(|err| CliError::Failed(format!("cannot write {}: {}", path, err)))?;
+        info!("saved snapshot to {}", path);
+    }
+
+    match outcome.reason {
+        StopReason::Fault(_) => Err(CliError::Failed("program faulted".to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// CPU with the image in RAM, `console` and the timer attached
+fn build_cpu(image: &Image, console: Console) -> Result<CentralProcessingUnit, CliError> {
+    let mut address_space = AddressSpace::default();
+    image.load_into(&mut address_space);
+
+    let mut bus = Bus::from(address_space);
+    bus.attach(CONSOLE_BASE, CONSOLE_LEN, console)
+        .map_err(|err| CliError::Failed(format!("cannot attach console: {}", err)))?;
+    bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+        .map_err(|err| CliError::Failed(format!("cannot attach timer: {}", err)))?;
+
+    Ok(CentralProcessingUnit::from(
+        Registers::default(),
+        bus,
+        ArithmeticLogicUnit::default(),
+    ))
+}
+
+fn debug(path: &str, history: usize) -> Result<(), CliError> {
+    init_logging(None);
+
+    let image = load_image(path)?;
+    // The prompt owns stdin, so the guest console only prints
+    let (_, no_input) = mpsc::channel();
+    let mut cpu = build_cpu(&image, Console::new(io::stdout(), no_input))?;
+    cpu.set_history_depth(history);
+    let mut debugger = Debugger::new(cpu);
+
+    println!("{}", debugger.execute(Command::Disassemble(0)));
+    let mut last = None;
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("(eris) ");
+        let _ = io::stdout().flush();
+
+        let Some(Ok(line)) = lines.next() else { break };
+        let command = if line.trim().is_empty() {
+            // Enter repeats the last command
+            match last {
+                Some(command) => command,
+                None => continue,
+            }
+        } else {
+            match parse_command(&line) {
+                Ok(command) => command,
+                Err(err) => {
+                    println!("{}", err);
+                    continue;
+                }
+            }
+        };
+
+        match command {
+            Command::Quit => break,
+            command => println!("{}", debugger.execute(command)),
+        }
+        last = Some(command);
+    }
+    Ok(())
+}
+
+fn gdb(path: &str, port: u16) -> Result<(), CliError> {
+    init_logging(None);
+
+    let image = load_image(path)?;
+    let cpu = build_cpu(&image, Console::stdio())?;
+    let mut stub = GdbStub::new(Debugger::new(cpu));
+
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|err| CliError::Failed(format!("cannot listen on port {}: {}", port, err)))?;
+    println!("waiting for gdb on 127.0.0.1:{}", port);
+    stub.serve(&listener)
+        .map_err(|err| CliError::Failed(format!("gdb connection failed: {}", err)))
+}
+
+fn asm(options: &AsmOptions) -> Result<(), CliError> {
+    init_logging(None);
+
+    let image = load_image(&options.path)?;
+    let output = options.output.clone().unwrap_or_else(|| {
+        Path::new(&options.path)
+            .with_extension("bst")
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    let bytes = image
+        .to_bytes()
+        .map_err(|err| CliError::Failed(format!("{}: {}", options.path, err)))?;
+    fs::write(&output, bytes)
+        .map_err(|err| CliError::Failed(format!("cannot write {}: {}", output, err)))?;
+    info!("wrote {} words to {}", image.len(), output);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    /// Message of a usage error, panicking on success or any other error
+    fn usage_error<T>(result: Result<T, CliError>) -> String {
+        match result {
+            Err(CliError::Usage(message)) => message,
+            Err(CliError::Failed(message)) => panic!("expected a usage error, got '{}'", message),
+            Ok(_) => panic!("expected a usage error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_run_options() {
+        let Ok(options) = parse_run_options(&args("prog.s")) else {
+            panic!("plain path rejected");
+        };
+        assert_eq!(options.path, "prog.s");
+        assert_eq!(options.cycles, DEFAULT_CYCLES);
+        assert!(options.trace.is_none() && options.log_level.is_none());
+        assert!(options.dumps.is_empty());
+        assert!(options.restore.is_none() && options.save.is_none());
+
+        let Ok(options) = parse_run_options(&args(
+            "-c 500 --trace-format json -l debug -d 100 --dump -3:4 --restore a.snap --save b.snap prog.bst",
+        )) else {
+            panic!("full option set rejected");
+        };
+        assert_eq!(options.path, "prog.bst");
+        assert_eq!(options.cycles, 500);
+        assert_eq!(options.trace, Some(TraceFormat::JsonLines));
+        assert_eq!(options.log_level, Some(LevelFilter::Debug));
+        assert_eq!(options.dumps, [(100, 1), (-3, 4)]);
+        assert_eq!(options.restore.as_deref(), Some("a.snap"));
+        assert_eq!(options.save.as_deref(), Some("b.snap"));
+
+        // --trace keeps a format already chosen
+        let Ok(options) = parse_run_options(&args("--trace-format json --trace prog.s")) else {
+            panic!("--trace after --trace-format rejected");
+        };
+        assert_eq!(options.trace, Some(TraceFormat::JsonLines));
+    }
+
+    #[test]
+    fn test_parse_run_options_errors() {
+        assert_eq!(usage_error(parse_run_options(&[])), "missing program path");
+        assert_eq!(
+            usage_error(parse_run_options(&args("prog.s --cycles"))),
+            "--cycles needs a value"
+        );
+        assert_eq!(
+            usage_error(parse_run_options(&args("-c many prog.s"))),
+            "invalid cycle count 'many'"
+        );
+        assert_eq!(
+            usage_error(parse_run_options(&args("--trace-format xml prog.s"))),
+            "invalid trace format 'xml'"
+        );
+        assert_eq!(
+            usage_error(parse_run_options(&args("--frobnicate prog.s"))),
+            "unknown option '--frobnicate'"
+        );
+        assert_eq!(
+            usage_error(parse_run_options(&args("prog.s other.s"))),
+            "unexpected argument 'other.s'"
+        );
+    }
+
+    #[test]
+    fn test_parse_dump() {
+        assert!(matches!(parse_dump("100"), Ok((100, 1))));
+        assert!(matches!(parse_dump("-3:4"), Ok((-3, 4))));
+        assert!(matches!(parse_dump("0t1T:2"), Ok((2, 2))));
+
+        for text in ["", "abc", "5:", "5:x", "5:-1", ":3"] {
+            assert_eq!(
+                usage_error(parse_dump(text)),
+                format!("invalid dump range '{}'", text)
+            );
+        }
+    }
+}
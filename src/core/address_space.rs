@@ -4,30 +4,130 @@ use crate::arch::trit::{TritField, Tryte};
 
 pub type Address = TritField<27>;
 
+/// Words per page: the low 9 trits of an address select the word, the high
+/// 18 trits select the page
+pub const PAGE_WORDS: usize = 19_683;
+const HALF_PAGE: i128 = (PAGE_WORDS as i128 - 1) / 2;
+
+type Page = Box<[Tryte]>;
+
+/// Word-addressed sparse memory. Pages are allocated on the first nonzero
+/// write and hold packed words, 16 bytes each.
 #[derive(Default)]
 pub struct AddressSpace {
-    mmio: HashMap<Address, Tryte>,
+    pages: HashMap<i64, Page>,
+    /// Number of installed words starting at address 0; `None` maps every address
+    size: Option<i128>,
+}
+
+/// An access the address space could not serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryFault {
+    Unmapped(Address),
+    /// A guest store to ROM
+    ReadOnly(Address),
 }
 
 impl AddressSpace {
+    /// Address space with `size` words installed at addresses `0..size`
+    pub fn with_size(size: i128) -> Self {
+        Self {
+            pages: HashMap::new(),
+            size: Some(size),
+        }
+    }
+
+    pub fn is_mapped(&self, address: Address) -> bool {
+        match self.size {
+            Some(size) => (0..size).contains(&address.to_i128()),
+            None => true,
+        }
+    }
+
+    /// Installed words from address 0, or `None` if every address is mapped
+    pub fn size(&self) -> Option<i128> {
+        self.size
+    }
+
+    /// Every nonzero word, in address order
+    pub fn words(&self) -> Vec<(i128, Tryte)> {
+        let mut pages: Vec<_> = self.pages.iter().collect();
+        pages.sort_by_key(|(page, _)| **page);
+
+        let mut words = Vec::new();
+        for (page, page_words) in pages {
+            let first = *page as i128 * PAGE_WORDS as i128 - HALF_PAGE;
+            for (offset, word) in page_words.iter().enumerate() {
+                if !word.is_zero() {
+                    words.push((first + offset as i128, *word));
+                }
+            }
+        }
+        words
+    }
+
+    /// Number of pages backed by host memory
+    pub fn allocated_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Host-side read; never faults
     pub fn read(&self, address: Address) -> TritField<27> {
-        self.mmio.get(&address).copied().unwrap_or_default()
+        let (page, offset) = split(address);
+        self.pages
+            .get(&page)
+            .map(|words| words[offset])
+            .unwrap_or_default()
     }
 
+    /// Host-side write; never faults
     pub fn write(&mut self, address: Address, value: Tryte) {
-        self.mmio.insert(address, value);
+        let (page, offset) = split(address);
+        if let Some(words) = self.pages.get_mut(&page) {
+            words[offset] = value;
+        } else if !value.is_zero() {
+            // Absent pages already read as zero
+            let mut words: Page = vec![Tryte::default(); PAGE_WORDS].into_boxed_slice();
+            words[offset] = value;
+            self.pages.insert(page, words);
+        }
+    }
+
+    /// Guest read, faulting on addresses outside installed memory
+    pub fn load(&self, address: Address) -> Result<Tryte, MemoryFault> {
+        if !self.is_mapped(address) {
+            return Err(MemoryFault::Unmapped(address));
+        }
+        Ok(self.read(address))
+    }
+
+    /// Guest write, faulting on addresses outside installed memory
+    pub fn store(&mut self, address: Address, value: Tryte) -> Result<(), MemoryFault> {
+        if !self.is_mapped(address) {
+            return Err(MemoryFault::Unmapped(address));
+        }
+        self.write(address, value);
+        Ok(())
     }
 }
 
+/// Page number and word offset of an address. Page `p` holds the addresses
+/// `p * 3^9 - 9841 ..= p * 3^9 + 9841`.
+fn split(address: Address) -> (i64, usize) {
+    let biased = address.to_i128() + HALF_PAGE;
+    let page = biased.div_euclid(PAGE_WORDS as i128) as i64;
+    let offset = biased.rem_euclid(PAGE_WORDS as i128) as usize;
+    (page, offset)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arch::trit::TRYTE_MAX;
 
     #[test]
     fn test_write_and_read_success() {
-        let mut space = AddressSpace {
-            mmio: HashMap::new(),
-        };
+        let mut space = AddressSpace::default();
 
         let addr = Address::from_i128(12345); // Assuming TritField can be created from an integer
         let val = Tryte::from_i128(42);
@@ -43,9 +143,7 @@ mod tests {
 
     #[test]
     fn test_read_empty_address_returns_default() {
-        let space = AddressSpace {
-            mmio: HashMap::new(),
-        };
+        let space = AddressSpace::default();
 
         let addr = Address::from_i128(999);
         let result = space.read(addr);
@@ -60,9 +158,7 @@ mod tests {
 
     #[test]
     fn test_overwrite_address() {
-        let mut space = AddressSpace {
-            mmio: HashMap::new(),
-        };
+        let mut space = AddressSpace::default();
 
         let addr = Address::from_i128(55);
         let first_val = Tryte::from_i128(10);
@@ -80,9 +176,7 @@ mod tests {
 
     #[test]
     fn test_multiple_addresses_independence() {
-        let mut space = AddressSpace {
-            mmio: HashMap::new(),
-        };
+        let mut space = AddressSpace::default();
 
         let addr_a = Address::from_i128(1);
         let addr_b = Address::from_i128(2);
@@ -95,4 +189,64 @@ mod tests {
         assert_eq!(space.read(addr_a), val_a);
         assert_eq!(space.read(addr_b), val_b);
     }
+
+    #[test]
+    fn test_sized_space_faults_outside_installed_memory() {
+        let mut space = AddressSpace::with_size(10);
+
+        assert_eq!(
+            space.store(Address::from_i128(9), Tryte::from_i128(3)),
+            Ok(())
+        );
+        assert_eq!(space.load(Address::from_i128(9)), Ok(Tryte::from_i128(3)));
+
+        for outside in [10, -1] {
+            let addr = Address::from_i128(outside);
+            assert_eq!(space.load(addr), Err(MemoryFault::Unmapped(addr)));
+            assert_eq!(
+                space.store(addr, Tryte::from_i128(1)),
+                Err(MemoryFault::Unmapped(addr))
+            );
+        }
+
+        // Unsized spaces map every address
+        let unsized_space = AddressSpace::default();
+        assert!(unsized_space.load(Address::from_i128(-1_000_000)).is_ok());
+    }
+
+    #[test]
+    fn test_words_either_side_of_page_boundaries() {
+        let mut space = AddressSpace::default();
+        let addresses = [-9842, -9841, 0, 9841, 9842, 1_000_000_000_000, -TRYTE_MAX];
+
+        for (i, &address) in addresses.iter().enumerate() {
+            space.write(Address::from_i128(address), Tryte::from_i128(i as i128 + 1));
+        }
+        for (i, &address) in addresses.iter().enumerate() {
+            assert_eq!(
+                space.read(Address::from_i128(address)).to_i128(),
+                i as i128 + 1
+            );
+        }
+        assert_eq!(split(Address::from_i128(9841)), (0, PAGE_WORDS - 1));
+        assert_eq!(split(Address::from_i128(9842)), (1, 0));
+        assert_eq!(space.allocated_pages(), 5);
+    }
+
+    #[test]
+    fn test_pages_are_allocated_on_first_nonzero_write() {
+        let mut space = AddressSpace::default();
+
+        space.write(Address::from_i128(5), Tryte::default());
+        assert_eq!(space.allocated_pages(), 0);
+
+        space.write(Address::from_i128(5), Tryte::from_i128(7));
+        space.write(Address::from_i128(6), Tryte::from_i128(8));
+        assert_eq!(space.allocated_pages(), 1);
+
+        // Clearing a word keeps its page
+        space.write(Address::from_i128(5), Tryte::default());
+        assert_eq!(space.read(Address::from_i128(5)), Tryte::default());
+        assert_eq!(space.allocated_pages(), 1);
+    }
 }
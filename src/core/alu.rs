@@ -1,6 +1,7 @@
 use crate::arch::{
     circuits::ErisCircuit,
-    instructions::AluOp,
+    fast_arith,
+    instructions::{AluOp, UPPER_SHIFT},
     trit::{Trit, TritField, Tryte},
 };
 
@@ -9,8 +10,14 @@ pub struct ArithmeticLogicUnit {
     circuit: ErisCircuit,
     pub result: Tryte,
     pub zero_flag: Trit,
+    /// Sign of the exact result: Negative, Zero or Positive. For add and subtract
+    /// it includes the carry out of the top trit, so it is right even on overflow.
+    pub sign_flag: Trit,
+    /// Carry out of the top trit of the last add or subtract
+    pub carry_flag: Trit,
     input_a: Tryte,
     input_b: Tryte,
+    carry_in: Trit,
     alu_ctrl: AluOp,
 }
 
@@ -21,6 +28,11 @@ impl ArithmeticLogicUnit {
         self.alu_ctrl = alu_ctrl;
     }
 
+    /// Carry trit fed into the lowest trit of the next add or subtract
+    pub fn alu_set_carry_in(&mut self, carry_in: Trit) {
+        self.carry_in = carry_in;
+    }
+
     pub fn alu_exec(&mut self) {
         match self.alu_ctrl {
             AluOp::Add => self.add(),
@@ -28,6 +40,37 @@ impl ArithmeticLogicUnit {
             AluOp::PassB => {
                 self.result = self.input_b;
             }
+            AluOp::Min => self.tritwise(ErisCircuit::min),
+            AluOp::Max => self.tritwise(ErisCircuit::max),
+            AluOp::Consensus => self.tritwise(ErisCircuit::consensus),
+            AluOp::Antimin => self.tritwise(ErisCircuit::antimin),
+            AluOp::Sum => self.tritwise(ErisCircuit::sum),
+            AluOp::Neg => self.tritwise(|circuit, a, _| circuit.not(a)),
+            AluOp::ShiftLeft => self.shift(self.input_b.to_i128()),
+            AluOp::ShiftRight => self.shift(-self.input_b.to_i128()),
+            AluOp::RotateLeft => self.rotate(self.input_b.to_i128()),
+            AluOp::RotateRight => self.rotate(-self.input_b.to_i128()),
+            AluOp::LoadUpper => {
+                self.input_a = self.input_b;
+                self.shift(UPPER_SHIFT as i128);
+            }
+            AluOp::Mul => {
+                let product = self.multiply();
+                self.set_result(low_half(&product));
+            }
+            AluOp::MulHigh => {
+                let product = self.multiply();
+                self.set_result(high_half(&product));
+            }
+            AluOp::Div => {
+                let (quotient, _) = self.divide();
+                self.set_result(quotient);
+            }
+            AluOp::Rem => {
+                let (_, remainder) = self.divide();
+                self.set_result(remainder);
+            }
+            AluOp::SubMagnitude => self.sub_magnitude(),
             AluOp::None => {}
         }
     }
@@ -35,6 +78,9 @@ impl ArithmeticLogicUnit {
     pub fn alu_reset(&mut self) {
         self.result = TritField::default();
         self.zero_flag = Trit::default();
+        self.sign_flag = Trit::default();
+        self.carry_flag = Trit::default();
+        self.carry_in = Trit::default();
         self.input_a = TritField::default();
         self.input_b = TritField::default();
         self.alu_ctrl = AluOp::default();
@@ -44,64 +90,396 @@ impl ArithmeticLogicUnit {
 impl ArithmeticLogicUnit {
     /// Adds input_a and input_b, storing the result and setting flags.
     pub fn add(&mut self) {
-        let mut carry = Trit::Zero;
-        let mut is_zero_result = true;
+        let (sum, carry) = self.adder(&self.input_a, &self.input_b);
+        self.set_sum(sum, carry);
+    }
+
+    /// Subtracts input_b from input_a (A - B)
+    /// Logic: A + (-B). The carry-in is added, not subtracted: carries are
+    /// signed trits, so chaining words works the same way as for add.
+    pub fn sub(&mut self) {
+        // In Balanced Ternary, negation is just inverting every Trit.
+        let b_negated = self.circuit.negate(&self.input_b);
+        let (sum, carry) = self.adder(&self.input_a, &b_negated);
+        self.set_sum(sum, carry);
+    }
+
+    /// Ripple-carry adder, or the word-level path with the `fast-arith` feature.
+    fn adder(&self, input_a: &Tryte, input_b: &Tryte) -> (Tryte, Trit) {
+        if cfg!(feature = "fast-arith") {
+            fast_arith::add(input_a, input_b, self.carry_in)
+        } else {
+            self.circuit
+                .ripple_add_with_carry(input_a, input_b, self.carry_in)
+        }
+    }
+
+    fn set_sum(&mut self, sum: Tryte, carry: Trit) {
+        self.set_result(sum);
+        self.carry_flag = carry;
+        if carry != Trit::Zero {
+            self.sign_flag = carry;
+        }
+    }
+
+    /// Moves every trit of input_a `amount` places towards the most significant end
+    /// (towards the least significant end if negative), filling with Zero.
+    ///
+    /// Left shifts multiply by 3^amount. Right shifts divide by 3^amount truncating
+    /// toward zero: dropping the low trits alone would round to the nearest integer,
+    /// so the result steps one toward zero when the dropped trits have the opposite
+    /// sign to the kept ones.
+    fn shift(&mut self, amount: i128) {
+        let mut result = Tryte::default();
+        for i in 0..27 {
+            let source = i as i128 - amount;
+            if (0..27).contains(&source) {
+                result.set_trit(i, self.input_a.trit(source as usize));
+            }
+        }
+
+        if amount < 0 {
+            let dropped = (-amount).min(27) as usize;
+            let dropped_sign = leading_trit(&self.input_a.to_trits()[..dropped]);
+            if dropped_sign.to_i8() * self.circuit.sign(&result).to_i8() < 0 {
+                result = Tryte::from_i128(result.to_i128() + dropped_sign.to_i8() as i128);
+            }
+        }
+        self.set_result(result);
+    }
 
-        // Iterate from Least Significant Trit (0) to Most Significant (26)
+    /// Like `shift`, but trits leaving one end re-enter at the other.
+    fn rotate(&mut self, amount: i128) {
+        let mut result = Tryte::default();
         for i in 0..27 {
-            let a = self.input_a.0[i];
-            let b = self.input_b.0[i];
+            let source = (i as i128 - amount).rem_euclid(27) as usize;
+            result.set_trit(i, self.input_a.trit(source));
+        }
+        self.set_result(result);
+    }
+
+    /// Shift-and-add multiplier: one partial product per trit of input_b,
+    /// accumulated in a double-width register. The full product always fits.
+    fn multiply(&self) -> TritField<54> {
+        let mut product = TritField::<54>::default();
+
+        for (shift, multiplier) in self.input_b.trits().enumerate() {
+            if multiplier == Trit::Zero {
+                continue;
+            }
+
+            let mut partial = TritField::<54>::default();
+            for (i, trit) in self.input_a.trits().enumerate() {
+                partial.set_trit(i + shift, self.circuit.mul(trit, multiplier));
+            }
+            product = self.circuit.ripple_add(&product, &partial).0;
+        }
+
+        product
+    }
+
+    /// Balanced long division, one quotient trit per step from the top.
+    ///
+    /// Each step picks the quotient trit that brings the partial remainder closest
+    /// to zero, so the quotient is rounded to the nearest integer and the remainder
+    /// satisfies |r| <= |d| / 2 (either sign is possible when an even divisor leaves
+    /// exactly |d| / 2). Dividing by zero yields quotient 0 and remainder A, which
+    /// keeps A = B * q + r true.
+    fn divide(&self) -> (Tryte, Tryte) {
+        let circuit = &self.circuit;
+        let mut quotient = Tryte::default();
+        let mut remainder = widen(&self.input_a);
+
+        let divisor_sign = circuit.sign(&self.input_b);
+        if divisor_sign == Trit::Zero {
+            return (quotient, self.input_a);
+        }
+        let divisor = widen(&self.input_b);
+        let divisor_abs = if divisor_sign == Trit::Negative {
+            circuit.negate(&divisor)
+        } else {
+            divisor
+        };
 
-            // Use the circuit's full adder
-            let (sum, new_carry) = self.circuit.full_trit_adder(a, b, carry);
+        for i in (0..27).rev() {
+            let remainder_sign = circuit.sign(&remainder);
+            if remainder_sign == Trit::Zero {
+                break;
+            }
 
-            if sum != Trit::Zero {
-                is_zero_result = false;
+            // |d| * 3^i
+            let mut step = TritField::<54>::default();
+            for j in 0..27 {
+                step.set_trit(i + j, divisor_abs.trit(j));
+            }
+
+            // Take the trit only if 2|r| > |d| * 3^i
+            let remainder_abs = if remainder_sign == Trit::Negative {
+                circuit.negate(&remainder)
+            } else {
+                remainder
+            };
+            let twice = circuit.ripple_add(&remainder_abs, &remainder_abs).0;
+            let margin = circuit.ripple_add(&twice, &circuit.negate(&step)).0;
+            if circuit.sign(&margin) != Trit::Positive {
+                continue;
             }
 
-            self.result.0[i] = sum;
-            carry = new_carry;
+            quotient.set_trit(i, circuit.mul(remainder_sign, divisor_sign));
+            let towards_zero = if remainder_sign == Trit::Positive {
+                circuit.negate(&step)
+            } else {
+                step
+            };
+            remainder = circuit.ripple_add(&remainder, &towards_zero).0;
         }
 
-        self.zero_flag = if is_zero_result {
+        (quotient, low_half(&remainder))
+    }
+
+    /// Stores the result and sets the zero flag from it.
+    fn set_result(&mut self, result: Tryte) {
+        self.result = result;
+        self.zero_flag = if result.is_zero() {
             Trit::Positive
         } else {
             Trit::Zero
         };
+        self.sign_flag = self.circuit.sign(&result);
     }
 
-    /// Subtracts input_b from input_a (A - B)
-    /// Logic: A + (-B)
-    pub fn sub(&mut self) {
-        let mut carry = Trit::Zero;
-        let mut is_zero_result = true;
+    /// Subtracts |input_b| from |input_a|; the difference always fits in a word.
+    fn sub_magnitude(&mut self) {
+        let magnitude = |word: &Tryte| {
+            if self.circuit.sign(word) == Trit::Negative {
+                self.circuit.negate(word)
+            } else {
+                *word
+            }
+        };
+        let a = magnitude(&self.input_a);
+        let b = magnitude(&self.input_b);
+
+        let (difference, _) = self.circuit.ripple_add(&a, &self.circuit.negate(&b));
+        self.set_result(difference);
+    }
 
+    /// Applies a two-input gate to every trit position of input_a and input_b.
+    fn tritwise(&mut self, gate: fn(&ErisCircuit, Trit, Trit) -> Trit) {
+        let mut result = Tryte::default();
         for i in 0..27 {
-            let a = self.input_a.0[i];
-            let b = self.input_b.0[i];
-
-            // In Balanced Ternary, negation is just inverting the Trit.
-            // 1 becomes -1, -1 becomes 1, 0 stays 0.
-            let b_negated = match b {
-                Trit::Positive => Trit::Negative,
-                Trit::Zero => Trit::Zero,
-                Trit::Negative => Trit::Positive,
-            };
+            result.set_trit(
+                i,
+                gate(&self.circuit, self.input_a.trit(i), self.input_b.trit(i)),
+            );
+        }
+        self.set_result(result);
+    }
+}
+
+/// Sign-extends a word into a double-width register; in balanced ternary
+/// that is just zero-filling the upper trits.
+fn widen(word: &Tryte) -> TritField<54> {
+    let mut wide = TritField::<54>::default();
+    for (i, trit) in word.trits().enumerate() {
+        wide.set_trit(i, trit);
+    }
+    wide
+}
+
+/// Sign of the value held in `trits`, least significant first
+fn leading_trit(trits: &[Trit]) -> Trit {
+    trits
+        .iter()
+        .rev()
+        .copied()
+        .find(|trit| *trit != Trit::Zero)
+        .unwrap_or(Trit::Zero)
+}
+
+fn low_half(wide: &TritField<54>) -> Tryte {
+    TritField::from_trits(std::array::from_fn(|i| wide.trit(i)))
+}
+
+fn high_half(wide: &TritField<54>) -> Tryte {
+    TritField::from_trits(std::array::from_fn(|i| wide.trit(27 + i)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::trit::TRYTE_MAX;
 
-            let (sum, new_carry) = self.circuit.full_trit_adder(a, b_negated, carry);
+    fn exec(a: i128, b: i128, op: AluOp) -> ArithmeticLogicUnit {
+        let mut alu = ArithmeticLogicUnit::default();
+        alu.alu_set(Tryte::from_i128(a), Tryte::from_i128(b), op);
+        alu.alu_exec();
+        alu
+    }
+
+    #[test]
+    fn test_logic_ops_apply_per_trit() {
+        // 0t1T0 = 6, 0t011 = 4
+        assert_eq!(exec(6, 4, AluOp::Min).result.to_trit_string()[24..], *"0T0");
+        assert_eq!(exec(6, 4, AluOp::Max).result.to_trit_string()[24..], *"111");
+        assert_eq!(
+            exec(6, 4, AluOp::Consensus).result.to_trit_string()[24..],
+            *"000"
+        );
+        assert_eq!(
+            exec(6, 4, AluOp::Antimin).result.to_trit_string()[24..],
+            *"010"
+        );
+        assert_eq!(exec(6, 4, AluOp::Sum).result.to_trit_string()[24..], *"101");
+    }
 
-            if sum != Trit::Zero {
-                is_zero_result = false;
+    #[test]
+    fn test_neg_is_arithmetic_negation() {
+        for value in [0, 1, -13, 797_161, -TRYTE_MAX] {
+            assert_eq!(exec(value, 99, AluOp::Neg).result.to_i128(), -value);
+        }
+    }
+
+    #[test]
+    fn test_shifts_scale_by_powers_of_three() {
+        assert_eq!(exec(5, 3, AluOp::ShiftLeft).result.to_i128(), 135);
+        assert_eq!(exec(-7, 2, AluOp::ShiftLeft).result.to_i128(), -63);
+        assert_eq!(exec(19, 1, AluOp::ShiftRight).result.to_i128(), 6);
+        assert_eq!(exec(19, 2, AluOp::ShiftRight).result.to_i128(), 2);
+        assert_eq!(exec(-19, 2, AluOp::ShiftRight).result.to_i128(), -2);
+        // Negative counts shift the other way, oversized counts clear the word
+        assert_eq!(exec(19, -1, AluOp::ShiftRight).result.to_i128(), 57);
+        assert_eq!(exec(19, 27, AluOp::ShiftLeft).result.to_i128(), 0);
+        assert_eq!(exec(19, 27, AluOp::ShiftLeft).zero_flag, Trit::Positive);
+    }
+
+    #[test]
+    fn test_right_shifts_truncate_toward_zero() {
+        // 0t1TT = 5 would round to 0t1T = 2; truncation gives 1
+        assert_eq!(exec(5, 1, AluOp::ShiftRight).result.to_i128(), 1);
+        assert_eq!(exec(-5, 1, AluOp::ShiftRight).result.to_i128(), -1);
+        assert_eq!(exec(2, 1, AluOp::ShiftRight).result.to_i128(), 0);
+        assert_eq!(exec(-2, 1, AluOp::ShiftRight).result.to_i128(), 0);
+        assert_eq!(exec(-2, 1, AluOp::ShiftRight).zero_flag, Trit::Positive);
+        for value in [-1000, -247, -13, 7, 100, TRYTE_MAX] {
+            for count in 0..8 {
+                assert_eq!(
+                    exec(value, count, AluOp::ShiftRight).result.to_i128(),
+                    value / 3_i128.pow(count as u32),
+                    "{} >> {}",
+                    value,
+                    count
+                );
             }
+        }
+        assert_eq!(exec(-1000, 2, AluOp::ShiftLeft).result.to_i128(), -9000);
+        assert_eq!(exec(-1000, -2, AluOp::ShiftLeft).result.to_i128(), -111);
+    }
+
+    #[test]
+    fn test_rotates_wrap_around() {
+        let top = 3_i128.pow(26);
+        assert_eq!(exec(top, 1, AluOp::RotateLeft).result.to_i128(), 1);
+        assert_eq!(exec(-1, 1, AluOp::RotateRight).result.to_i128(), -top);
+        assert_eq!(exec(19, 28, AluOp::RotateLeft).result.to_i128(), 57);
+        assert_eq!(
+            exec(19, -3, AluOp::RotateLeft).result.to_i128(),
+            exec(19, 3, AluOp::RotateRight).result.to_i128()
+        );
+    }
+
+    #[test]
+    fn test_mul_and_mulh_split_the_full_product() {
+        let cases = [
+            (0, 5),
+            (7, -6),
+            (-1234, -5678),
+            (TRYTE_MAX, TRYTE_MAX),
+            (TRYTE_MAX, -TRYTE_MAX),
+            (1 << 40, 3),
+        ];
 
-            self.result.0[i] = sum;
-            carry = new_carry;
+        for (a, b) in cases {
+            let low = exec(a, b, AluOp::Mul).result.to_i128();
+            let high = exec(a, b, AluOp::MulHigh).result.to_i128();
+            assert_eq!(high * 3_i128.pow(27) + low, a * b, "{} * {}", a, b);
+            assert!(low.abs() <= TRYTE_MAX);
         }
+        assert_eq!(exec(7, -6, AluOp::Mul).result.to_i128(), -42);
+    }
 
-        self.zero_flag = if is_zero_result {
-            Trit::Positive
-        } else {
-            Trit::Zero
-        };
+    #[test]
+    fn test_div_rounds_to_nearest() {
+        let dividends = [
+            0, 1, -1, 7, -7, 13, 100, -100, 1_000_003, TRYTE_MAX, -TRYTE_MAX,
+        ];
+        let divisors = [1, -1, 2, 3, -3, 4, 5, -7, 10, 729, TRYTE_MAX];
+
+        for a in dividends {
+            for d in divisors {
+                let q = exec(a, d, AluOp::Div).result.to_i128();
+                let r = exec(a, d, AluOp::Rem).result.to_i128();
+                assert_eq!(q * d + r, a, "{} / {}", a, d);
+                assert!(2 * r.abs() <= d.abs(), "{} % {} = {}", a, d, r);
+            }
+        }
+
+        assert_eq!(exec(7, 3, AluOp::Div).result.to_i128(), 2);
+        assert_eq!(exec(8, 3, AluOp::Div).result.to_i128(), 3);
+        assert_eq!(exec(8, 3, AluOp::Rem).result.to_i128(), -1);
+        assert_eq!(exec(-8, 3, AluOp::Div).result.to_i128(), -3);
+    }
+
+    #[test]
+    fn test_divide_by_zero() {
+        assert_eq!(exec(42, 0, AluOp::Div).result.to_i128(), 0);
+        assert_eq!(exec(42, 0, AluOp::Rem).result.to_i128(), 42);
+    }
+
+    #[test]
+    fn test_sign_flag_survives_overflow() {
+        assert_eq!(exec(3, 5, AluOp::Sub).sign_flag, Trit::Negative);
+        assert_eq!(exec(5, 5, AluOp::Sub).sign_flag, Trit::Zero);
+        // The difference wraps to a negative word, but the flag keeps the true sign
+        let wrapped = exec(TRYTE_MAX, -TRYTE_MAX, AluOp::Sub);
+        assert!(wrapped.result.to_i128() < 0);
+        assert_eq!(wrapped.sign_flag, Trit::Positive);
+        assert_eq!(
+            exec(-TRYTE_MAX, -TRYTE_MAX, AluOp::Add).sign_flag,
+            Trit::Negative
+        );
+    }
+
+    #[test]
+    fn test_carry_chains_across_words() {
+        let word = 3_i128.pow(27);
+
+        // (TRYTE_MAX + TRYTE_MAX) spills one carry trit out of the low word
+        let low = exec(TRYTE_MAX, TRYTE_MAX, AluOp::Add);
+        assert_eq!(low.carry_flag, Trit::Positive);
+        assert_eq!(low.result.to_i128() + word, 2 * TRYTE_MAX);
+
+        let mut high = ArithmeticLogicUnit::default();
+        high.alu_set(Tryte::from_i128(4), Tryte::from_i128(-6), AluOp::Sub);
+        high.alu_set_carry_in(low.carry_flag);
+        high.alu_exec();
+        assert_eq!(high.result.to_i128(), 11);
+        assert_eq!(high.carry_flag, Trit::Zero);
+
+        assert_eq!(exec(-TRYTE_MAX, 1, AluOp::Sub).carry_flag, Trit::Negative);
+    }
+
+    #[test]
+    fn test_sub_magnitude_compares_absolute_values() {
+        assert_eq!(exec(-7, 5, AluOp::SubMagnitude).result.to_i128(), 2);
+        assert_eq!(exec(3, -9, AluOp::SubMagnitude).sign_flag, Trit::Negative);
+        assert_eq!(exec(-4, 4, AluOp::SubMagnitude).zero_flag, Trit::Positive);
+    }
+
+    #[test]
+    fn test_logic_ops_set_zero_flag() {
+        assert_eq!(exec(5, -5, AluOp::Consensus).zero_flag, Trit::Positive);
+        assert_eq!(exec(5, -5, AluOp::Max).zero_flag, Trit::Zero);
+        assert_eq!(exec(3, 3, AluOp::Sub).zero_flag, Trit::Positive);
     }
 }
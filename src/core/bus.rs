@@ -0,0 +1,436 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use crate::{
+    arch::trit::Tryte,
+    core::{
+        address_space::{Address, AddressSpace, MemoryFault},
+        interrupts::INTERRUPT_LINES,
+    },
+};
+
+/// A memory-mapped peripheral. Offsets are relative to the start of the
+/// range the device is attached at.
+pub trait Device {
+    fn read(&mut self, offset: i128) -> Tryte;
+    fn write(&mut self, offset: i128, value: Tryte);
+    /// Called once per CPU cycle
+    fn tick(&mut self) {}
+    /// Whether the device's interrupt line is raised
+    fn interrupt(&self) -> bool {
+        false
+    }
+    /// Internal state to keep in a machine snapshot
+    fn save(&self) -> Vec<Tryte> {
+        Vec::new()
+    }
+    /// Restores state produced by `save`
+    fn restore(&mut self, _state: &[Tryte]) {}
+}
+
+enum Target {
+    Rom(Vec<Tryte>),
+    Device(Box<dyn Device>),
+}
+
+struct Region {
+    range: Range<i128>,
+    target: Target,
+    /// Interrupt line the device raises
+    line: Option<usize>,
+}
+
+/// A range could not be registered on the bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BusError {
+    EmptyRange(Range<i128>),
+    /// Interrupt lines are numbered `0..INTERRUPT_LINES`
+    InvalidLine(usize),
+    Overlap {
+        range: Range<i128>,
+        existing: Range<i128>,
+    },
+}
+
+impl Display for BusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BusError::EmptyRange(range) => {
+                write!(f, "empty range {}..{}", range.start, range.end)
+            }
+            BusError::InvalidLine(line) => write!(f, "no interrupt line {}", line),
+            BusError::Overlap { range, existing } => write!(
+                f,
+                "range {}..{} overlaps {}..{}",
+                range.start, range.end, existing.start, existing.end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BusError {}
+
+/// Routes CPU accesses to ROM and device ranges, and everything else to RAM.
+/// Registered ranges take priority over RAM and may not overlap each other.
+pub struct Bus {
+    ram: AddressSpace,
+    regions: Vec<Region>,
+}
+
+impl From<AddressSpace> for Bus {
+    fn from(ram: AddressSpace) -> Self {
+        Self {
+            ram,
+            regions: Vec::new(),
+        }
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::from(AddressSpace::default())
+    }
+}
+
+impl Bus {
+    pub fn ram(&self) -> &AddressSpace {
+        &self.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut AddressSpace {
+        &mut self.ram
+    }
+
+    /// Maps `words` read-only starting at `start`; guest stores to it fault
+    pub fn map_rom(&mut self, start: i128, words: Vec<Tryte>) -> Result<(), BusError> {
+        let range = start..start + words.len() as i128;
+        self.register(range, Target::Rom(words), None)
+    }
+
+    /// Attaches `device` to the `len` words starting at `start`. Its interrupt
+    /// line, if it has one, is not connected.
+    pub fn attach(
+        &mut self,
+        start: i128,
+        len: i128,
+        device: impl Device + 'static,
+    ) -> Result<(), BusError> {
+        self.register(start..start + len, Target::Device(Box::new(device)), None)
+    }
+
+    /// Attaches `device` like `attach`, wiring its interrupt to `line`.
+    /// Several devices may share a line.
+    pub fn attach_interrupting(
+        &mut self,
+        start: i128,
+        len: i128,
+        line: usize,
+        device: impl Device + 'static,
+    ) -> Result<(), BusError> {
+        if line >= INTERRUPT_LINES {
+            return Err(BusError::InvalidLine(line));
+        }
+        self.register(
+            start..start + len,
+            Target::Device(Box::new(device)),
+            Some(line),
+        )
+    }
+
+    fn register(
+        &mut self,
+        range: Range<i128>,
+        target: Target,
+        line: Option<usize>,
+    ) -> Result<(), BusError> {
+        if range.is_empty() {
+            return Err(BusError::EmptyRange(range));
+        }
+        if let Some(region) = self
+            .regions
+            .iter()
+            .find(|region| range.start < region.range.end && region.range.start < range.end)
+        {
+            return Err(BusError::Overlap {
+                range,
+                existing: region.range.clone(),
+            });
+        }
+
+        self.regions.push(Region {
+            range,
+            target,
+            line,
+        });
+        Ok(())
+    }
+
+    fn region_mut(&mut self, address: i128) -> Option<&mut Region> {
+        self.regions
+            .iter_mut()
+            .find(|region| region.range.contains(&address))
+    }
+
+    /// Host-side read; never faults. Device registers read as zero, since
+    /// reading them may have side effects.
+    pub fn read(&self, address: Address) -> Tryte {
+        let value = address.to_i128();
+        match self
+            .regions
+            .iter()
+            .find(|region| region.range.contains(&value))
+        {
+            Some(Region {
+                range,
+                target: Target::Rom(words),
+                ..
+            }) => words[(value - range.start) as usize],
+            Some(_) => Tryte::default(),
+            None => self.ram.read(address),
+        }
+    }
+
+    /// Host-side write to RAM or ROM; never faults. Device ranges are skipped.
+    pub fn write(&mut self, address: Address, value: Tryte) {
+        let position = address.to_i128();
+        match self.region_mut(position) {
+            Some(Region {
+                range,
+                target: Target::Rom(words),
+                ..
+            }) => words[(position - range.start) as usize] = value,
+            Some(_) => {}
+            None => self.ram.write(address, value),
+        }
+    }
+
+    /// Guest read
+    pub fn load(&mut self, address: Address) -> Result<Tryte, MemoryFault> {
+        let position = address.to_i128();
+        match self.region_mut(position) {
+            Some(Region { range, target, .. }) => {
+                let offset = position - range.start;
+                Ok(match target {
+                    Target::Rom(words) => words[offset as usize],
+                    Target::Device(device) => device.read(offset),
+                })
+            }
+            None => self.ram.load(address),
+        }
+    }
+
+    /// Guest write, faulting on ROM
+    pub fn store(&mut self, address: Address, value: Tryte) -> Result<(), MemoryFault> {
+        let position = address.to_i128();
+        match self.region_mut(position) {
+            Some(Region {
+                target: Target::Rom(_),
+                ..
+            }) => Err(MemoryFault::ReadOnly(address)),
+            Some(Region {
+                range,
+                target: Target::Device(device),
+                ..
+            }) => {
+                device.write(position - range.start, value);
+                Ok(())
+            }
+            None => self.ram.store(address, value),
+        }
+    }
+
+    /// Connected interrupt lines currently raised, bit `i` being line `i`
+    pub fn raised_lines(&self) -> u32 {
+        let mut lines = 0;
+        for region in &self.regions {
+            if let (Some(line), Target::Device(device)) = (region.line, &region.target)
+                && device.interrupt()
+            {
+                lines |= 1 << line;
+            }
+        }
+        lines
+    }
+
+    /// ROM and device ranges in the order they were registered
+    pub fn region_ranges(&self) -> Vec<Range<i128>> {
+        self.regions
+            .iter()
+            .map(|region| region.range.clone())
+            .collect()
+    }
+
+    /// State of each region, in `region_ranges` order: ROM contents, or
+    /// what the device saves
+    pub fn save_regions(&self) -> Vec<Vec<Tryte>> {
+        self.regions
+            .iter()
+            .map(|region| match &region.target {
+                Target::Rom(words) => words.clone(),
+                Target::Device(device) => device.save(),
+            })
+            .collect()
+    }
+
+    /// Restores `save_regions` output onto a bus with the same regions
+    pub fn restore_regions(&mut self, states: &[Vec<Tryte>]) {
+        for (region, state) in self.regions.iter_mut().zip(states) {
+            match &mut region.target {
+                Target::Rom(words) => {
+                    let len = words.len().min(state.len());
+                    words[..len].copy_from_slice(&state[..len]);
+                }
+                Target::Device(device) => device.restore(state),
+            }
+        }
+    }
+
+    /// `Device::save` of every attached device, in registration order
+    pub fn save_devices(&self) -> Vec<Vec<Tryte>> {
+        self.devices().map(|device| device.save()).collect()
+    }
+
+    /// Restores `save_devices` output onto the same devices
+    pub fn restore_devices(&mut self, states: &[Vec<Tryte>]) {
+        let devices = self
+            .regions
+            .iter_mut()
+            .filter_map(|region| match &mut region.target {
+                Target::Device(device) => Some(device),
+                Target::Rom(_) => None,
+            });
+        for (device, state) in devices.zip(states) {
+            device.restore(state);
+        }
+    }
+
+    fn devices(&self) -> impl Iterator<Item = &dyn Device> {
+        self.regions
+            .iter()
+            .filter_map(|region| match &region.target {
+                Target::Device(device) => Some(device.as_ref()),
+                Target::Rom(_) => None,
+            })
+    }
+
+    /// Advances every attached device by one cycle
+    pub fn tick(&mut self) {
+        for region in &mut self.regions {
+            if let Target::Device(device) = &mut region.target {
+                device.tick();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    /// Counts ticks and echoes the last written word back, plus one
+    #[derive(Default)]
+    struct Latch {
+        value: i128,
+        ticks: Rc<Cell<u32>>,
+    }
+
+    impl Device for Latch {
+        fn read(&mut self, offset: i128) -> Tryte {
+            Tryte::from_i128(self.value + offset)
+        }
+
+        fn write(&mut self, _offset: i128, value: Tryte) {
+            self.value = value.to_i128() + 1;
+        }
+
+        fn tick(&mut self) {
+            self.ticks.set(self.ticks.get() + 1);
+        }
+    }
+
+    fn addr(value: i128) -> Address {
+        Address::from_i128(value)
+    }
+
+    #[test]
+    fn test_routes_ram_rom_and_devices() {
+        let mut bus = Bus::from(AddressSpace::with_size(100));
+        bus.map_rom(200, vec![Tryte::from_i128(7), Tryte::from_i128(8)])
+            .unwrap();
+        bus.attach(300, 4, Latch::default()).unwrap();
+
+        bus.store(addr(5), Tryte::from_i128(42)).unwrap();
+        assert_eq!(bus.load(addr(5)), Ok(Tryte::from_i128(42)));
+        assert_eq!(bus.load(addr(201)), Ok(Tryte::from_i128(8)));
+
+        bus.store(addr(300), Tryte::from_i128(10)).unwrap();
+        assert_eq!(bus.load(addr(302)), Ok(Tryte::from_i128(13)));
+        assert_eq!(bus.read(addr(302)), Tryte::default());
+
+        // Outside RAM and every registered range
+        assert_eq!(bus.load(addr(250)), Err(MemoryFault::Unmapped(addr(250))));
+    }
+
+    #[test]
+    fn test_rom_rejects_guest_stores() {
+        let mut bus = Bus::default();
+        bus.map_rom(0, vec![Tryte::from_i128(1)]).unwrap();
+
+        assert_eq!(
+            bus.store(addr(0), Tryte::from_i128(2)),
+            Err(MemoryFault::ReadOnly(addr(0)))
+        );
+
+        // The host may still patch it
+        bus.write(addr(0), Tryte::from_i128(3));
+        assert_eq!(bus.load(addr(0)), Ok(Tryte::from_i128(3)));
+    }
+
+    #[test]
+    fn test_overlapping_ranges_are_rejected() {
+        let mut bus = Bus::default();
+        bus.attach(10, 5, Latch::default()).unwrap();
+
+        assert_eq!(
+            bus.attach(14, 2, Latch::default()),
+            Err(BusError::Overlap {
+                range: 14..16,
+                existing: 10..15
+            })
+        );
+        assert_eq!(
+            bus.map_rom(0, vec![Tryte::default(); 11]),
+            Err(BusError::Overlap {
+                range: 0..11,
+                existing: 10..15
+            })
+        );
+        assert_eq!(
+            bus.attach(20, 0, Latch::default()),
+            Err(BusError::EmptyRange(20..20))
+        );
+
+        // Adjacent ranges are fine
+        assert!(bus.attach(15, 1, Latch::default()).is_ok());
+        assert!(bus.map_rom(0, vec![Tryte::default(); 10]).is_ok());
+    }
+
+    #[test]
+    fn test_tick_reaches_every_device() {
+        let ticks = Rc::new(Cell::new(0));
+        let mut bus = Bus::default();
+        for start in [0, 10] {
+            let latch = Latch {
+                ticks: Rc::clone(&ticks),
+                ..Default::default()
+            };
+            bus.attach(start, 1, latch).unwrap();
+        }
+
+        bus.tick();
+        bus.tick();
+        assert_eq!(ticks.get(), 4);
+    }
+}
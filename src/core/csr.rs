@@ -0,0 +1,183 @@
+/// A control and status register, addressed by the immediate of the CSR
+/// instructions. Indices are grouped by function at powers of three.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Csr {
+    /// `Status` flags of the last arithmetic instruction
+    Status,
+    /// Free for the guest, typically a handler's save slot
+    Scratch,
+    /// Index of this hart, always 0; read-only
+    HartId,
+    /// Cycles executed; read-only
+    Cycle,
+    /// Instructions retired; read-only
+    Instret,
+    /// `TrapCause::code` of the most recent trap
+    TrapCause,
+    /// Address of the instruction that last trapped
+    TrapPc,
+    /// Trap handler address; 0 means no handler
+    TrapVector,
+    /// Interrupt-enable flag: 1 or 0, only trit 0 is written
+    InterruptEnable,
+    InterruptMask,
+    /// Raised interrupt lines; read-only
+    InterruptPending,
+    /// Interrupt handler address; 0 means no handler
+    InterruptVector,
+    /// PC saved when the current interrupt was taken
+    InterruptPc,
+}
+
+const CSRS: [(Csr, i32, &str); 13] = [
+    (Csr::Status, 1, "status"),
+    (Csr::Scratch, 2, "scratch"),
+    (Csr::HartId, 3, "hartid"),
+    (Csr::Cycle, 9, "cycle"),
+    (Csr::Instret, 10, "instret"),
+    (Csr::TrapCause, 27, "tcause"),
+    (Csr::TrapPc, 28, "tpc"),
+    (Csr::TrapVector, 29, "tvec"),
+    (Csr::InterruptEnable, 81, "ie"),
+    (Csr::InterruptMask, 82, "imask"),
+    (Csr::InterruptPending, 83, "ipend"),
+    (Csr::InterruptVector, 84, "ivec"),
+    (Csr::InterruptPc, 85, "ipc"),
+];
+
+impl Csr {
+    pub fn from_index(index: i32) -> Option<Csr> {
+        CSRS.iter()
+            .find(|(_, i, _)| *i == index)
+            .map(|(csr, _, _)| *csr)
+    }
+
+    /// Looks up the assembler name, e.g. `cycle`
+    pub fn from_name(name: &str) -> Option<Csr> {
+        CSRS.iter()
+            .find(|(_, _, n)| n.eq_ignore_ascii_case(name))
+            .map(|(csr, _, _)| *csr)
+    }
+
+    pub fn index(&self) -> i32 {
+        self.entry().1
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.entry().2
+    }
+
+    /// Writes to read-only registers are ignored
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Csr::HartId | Csr::Cycle | Csr::Instret | Csr::InterruptPending
+        )
+    }
+
+    fn entry(&self) -> &'static (Csr, i32, &'static str) {
+        CSRS.iter().find(|(csr, _, _)| csr == self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arch::trit::{TRYTE_MAX, Tryte},
+        core::{address_space::AddressSpace, alu::ArithmeticLogicUnit, registers::Registers},
+        cpu::{CentralProcessingUnit, StopReason},
+    };
+
+    fn run(source: &str) -> CentralProcessingUnit {
+        let program = crate::asm::assembler::assemble(source).unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), mem, ArithmeticLogicUnit::default());
+        assert_eq!(cpu.run(50).reason, StopReason::Halted);
+        cpu
+    }
+
+    #[test]
+    fn test_index_and_name_round_trip() {
+        for (csr, index, name) in CSRS {
+            assert_eq!(Csr::from_index(index), Some(csr));
+            assert_eq!(Csr::from_name(name), Some(csr));
+            assert_eq!(csr.index(), index);
+            assert_eq!(csr.name(), name);
+        }
+        assert_eq!(Csr::from_index(0), None);
+        assert_eq!(Csr::from_name("CYCLE"), Some(Csr::Cycle));
+        assert_eq!(Csr::from_name("x1"), None);
+    }
+
+    #[test]
+    fn test_guest_writes_to_read_only_csrs_are_ignored() {
+        let cpu = run("
+            addi  x1, x0, 100
+            csrrw x0, hartid, x1
+            csrrw x0, cycle, x1
+            csrrw x0, instret, x1
+            csrrw x0, ipend, x1
+            csrrs x0, cycle, x1
+            csrrc x0, instret, x1
+            halt
+            ");
+        assert_eq!(cpu.read_csr(Csr::HartId).to_i128(), 0);
+        assert_eq!(cpu.read_csr(Csr::Cycle).to_i128(), 8);
+        assert_eq!(cpu.read_csr(Csr::Instret).to_i128(), 8);
+        assert_eq!(cpu.read_csr(Csr::InterruptPending).to_i128(), 0);
+    }
+
+    #[test]
+    fn test_set_and_clear_with_x0_only_read() {
+        // 20 = 0t1T1T has negative trits that MAX with 0 would clear
+        let cpu = run("
+            addi  x1, x0, 20
+            csrrw x0, scratch, x1
+            csrrs x2, scratch, x0
+            csrrc x3, scratch, x0
+            halt
+            ");
+        let reg = |index| {
+            cpu.registers()
+                .read_gpr(crate::core::registers::RegAddr::from_i128(index))
+                .to_i128()
+        };
+        assert_eq!((reg(2), reg(3)), (20, 20));
+        assert_eq!(cpu.read_csr(Csr::Scratch).to_i128(), 20);
+    }
+
+    #[test]
+    fn test_interrupt_enable_only_looks_at_trit_0() {
+        // 0tT1 = -2 and 0t11 = 4 have trit 0 set, 0t10 = 3 does not
+        for (value, enabled) in [(-2, true), (4, true), (3, false), (-3, false)] {
+            let cpu = run(&format!(
+                "
+                addi  x1, x0, {}
+                csrrw x0, ie, x1
+                halt
+                ",
+                value
+            ));
+            assert_eq!(cpu.interrupts().is_enabled(), enabled, "ie <- {}", value);
+            assert_eq!(
+                cpu.read_csr(Csr::InterruptEnable).to_i128(),
+                enabled as i128
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_counter_writes_clamp_negative_values() {
+        let mut cpu = run("halt");
+        cpu.write_csr(Csr::Cycle, &Tryte::from_i128(-5));
+        cpu.write_csr(Csr::Instret, &Tryte::from_i128(-TRYTE_MAX));
+        assert_eq!(cpu.read_csr(Csr::Cycle).to_i128(), 0);
+        assert_eq!(cpu.read_csr(Csr::Instret).to_i128(), 0);
+
+        cpu.write_csr(Csr::Cycle, &Tryte::from_i128(12));
+        assert_eq!(cpu.read_csr(Csr::Cycle).to_i128(), 12);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    arch::trit::{Trit, Tryte},
+    core::{interrupts::InterruptController, registers::RegAddr},
+};
+
+/// What one cycle changed, enough to undo it.
+///
+/// Small state is copied whole before the cycle runs; general-purpose
+/// registers and memory words are logged as their old values when written.
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    /// Value of the cycle counter before this cycle
+    pub cycle: u64,
+    pub retired: u64,
+    pub halted: bool,
+    pub pc: Tryte,
+    pub status: Tryte,
+    pub scratch: Tryte,
+    pub trap_cause: Tryte,
+    pub trap_pc: Tryte,
+    pub trap_vector: Option<Tryte>,
+    pub alu_result: Tryte,
+    /// Zero, sign and carry flags
+    pub alu_flags: [Trit; 3],
+    pub interrupts: InterruptController,
+    /// `Bus::save_devices` before the cycle
+    pub devices: Vec<Vec<Tryte>>,
+    /// Old values of the registers written, in write order
+    pub gprs: Vec<(RegAddr, Tryte)>,
+    /// Old values of the memory words stored to, in write order
+    pub memory: Vec<(i128, Tryte)>,
+}
+
+impl UndoEntry {
+    pub fn wrote(&self, address: i128) -> bool {
+        self.memory.iter().any(|(written, _)| *written == address)
+    }
+}
+
+/// Undo log of the most recent cycles. A depth of 0 records nothing.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    depth: usize,
+    entries: VecDeque<UndoEntry>,
+}
+
+/// A cycle `rewind_to` cannot reach
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryError {
+    /// Older than the oldest recorded cycle
+    NotRecorded { cycle: u64, oldest: u64 },
+    /// Later than the current cycle
+    InFuture { cycle: u64, current: u64 },
+}
+
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryError::NotRecorded { cycle, oldest } => write!(
+                f,
+                "cycle {} is not recorded; history starts at cycle {}",
+                cycle, oldest
+            ),
+            HistoryError::InFuture { cycle, current } => {
+                write!(
+                    f,
+                    "cycle {} has not run yet; now at cycle {}",
+                    cycle, current
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl History {
+    pub fn with_depth(depth: usize) -> Self {
+        Self {
+            depth,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Changes the number of cycles kept, dropping the oldest ones if needed
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.entries.len() > depth {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.depth > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Cycle number the machine returns to if every entry is undone
+    pub fn oldest_cycle(&self) -> Option<u64> {
+        self.entries.front().map(|entry| entry.cycle)
+    }
+
+    pub fn last(&self) -> Option<&UndoEntry> {
+        self.entries.back()
+    }
+
+    pub fn push(&mut self, entry: UndoEntry) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.entries.len() == self.depth {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn pop(&mut self) -> Option<UndoEntry> {
+        self.entries.pop_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cycle: u64) -> UndoEntry {
+        UndoEntry {
+            cycle,
+            retired: cycle,
+            halted: false,
+            pc: Tryte::default(),
+            status: Tryte::default(),
+            scratch: Tryte::default(),
+            trap_cause: Tryte::default(),
+            trap_pc: Tryte::default(),
+            trap_vector: None,
+            alu_result: Tryte::default(),
+            alu_flags: [Trit::Zero; 3],
+            interrupts: InterruptController::default(),
+            devices: Vec::new(),
+            gprs: Vec::new(),
+            memory: vec![(cycle as i128, Tryte::default())],
+        }
+    }
+
+    #[test]
+    fn test_depth_bounds_the_log() {
+        let mut history = History::default();
+        history.push(entry(0));
+        assert!(history.is_empty(), "depth 0 records nothing");
+
+        history.set_depth(3);
+        for cycle in 0..5 {
+            history.push(entry(cycle));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.oldest_cycle(), Some(2));
+        assert!(history.last().unwrap().wrote(4));
+
+        history.set_depth(2);
+        assert_eq!(history.oldest_cycle(), Some(3));
+        assert_eq!(history.pop().map(|entry| entry.cycle), Some(4));
+        assert_eq!(history.pop().map(|entry| entry.cycle), Some(3));
+        assert!(history.pop().is_none());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::arch::trit::{Trit, TritField, Tryte};
+
+/// Interrupt lines, one per trit of the mask and pending registers.
+/// Lower-numbered lines have higher priority.
+pub const INTERRUPT_LINES: usize = 27;
+
+/// Collects device interrupt lines and decides when the CPU is interrupted.
+///
+/// A line interrupts the CPU when it is raised, its mask trit is positive and
+/// the interrupt-enable flag is set. Taking an interrupt saves the PC, clears
+/// the enable flag and jumps to the vector; IRET restores the PC and sets the
+/// flag again, so handlers do not nest.
+#[derive(Debug, Clone)]
+pub struct InterruptController {
+    /// Trit `i` positive enables line `i`
+    mask: Tryte,
+    /// Trit `i` positive while line `i` is raised
+    pending: Tryte,
+    enabled: bool,
+    saved_pc: Tryte,
+    /// Handler address; interrupts stay pending while this is unset
+    vector: Option<Tryte>,
+    /// Line of the interrupt being handled
+    active: Option<usize>,
+}
+
+impl Default for InterruptController {
+    /// Every line unmasked, interrupts disabled until the guest runs EI
+    fn default() -> Self {
+        Self {
+            mask: TritField::from_trits([Trit::Positive; INTERRUPT_LINES]),
+            pending: Tryte::default(),
+            enabled: false,
+            saved_pc: Tryte::default(),
+            vector: None,
+            active: None,
+        }
+    }
+}
+
+impl InterruptController {
+    pub fn read_mask(&self) -> &Tryte {
+        &self.mask
+    }
+
+    pub fn write_mask(&mut self, mask: &Tryte) {
+        self.mask = *mask;
+    }
+
+    pub fn read_pending(&self) -> &Tryte {
+        &self.pending
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn read_saved_pc(&self) -> &Tryte {
+        &self.saved_pc
+    }
+
+    pub fn write_saved_pc(&mut self, saved_pc: &Tryte) {
+        self.saved_pc = *saved_pc;
+    }
+
+    pub fn read_vector(&self) -> Option<&Tryte> {
+        self.vector.as_ref()
+    }
+
+    pub fn write_vector(&mut self, vector: Option<&Tryte>) {
+        self.vector = vector.copied();
+    }
+
+    /// Line of the interrupt being handled, until IRET
+    pub fn active_line(&self) -> Option<usize> {
+        self.active
+    }
+
+    pub fn set_active_line(&mut self, line: Option<usize>) {
+        self.active = line;
+    }
+
+    /// Latches the device lines, bit `i` of `lines` being line `i`
+    pub fn update(&mut self, lines: u32) {
+        for line in 0..INTERRUPT_LINES {
+            let raised = if lines & (1 << line) != 0 {
+                Trit::Positive
+            } else {
+                Trit::Zero
+            };
+            self.pending.set_trit(line, raised);
+        }
+    }
+
+    /// Highest-priority line that is raised and unmasked
+    pub fn highest_pending(&self) -> Option<usize> {
+        (0..INTERRUPT_LINES).find(|&line| {
+            self.pending.trit(line) == Trit::Positive && self.mask.trit(line) == Trit::Positive
+        })
+    }
+
+    /// Takes the highest-priority interrupt if one can be taken, saving `pc`.
+    /// Returns the handler address to continue at.
+    pub fn take(&mut self, pc: &Tryte) -> Option<Tryte> {
+        if !self.enabled {
+            return None;
+        }
+        let vector = self.vector?;
+        let line = self.highest_pending()?;
+
+        self.saved_pc = *pc;
+        self.enabled = false;
+        self.active = Some(line);
+        Some(vector)
+    }
+
+    /// IRET: re-enables interrupts and returns the PC to resume at
+    pub fn interrupt_return(&mut self) -> Tryte {
+        self.enabled = true;
+        self.active = None;
+        self.saved_pc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller() -> InterruptController {
+        let mut controller = InterruptController::default();
+        controller.write_vector(Some(&Tryte::from_i128(40)));
+        controller.set_enabled(true);
+        controller
+    }
+
+    #[test]
+    fn test_lowest_line_wins_and_masked_lines_wait() {
+        let mut controller = controller();
+        controller.update(0b1010);
+        assert_eq!(controller.highest_pending(), Some(1));
+
+        let mut mask = *controller.read_mask();
+        mask.set_trit(1, Trit::Zero);
+        controller.write_mask(&mask);
+        assert_eq!(controller.highest_pending(), Some(3));
+
+        controller.update(0b0010);
+        assert_eq!(controller.highest_pending(), None);
+        assert_eq!(controller.read_pending().trit(1), Trit::Positive);
+    }
+
+    #[test]
+    fn test_take_saves_pc_and_iret_restores_it() {
+        let mut controller = controller();
+        controller.update(0b100);
+
+        let pc = Tryte::from_i128(7);
+        assert_eq!(controller.take(&pc), Some(Tryte::from_i128(40)));
+        assert_eq!(controller.active_line(), Some(2));
+        assert!(!controller.is_enabled());
+
+        // No nesting while the handler runs
+        assert_eq!(controller.take(&Tryte::from_i128(41)), None);
+
+        assert_eq!(controller.interrupt_return(), pc);
+        assert!(controller.is_enabled());
+        assert_eq!(controller.active_line(), None);
+    }
+
+    #[test]
+    fn test_nothing_is_taken_without_enable_or_vector() {
+        let mut controller = InterruptController::default();
+        controller.update(1);
+        assert_eq!(controller.take(&Tryte::default()), None);
+
+        controller.set_enabled(true);
+        assert_eq!(controller.take(&Tryte::default()), None);
+    }
+}
@@ -1,3 +1,9 @@
 pub mod address_space;
 pub mod alu;
+pub mod bus;
+pub mod csr;
+pub mod history;
+pub mod interrupts;
 pub mod registers;
+pub mod status;
+pub mod trap;
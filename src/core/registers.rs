@@ -8,6 +8,16 @@ pub type RegAddr = TritField<3>;
 pub struct Registers {
     pc: Tryte,
     gpr: HashMap<RegAddr, Tryte>,
+    /// `TrapCause::code` of the most recent trap
+    trap_cause: Tryte,
+    /// Address of the instruction that last trapped
+    trap_pc: Tryte,
+    /// Handler address; traps are returned to the host while this is unset
+    trap_vector: Option<Tryte>,
+    /// `Status` flags of the last arithmetic instruction
+    status: Tryte,
+    /// Guest-defined, see `Csr::Scratch`
+    scratch: Tryte,
 }
 
 impl Registers {
@@ -19,6 +29,46 @@ impl Registers {
         self.pc = *new_pc;
     }
 
+    pub fn read_trap_cause(&self) -> &Tryte {
+        &self.trap_cause
+    }
+
+    pub fn write_trap_cause(&mut self, cause: &Tryte) {
+        self.trap_cause = *cause;
+    }
+
+    pub fn read_trap_pc(&self) -> &Tryte {
+        &self.trap_pc
+    }
+
+    pub fn write_trap_pc(&mut self, trap_pc: &Tryte) {
+        self.trap_pc = *trap_pc;
+    }
+
+    pub fn read_trap_vector(&self) -> Option<&Tryte> {
+        self.trap_vector.as_ref()
+    }
+
+    pub fn write_trap_vector(&mut self, vector: Option<&Tryte>) {
+        self.trap_vector = vector.copied();
+    }
+
+    pub fn read_status(&self) -> &Tryte {
+        &self.status
+    }
+
+    pub fn write_status(&mut self, status: &Tryte) {
+        self.status = *status;
+    }
+
+    pub fn read_scratch(&self) -> &Tryte {
+        &self.scratch
+    }
+
+    pub fn write_scratch(&mut self, scratch: &Tryte) {
+        self.scratch = *scratch;
+    }
+
     pub fn read_gpr(&self, index: RegAddr) -> Tryte {
         if index.to_i128() == 0 {
             Tryte::default()
@@ -49,6 +99,7 @@ mod tests {
         let mut regs = Registers {
             pc: Tryte::default(),
             gpr: HashMap::new(),
+            ..Default::default()
         };
 
         let new_val = Tryte::from_i128(123);
@@ -62,6 +113,7 @@ mod tests {
         let mut regs = Registers {
             pc: Tryte::default(),
             gpr: HashMap::new(),
+            ..Default::default()
         };
 
         let r0 = make_reg(0);
@@ -83,6 +135,7 @@ mod tests {
         let mut regs = Registers {
             pc: Tryte::default(),
             gpr: HashMap::new(),
+            ..Default::default()
         };
 
         let r1 = make_reg(1);
@@ -102,6 +155,7 @@ mod tests {
         let regs = Registers {
             pc: Tryte::default(),
             gpr: HashMap::new(),
+            ..Default::default()
         };
 
         let r10 = make_reg(10);
@@ -0,0 +1,61 @@
+use crate::arch::trit::{Trit, Tryte};
+
+/// Flags latched by the arithmetic instructions (ADD, SUB, ADDI and the
+/// carry and overflow-checking variants).
+///
+/// Stored in the status register as one word:
+/// - trit 0: carry out of the top trit (Negative, Zero or Positive)
+/// - trit 1: overflow, Positive when the exact result did not fit in 27 trits
+/// - trit 2: sign of the exact result
+/// - trit 3: zero, Positive when the stored result is zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Status {
+    pub carry: Trit,
+    pub overflow: bool,
+    pub sign: Trit,
+    pub zero: bool,
+}
+
+impl Status {
+    pub fn to_tryte(&self) -> Tryte {
+        let mut word = Tryte::default();
+        word.set_trit(0, self.carry);
+        word.set_trit(1, flag(self.overflow));
+        word.set_trit(2, self.sign);
+        word.set_trit(3, flag(self.zero));
+        word
+    }
+
+    pub fn from_tryte(word: &Tryte) -> Self {
+        Status {
+            carry: word.trit(0),
+            overflow: word.trit(1) == Trit::Positive,
+            sign: word.trit(2),
+            zero: word.trit(3) == Trit::Positive,
+        }
+    }
+}
+
+fn flag(set: bool) -> Trit {
+    if set { Trit::Positive } else { Trit::Zero }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trips_through_tryte() {
+        let status = Status {
+            carry: Trit::Negative,
+            overflow: true,
+            sign: Trit::Negative,
+            zero: false,
+        };
+
+        let word = status.to_tryte();
+        assert_eq!(word.to_i128(), -1 + 3 - 9);
+        assert_eq!(Status::from_tryte(&word), status);
+        assert_eq!(Status::from_tryte(&Tryte::default()), Status::default());
+    }
+}
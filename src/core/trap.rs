@@ -0,0 +1,72 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{arch::trit::Tryte, core::address_space::Address};
+
+/// What went wrong in the instruction that trapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapCause {
+    /// The fetched word is not a valid instruction
+    IllegalInstruction(Tryte),
+    /// The PC points outside installed memory
+    InstructionAccessFault(Address),
+    LoadAccessFault(Address),
+    StoreAccessFault(Address),
+    /// An overflow-checking instruction (ADDV, SUBV) produced a result outside 27 trits
+    Overflow,
+}
+
+impl TrapCause {
+    /// Value written to the trap-cause register
+    pub fn code(&self) -> i128 {
+        match self {
+            TrapCause::IllegalInstruction(_) => 1,
+            TrapCause::InstructionAccessFault(_) => 2,
+            TrapCause::LoadAccessFault(_) => 3,
+            TrapCause::StoreAccessFault(_) => 4,
+            TrapCause::Overflow => 5,
+        }
+    }
+}
+
+impl Display for TrapCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrapCause::IllegalInstruction(raw) => {
+                write!(f, "illegal instruction {}", raw.to_trit_string())
+            }
+            TrapCause::InstructionAccessFault(address) => {
+                write!(
+                    f,
+                    "instruction fetch from unmapped address {}",
+                    address.to_i128()
+                )
+            }
+            TrapCause::LoadAccessFault(address) => {
+                write!(f, "load from unmapped address {}", address.to_i128())
+            }
+            TrapCause::StoreAccessFault(address) => {
+                write!(
+                    f,
+                    "store to unmapped or read-only address {}",
+                    address.to_i128()
+                )
+            }
+            TrapCause::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+/// A trap raised by an instruction, with the address of that instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trap {
+    pub cause: TrapCause,
+    pub pc: Address,
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at pc {}", self.cause, self.pc.to_i128())
+    }
+}
+
+impl std::error::Error for Trap {}
@@ -1,59 +1,239 @@
+use std::collections::HashSet;
+
+use log::{Level, log_enabled, trace};
+
 use crate::{
     arch::{
-        instructions::{ControlSignals, Instruction},
+        instructions::{BranchCondition, ControlSignals, Instruction, split_sign_offsets},
         trit::{Trit, Tryte},
     },
     core::{
-        address_space::AddressSpace,
+        address_space::Address,
         alu::ArithmeticLogicUnit,
+        bus::Bus,
+        csr::Csr,
+        history::{History, HistoryError, UndoEntry},
+        interrupts::InterruptController,
         registers::{RegAddr, Registers},
+        status::Status,
+        trap::{Trap, TrapCause},
     },
+    trace::{AccessKind, MemoryAccess, RegisterWrite, TraceRecord, TracedRegister, Tracer},
 };
 
 pub struct CentralProcessingUnit {
     registers: Registers,
-    address_space: AddressSpace,
+    bus: Bus,
     arithmetic_logic_unit: ArithmeticLogicUnit,
     current_instruction: Instruction,
+    /// Word `current_instruction` was decoded from
+    raw_instruction: Tryte,
     control_signals: ControlSignals,
     immediate: i32,
+    halted: bool,
+    interrupts: InterruptController,
+    /// Cycles executed, for `Csr::Cycle`
+    cycles: u64,
+    /// Instructions retired, for `Csr::Instret`
+    retired: u64,
+    breakpoints: HashSet<Address>,
+    tracer: Option<Box<dyn Tracer>>,
+    /// Record of the instruction executing, while tracing
+    trace: Option<TraceRecord>,
+    history: History,
+    /// Undo entry of the cycle executing, while history is recorded
+    undo: Option<UndoEntry>,
+}
+
+/// Why `CentralProcessingUnit::run` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// A HALT instruction retired (or the CPU was already halted)
+    Halted,
+    /// The cycle budget ran out
+    CycleLimit,
+    /// The PC reached a breakpoint; the instruction there has not executed yet
+    Breakpoint(Address),
+    /// An instruction trapped with no trap vector installed; the PC still points at it
+    Fault(Trap),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOutcome {
+    pub reason: StopReason,
+    /// Instructions that completed during this call
+    pub retired: u64,
 }
 
 impl CentralProcessingUnit {
     pub fn from(
         registers: Registers,
-        address_space: AddressSpace,
+        bus: impl Into<Bus>,
         arithmetic_logic_unit: ArithmeticLogicUnit,
     ) -> Self {
         Self {
             registers,
-            address_space,
+            bus: bus.into(),
             arithmetic_logic_unit,
             current_instruction: Instruction::Nop,
+            raw_instruction: Tryte::default(),
             control_signals: ControlSignals::default(),
             immediate: 0,
+            halted: false,
+            interrupts: InterruptController::default(),
+            cycles: 0,
+            retired: 0,
+            breakpoints: HashSet::new(),
+            tracer: None,
+            trace: None,
+            history: History::default(),
+            undo: None,
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+
+    pub fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
+
+    pub fn interrupts_mut(&mut self) -> &mut InterruptController {
+        &mut self.interrupts
+    }
+
+    pub fn alu(&self) -> &ArithmeticLogicUnit {
+        &self.arithmetic_logic_unit
+    }
+
+    pub fn alu_mut(&mut self) -> &mut ArithmeticLogicUnit {
+        &mut self.arithmetic_logic_unit
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    pub fn add_breakpoint(&mut self, address: Address) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: Address) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    /// Sends a `TraceRecord` for every retired instruction to `tracer`.
+    /// Records are also logged at trace level, with or without a tracer.
+    pub fn set_tracer(&mut self, tracer: impl Tracer + 'static) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer>> {
+        self.tracer.take()
+    }
+
+    fn trace_register(&mut self, register: TracedRegister, value: Tryte) {
+        if let Some(record) = &mut self.trace
+            && register != TracedRegister::Gpr(0)
+        {
+            record
+                .register_writes
+                .push(RegisterWrite { register, value });
+        }
+    }
+
+    /// Register write that is traced and can be undone
+    fn write_gpr(&mut self, address: RegAddr, value: Tryte) {
+        if let Some(entry) = &mut self.undo {
+            entry.gprs.push((address, self.registers.read_gpr(address)));
+        }
+        self.registers.write_gpr(address, value);
+        self.trace_register(TracedRegister::Gpr(address.to_i128() as usize), value);
+    }
+
+    fn trace_memory(&mut self, kind: AccessKind, address: Tryte, value: Tryte) {
+        if let Some(record) = &mut self.trace {
+            record.memory_accesses.push(MemoryAccess {
+                kind,
+                address: address.to_i128(),
+                value,
+            });
         }
     }
 }
 
 impl CentralProcessingUnit {
-    fn fetch(&mut self) -> Tryte {
+    fn fetch(&mut self) -> Result<Tryte, TrapCause> {
         let pc_val = *self.registers.read_pc();
-        self.address_space.read(pc_val)
+        self.bus
+            .load(pc_val)
+            .map_err(|_| TrapCause::InstructionAccessFault(pc_val))
     }
 
-    fn decode(&mut self, raw_instr: Tryte) {
-        self.current_instruction = Instruction::from(raw_instr);
+    fn decode(&mut self, raw_instr: Tryte) -> Result<(), TrapCause> {
+        if let Some(record) = &mut self.trace {
+            record.raw = raw_instr;
+        }
+        self.raw_instruction = raw_instr;
+        self.current_instruction = Instruction::from_machine_code(raw_instr)
+            .map_err(|_| TrapCause::IllegalInstruction(raw_instr))?;
 
         let (signals, imm) = self.current_instruction.decode();
         self.control_signals = signals;
         self.immediate = imm;
+        Ok(())
     }
 
-    fn execute(&mut self) {
+    /// Faults are raised before any register or memory is written,
+    /// so a trapped instruction leaves no partial state behind.
+    fn execute(&mut self) -> Result<(), TrapCause> {
         let instr = self.current_instruction;
         let signals = self.control_signals;
 
+        // HALT freezes the PC on itself
+        if signals.halt {
+            self.halted = true;
+            return Ok(());
+        }
+
+        if signals.trap_return {
+            let trap_pc = *self.registers.read_trap_pc();
+            self.registers.write_pc(&trap_pc);
+            return Ok(());
+        }
+
+        if signals.interrupt_return {
+            let resume_pc = self.interrupts.interrupt_return();
+            self.registers.write_pc(&resume_pc);
+            self.trace_register(
+                TracedRegister::Csr(Csr::InterruptEnable),
+                Tryte::from_i128(1),
+            );
+            return Ok(());
+        }
+        if signals.enable_interrupts || signals.disable_interrupts {
+            self.interrupts.set_enabled(signals.enable_interrupts);
+            let enabled = Tryte::from_i128(signals.enable_interrupts as i128);
+            self.trace_register(TracedRegister::Csr(Csr::InterruptEnable), enabled);
+        }
+
         let rs1_addr = self.usize_to_regaddr(instr.rs1());
         let rs2_addr = self.usize_to_regaddr(instr.rs2());
         let rd_addr = self.usize_to_regaddr(instr.rd());
@@ -61,6 +241,10 @@ impl CentralProcessingUnit {
         let r_val_1 = self.registers.read_gpr(rs1_addr);
         let r_val_2 = self.registers.read_gpr(rs2_addr);
 
+        if signals.csr {
+            return self.execute_csr(signals, rd_addr, r_val_1);
+        }
+
         let input_a = r_val_1;
 
         // Mux: Choose between Register 2 or Immediate
@@ -74,18 +258,50 @@ impl CentralProcessingUnit {
         self.arithmetic_logic_unit.alu_reset();
         self.arithmetic_logic_unit
             .alu_set(input_a, input_b, signals.alu_op);
+        if signals.carry_in {
+            let status = Status::from_tryte(self.registers.read_status());
+            self.arithmetic_logic_unit.alu_set_carry_in(status.carry);
+        }
         self.arithmetic_logic_unit.alu_exec();
         let alu_result = self.arithmetic_logic_unit.result;
 
+        let alu = &self.arithmetic_logic_unit;
+        let overflow = alu.carry_flag != Trit::Zero;
+        if signals.trap_on_overflow && overflow {
+            return Err(TrapCause::Overflow);
+        }
+        if signals.set_status {
+            let status = Status {
+                carry: alu.carry_flag,
+                overflow,
+                sign: alu.sign_flag,
+                zero: alu.zero_flag == Trit::Positive,
+            };
+            self.registers.write_status(&status.to_tryte());
+            self.trace_register(TracedRegister::Csr(Csr::Status), status.to_tryte());
+        }
+
         let mut result_to_write = alu_result;
 
         // Store
         if signals.mem_write {
-            self.address_space.write(alu_result, r_val_2);
+            // Only worth reading while history is recorded
+            let old = self.undo.as_ref().map(|_| self.bus.read(alu_result));
+            self.bus
+                .store(alu_result, r_val_2)
+                .map_err(|_| TrapCause::StoreAccessFault(alu_result))?;
+            if let (Some(entry), Some(old)) = (&mut self.undo, old) {
+                entry.memory.push((alu_result.to_i128(), old));
+            }
+            self.trace_memory(AccessKind::Store, alu_result, r_val_2);
         }
 
         if signals.mem_read {
-            result_to_write = self.address_space.read(alu_result);
+            result_to_write = self
+                .bus
+                .load(alu_result)
+                .map_err(|_| TrapCause::LoadAccessFault(alu_result))?;
+            self.trace_memory(AccessKind::Load, alu_result, result_to_write);
         }
 
         if signals.reg_write {
@@ -98,20 +314,96 @@ impl CentralProcessingUnit {
                 alu_result
             };
 
-            self.registers.write_gpr(rd_addr, final_data);
+            self.write_gpr(rd_addr, final_data);
         }
 
         self.update_pc(signals);
+        Ok(())
+    }
+
+    /// The old CSR value goes to rd, and the ALU result of (old value, rs1) to the CSR.
+    /// Unknown CSRs are illegal; writes to read-only ones are dropped. CSRRS and CSRRC
+    /// with rs1 = x0 only read: MAX and MIN with 0 would still change nonzero trits.
+    fn execute_csr(
+        &mut self,
+        signals: ControlSignals,
+        rd_addr: RegAddr,
+        r_val_1: Tryte,
+    ) -> Result<(), TrapCause> {
+        let csr = Csr::from_index(self.immediate)
+            .ok_or(TrapCause::IllegalInstruction(self.raw_instruction))?;
+
+        let old = self.read_csr(csr);
+        self.arithmetic_logic_unit.alu_reset();
+        self.arithmetic_logic_unit
+            .alu_set(old, r_val_1, signals.alu_op);
+        self.arithmetic_logic_unit.alu_exec();
+        let new = self.arithmetic_logic_unit.result;
+
+        let read_only = matches!(
+            self.current_instruction,
+            Instruction::Csrrs { rs1: 0, .. } | Instruction::Csrrc { rs1: 0, .. }
+        );
+        if !csr.is_read_only() && !read_only {
+            self.write_csr(csr, &new);
+            self.trace_register(TracedRegister::Csr(csr), self.read_csr(csr));
+        }
+        self.write_gpr(rd_addr, old);
+        self.update_pc(signals);
+        Ok(())
+    }
+
+    pub fn read_csr(&self, csr: Csr) -> Tryte {
+        match csr {
+            Csr::Status => *self.registers.read_status(),
+            Csr::Scratch => *self.registers.read_scratch(),
+            Csr::HartId => Tryte::default(),
+            Csr::Cycle => Tryte::from_i128(self.cycles as i128),
+            Csr::Instret => Tryte::from_i128(self.retired as i128),
+            Csr::TrapCause => *self.registers.read_trap_cause(),
+            Csr::TrapPc => *self.registers.read_trap_pc(),
+            Csr::TrapVector => self
+                .registers
+                .read_trap_vector()
+                .copied()
+                .unwrap_or_default(),
+            Csr::InterruptEnable => Tryte::from_i128(self.interrupts.is_enabled() as i128),
+            Csr::InterruptMask => *self.interrupts.read_mask(),
+            Csr::InterruptPending => *self.interrupts.read_pending(),
+            Csr::InterruptVector => self.interrupts.read_vector().copied().unwrap_or_default(),
+            Csr::InterruptPc => *self.interrupts.read_saved_pc(),
+        }
+    }
+
+    /// Host-side write; unlike the CSR instructions it also sets read-only
+    /// counters, clamping negative values to 0. A zero vector removes the handler.
+    pub fn write_csr(&mut self, csr: Csr, value: &Tryte) {
+        let vector = (*value != Tryte::default()).then_some(value);
+        match csr {
+            Csr::Status => self.registers.write_status(value),
+            Csr::Scratch => self.registers.write_scratch(value),
+            Csr::HartId | Csr::InterruptPending => {}
+            Csr::Cycle => self.cycles = value.to_i128().max(0) as u64,
+            Csr::Instret => self.retired = value.to_i128().max(0) as u64,
+            Csr::TrapCause => self.registers.write_trap_cause(value),
+            Csr::TrapPc => self.registers.write_trap_pc(value),
+            Csr::TrapVector => self.registers.write_trap_vector(vector),
+            Csr::InterruptEnable => self.interrupts.set_enabled(value.trit(0) == Trit::Positive),
+            Csr::InterruptMask => self.interrupts.write_mask(value),
+            Csr::InterruptVector => self.interrupts.write_vector(vector),
+            Csr::InterruptPc => self.interrupts.write_saved_pc(value),
+        }
     }
 
     fn update_pc(&mut self, signals: ControlSignals) {
         let current_pc = self.registers.read_pc().to_i128();
-        let zero_flag = self.arithmetic_logic_unit.zero_flag;
 
-        let next_pc_val = if signals.jump {
-            current_pc + (self.immediate as i128)
-        } else if signals.branch && (zero_flag == Trit::Positive) {
+        let next_pc_val = if signals.jump_register {
+            self.arithmetic_logic_unit.result.to_i128()
+        } else if signals.jump {
             current_pc + (self.immediate as i128)
+        } else if signals.branch {
+            current_pc + self.branch_offset(signals.branch_condition) as i128
         } else {
             current_pc + 1
         };
@@ -119,10 +411,254 @@ impl CentralProcessingUnit {
         self.registers.write_pc(&Tryte::from_i128(next_pc_val));
     }
 
-    pub fn cycle(&mut self) {
-        let raw_instr = self.fetch();
-        self.decode(raw_instr);
-        self.execute();
+    /// PC offset of a branch: its immediate if taken, 1 otherwise.
+    fn branch_offset(&self, condition: BranchCondition) -> i32 {
+        let zero_flag = self.arithmetic_logic_unit.zero_flag;
+        let sign_flag = self.arithmetic_logic_unit.sign_flag;
+
+        let taken = match condition {
+            BranchCondition::Equal => zero_flag == Trit::Positive,
+            BranchCondition::NotEqual => zero_flag != Trit::Positive,
+            BranchCondition::Less => sign_flag == Trit::Negative,
+            BranchCondition::GreaterOrEqual => sign_flag != Trit::Negative,
+            BranchCondition::Sign => {
+                let (neg, pos) = split_sign_offsets(self.immediate);
+                return match sign_flag {
+                    Trit::Negative => neg,
+                    Trit::Zero => 1,
+                    Trit::Positive => pos,
+                };
+            }
+        };
+
+        if taken { self.immediate } else { 1 }
+    }
+
+    /// Runs one cycle: executes one instruction, or enters an interrupt handler.
+    /// Does nothing once the CPU has halted.
+    ///
+    /// A trapping instruction jumps to the trap vector if one is installed,
+    /// otherwise the trap is returned and the PC is left on the instruction.
+    pub fn cycle(&mut self) -> Result<(), Trap> {
+        self.step().map(|_| ())
+    }
+
+    /// One cycle; `Ok(true)` if an instruction retired, `Ok(false)` if it trapped into
+    /// the handler, entered an interrupt handler, or the CPU is halted.
+    fn step(&mut self) -> Result<bool, Trap> {
+        if self.halted {
+            return Ok(false);
+        }
+
+        if self.history.is_recording() {
+            self.undo = Some(self.undo_entry());
+        }
+
+        // Interrupts are taken between instructions. Entering the handler takes a
+        // cycle of its own, so the PC sits on the handler's first instruction
+        // before it runs and breakpoints there are seen.
+        self.interrupts.update(self.bus.raised_lines());
+        let pc = *self.registers.read_pc();
+        if let Some(vector) = self.interrupts.take(&pc) {
+            trace!(
+                "cycle {}: interrupt line {} at pc {}, entering handler at {}",
+                self.cycles,
+                self.interrupts.active_line().unwrap_or_default(),
+                pc.to_i128(),
+                vector.to_i128()
+            );
+            self.registers.write_pc(&vector);
+            self.end_cycle();
+            return Ok(false);
+        }
+
+        self.trace = (self.tracer.is_some() || log_enabled!(Level::Trace))
+            .then(|| TraceRecord::new(self.cycles, pc.to_i128()));
+        let result = self.fetch_decode_execute();
+        self.end_cycle();
+        match result {
+            Ok(()) => {
+                self.retired += 1;
+                self.finish_trace();
+                Ok(true)
+            }
+            Err(cause) => self.enter_trap(Trap { cause, pc }).map(|_| false),
+        }
+    }
+
+    /// Advances the devices and the cycle counter, and keeps the cycle's undo entry
+    fn end_cycle(&mut self) {
+        self.bus.tick();
+        self.cycles += 1;
+        if let Some(entry) = self.undo.take() {
+            self.history.push(entry);
+        }
+    }
+
+    /// Completes the record of the instruction that just retired and hands it out
+    fn finish_trace(&mut self) {
+        let Some(mut record) = self.trace.take() else {
+            return;
+        };
+        record.instruction = self.current_instruction;
+        record.signals = self.control_signals;
+        record.next_pc = self.registers.read_pc().to_i128();
+
+        trace!("{}", record);
+        if let Some(tracer) = &mut self.tracer {
+            tracer.trace(&record);
+        }
+    }
+
+    fn fetch_decode_execute(&mut self) -> Result<(), TrapCause> {
+        let raw_instr = self.fetch()?;
+        self.decode(raw_instr)?;
+        self.execute()
+    }
+
+    fn enter_trap(&mut self, trap: Trap) -> Result<(), Trap> {
+        let Some(vector) = self.registers.read_trap_vector().copied() else {
+            return Err(trap);
+        };
+
+        self.registers
+            .write_trap_cause(&Tryte::from_i128(trap.cause.code()));
+        self.registers.write_trap_pc(&trap.pc);
+        self.registers.write_pc(&vector);
+        Ok(())
+    }
+
+    /// Runs until HALT, a breakpoint, an unhandled trap, or `max_cycles` cycles.
+    ///
+    /// A breakpoint on the instruction the PC points at when `run` is called does not
+    /// stop it, so calling `run` again after a breakpoint continues past it.
+    pub fn run(&mut self, max_cycles: u64) -> RunOutcome {
+        let mut cycles = 0;
+        let mut retired = 0;
+
+        let reason = loop {
+            if self.halted {
+                break StopReason::Halted;
+            }
+
+            let pc = *self.registers.read_pc();
+            if cycles > 0 && self.breakpoints.contains(&pc) {
+                break StopReason::Breakpoint(pc);
+            }
+
+            if cycles == max_cycles {
+                break StopReason::CycleLimit;
+            }
+
+            match self.step() {
+                Ok(true) => retired += 1,
+                Ok(false) => {}
+                Err(trap) => break StopReason::Fault(trap),
+            }
+            cycles += 1;
+        };
+
+        RunOutcome { reason, retired }
+    }
+
+    /// Records up to `depth` cycles so they can be undone; 0 stops recording.
+    /// Host-side changes between cycles are not recorded.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Forgets every recorded cycle, e.g. after the host replaced the machine state
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Undoes the most recent recorded cycle. Returns `false` once the history
+    /// is exhausted. Device output already sent to the host stays sent.
+    pub fn step_back(&mut self) -> bool {
+        let Some(entry) = self.history.pop() else {
+            return false;
+        };
+
+        for &(address, old) in entry.memory.iter().rev() {
+            self.bus.write(Tryte::from_i128(address), old);
+        }
+        for &(address, old) in entry.gprs.iter().rev() {
+            self.registers.write_gpr(address, old);
+        }
+        self.bus.restore_devices(&entry.devices);
+
+        self.cycles = entry.cycle;
+        self.retired = entry.retired;
+        self.halted = entry.halted;
+        self.registers.write_pc(&entry.pc);
+        self.registers.write_status(&entry.status);
+        self.registers.write_scratch(&entry.scratch);
+        self.registers.write_trap_cause(&entry.trap_cause);
+        self.registers.write_trap_pc(&entry.trap_pc);
+        self.registers.write_trap_vector(entry.trap_vector.as_ref());
+        let alu = &mut self.arithmetic_logic_unit;
+        alu.result = entry.alu_result;
+        [alu.zero_flag, alu.sign_flag, alu.carry_flag] = entry.alu_flags;
+        self.interrupts = entry.interrupts;
+        true
+    }
+
+    /// Steps back to just before the most recent recorded store to `address`
+    /// and returns that cycle. Returns `None`, at the oldest recorded cycle,
+    /// if no recorded cycle stored there.
+    pub fn step_back_to_write(&mut self, address: i128) -> Option<u64> {
+        loop {
+            let entry = self.history.last()?;
+            let (cycle, wrote) = (entry.cycle, entry.wrote(address));
+            self.step_back();
+            if wrote {
+                return Some(cycle);
+            }
+        }
+    }
+
+    /// Steps back until the cycle counter reads `cycle`
+    pub fn rewind_to(&mut self, cycle: u64) -> Result<(), HistoryError> {
+        if cycle > self.cycles {
+            return Err(HistoryError::InFuture {
+                cycle,
+                current: self.cycles,
+            });
+        }
+        let oldest = self.history.oldest_cycle().unwrap_or(self.cycles);
+        if cycle < oldest {
+            return Err(HistoryError::NotRecorded { cycle, oldest });
+        }
+
+        while self.cycles > cycle && self.step_back() {}
+        Ok(())
+    }
+
+    /// Everything `step_back` needs except the register and memory writes,
+    /// which are logged as they happen
+    fn undo_entry(&self) -> UndoEntry {
+        let alu = &self.arithmetic_logic_unit;
+        UndoEntry {
+            cycle: self.cycles,
+            retired: self.retired,
+            halted: self.halted,
+            pc: *self.registers.read_pc(),
+            status: *self.registers.read_status(),
+            scratch: *self.registers.read_scratch(),
+            trap_cause: *self.registers.read_trap_cause(),
+            trap_pc: *self.registers.read_trap_pc(),
+            trap_vector: self.registers.read_trap_vector().copied(),
+            alu_result: alu.result,
+            alu_flags: [alu.zero_flag, alu.sign_flag, alu.carry_flag],
+            interrupts: self.interrupts.clone(),
+            devices: self.bus.save_devices(),
+            gprs: Vec::new(),
+            memory: Vec::new(),
+        }
     }
 
     fn usize_to_regaddr(&self, index: usize) -> RegAddr {
@@ -133,6 +669,7 @@ impl CentralProcessingUnit {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arch::trit::TRYTE_MAX;
     use crate::core::{
         address_space::AddressSpace, alu::ArithmeticLogicUnit, registers::Registers,
     };
@@ -144,7 +681,7 @@ mod tests {
     #[test]
     fn test_cpu_arithmetic() {
         // 1. Setup
-        let mut regs = Registers::default();
+        let regs = Registers::default();
         let mut mem = AddressSpace::default();
         let alu = ArithmeticLogicUnit::default();
 
@@ -165,9 +702,9 @@ mod tests {
         let mut cpu = CentralProcessingUnit::from(regs, mem, alu);
 
         // 4. Run Cycles
-        cpu.cycle(); // Exec ADDI x1
-        cpu.cycle(); // Exec ADDI x2
-        cpu.cycle(); // Exec ADD x3
+        cpu.cycle().unwrap(); // Exec ADDI x1
+        cpu.cycle().unwrap(); // Exec ADDI x2
+        cpu.cycle().unwrap(); // Exec ADD x3
 
         // 5. Assertions
         // Check Register 3
@@ -213,18 +750,18 @@ mod tests {
         let mut cpu = CentralProcessingUnit::from(regs, mem, alu);
 
         // 4. Run Cycles
-        cpu.cycle(); // Reg 1 = 42
-        cpu.cycle(); // Mem[100] = 42
+        cpu.cycle().unwrap(); // Reg 1 = 42
+        cpu.cycle().unwrap(); // Mem[100] = 42
 
         // Verify intermediate state: Check if memory actually updated
         let mem_addr_100 = Tryte::from_i128(100);
         assert_eq!(
-            cpu.address_space.read(mem_addr_100).to_i128(),
+            cpu.bus.read(mem_addr_100).to_i128(),
             42,
             "Memory at 100 should be 42"
         );
 
-        cpu.cycle(); // Reg 2 = Mem[100]
+        cpu.cycle().unwrap(); // Reg 2 = Mem[100]
 
         // 5. Assertions
         let reg2_addr = RegAddr::from_i128(2);
@@ -237,6 +774,914 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cpu_runs_assembled_loop() {
+        let program = crate::asm::assembler::assemble(
+            "
+                    addi x1, x0, 3      ; counter
+            loop:   beq  x1, x0, done
+                    addi x2, x2, 2
+                    addi x1, x1, -1
+                    jal  x0, loop
+            done:   addi x3, x0, 1
+                    halt
+            ",
+        )
+        .unwrap();
+
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), mem, ArithmeticLogicUnit::default());
+
+        // 1 setup + 3 iterations of 4 + final beq + done + halt
+        let outcome = cpu.run(100);
+        assert_eq!(outcome.reason, StopReason::Halted);
+        assert_eq!(outcome.retired, 16);
+
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(1)).to_i128(), 0);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(2)).to_i128(), 6);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(3)).to_i128(), 1);
+        assert_eq!(cpu.registers.read_pc().to_i128(), 6);
+    }
+
+    fn cpu_for(source: &str) -> CentralProcessingUnit {
+        let program = crate::asm::assembler::assemble(source).unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        CentralProcessingUnit::from(Registers::default(), mem, ArithmeticLogicUnit::default())
+    }
+
+    #[test]
+    fn test_logic_instructions() {
+        let mut cpu = cpu_for(
+            "
+            addi  x1, x0, 6        ; 0t1T0
+            addi  x2, x0, 4        ; 0t011
+            min   x3, x1, x2
+            max   x4, x1, x2
+            cons  x5, x1, x2
+            amin  x6, x1, x2
+            tsum  x7, x1, x2
+            neg   x8, x1
+            maxi  x9, x1, -13      ; upper trits of the immediate are zero
+            consi x10, x8, -6
+            halt
+            ",
+        );
+
+        assert_eq!(cpu.run(20).reason, StopReason::Halted);
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        assert_eq!(reg(3), -3); // 0t0T0
+        assert_eq!(reg(4), 13); // 0t111
+        assert_eq!(reg(5), 0);
+        assert_eq!(reg(6), 3); // 0t010
+        assert_eq!(reg(7), 10); // 0t101
+        assert_eq!(reg(8), -6);
+        assert_eq!(reg(9), 6); // 0t1T0 max 0tTTT
+        assert_eq!(reg(10), -6);
+    }
+
+    #[test]
+    fn test_shift_and_rotate_instructions() {
+        let mut cpu = cpu_for(
+            "
+            addi x1, x0, 19
+            addi x2, x0, 2
+            shl  x3, x1, x2        ; 19 * 9
+            shr  x4, x1, x2        ; 0t1TT1 -> 0t1T
+            shli x5, x1, -1        ; negative count shifts right
+            rori x6, x1, 1         ; low trit wraps to the top
+            roli x7, x6, 1
+            lui  x8, 2             ; 2 * 3^13
+            addi x8, x8, -5
+            halt
+            ",
+        );
+
+        assert_eq!(cpu.run(20).reason, StopReason::Halted);
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        assert_eq!(reg(3), 171);
+        assert_eq!(reg(4), 2);
+        assert_eq!(reg(5), 6);
+        assert_eq!(reg(6), 3_i128.pow(26) + 6);
+        assert_eq!(reg(7), 19);
+        assert_eq!(reg(8), 2 * 1_594_323 - 5);
+    }
+
+    #[test]
+    fn test_multiply_and_divide_instructions() {
+        let mut cpu = cpu_for(
+            "
+            addi x1, x0, -3000
+            addi x2, x0, 7
+            mul  x3, x1, x2
+            div  x4, x1, x2        ; -428.57 rounds to -429
+            rem  x5, x1, x2
+            lui  x6, 797161
+            mulh x7, x6, x6
+            div  x8, x1, x0        ; division by zero
+            rem  x9, x1, x0
+            halt
+            ",
+        );
+
+        assert_eq!(cpu.run(20).reason, StopReason::Halted);
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        assert_eq!(reg(3), -21000);
+        assert_eq!(reg(4), -429);
+        assert_eq!(reg(5), 3);
+        // (797161 * 3^13)^2 = 211821886640 * 3^27 + 3^26
+        assert_eq!(reg(7), 211_821_886_640);
+        assert_eq!(reg(8), 0);
+        assert_eq!(reg(9), -3000);
+    }
+
+    #[test]
+    fn test_conditional_branches() {
+        // Each taken branch skips the `addi x9` after it, so x9 counts branches not taken
+        let mut cpu = cpu_for(
+            "
+                 addi x1, x0, -5
+                 addi x2, x0, 3
+                 bne  x1, x2, a
+                 addi x9, x9, 1
+            a:   blt  x1, x2, b
+                 addi x9, x9, 1
+            b:   bge  x1, x2, c      ; not taken
+                 addi x9, x9, 1
+            c:   bltm x1, x2, d      ; |-5| < |3| is false
+                 addi x9, x9, 1
+            d:   bgem x1, x2, e
+                 addi x9, x9, 1
+            e:   bne  x2, x2, f      ; not taken
+                 addi x9, x9, 1
+            f:   bge  x2, x2, g
+                 addi x9, x9, 1
+            g:   halt
+            ",
+        );
+
+        assert_eq!(cpu.run(30).reason, StopReason::Halted);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(9)).to_i128(), 3);
+    }
+
+    #[test]
+    fn test_blt_compares_correctly_across_overflow() {
+        let mut cpu = cpu_for(
+            "
+                 addi x1, x0, 1
+                 shli x1, x1, 26
+                 neg  x2, x1
+                 blt  x2, x1, less   ; x2 - x1 wraps, but the sign flag is exact
+                 halt
+            less: addi x3, x0, 1
+                 halt
+            ",
+        );
+
+        cpu.run(10);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(3)).to_i128(), 1);
+    }
+
+    #[test]
+    fn test_sign_branch_is_three_way() {
+        let source = "
+                 lw   x1, 100(x0)
+                 bsgn x1, neg, pos
+                 addi x5, x0, 0      ; zero falls through
+                 halt
+            neg: addi x5, x0, -1
+                 halt
+            pos: addi x5, x0, 1
+                 halt
+            ";
+
+        for (input, expected) in [(-42, -1), (0, 0), (42, 1)] {
+            let mut cpu = cpu_for(source);
+            cpu.bus
+                .write(Tryte::from_i128(100), Tryte::from_i128(input));
+            cpu.registers
+                .write_gpr(RegAddr::from_i128(5), Tryte::from_i128(9));
+
+            assert_eq!(cpu.run(10).reason, StopReason::Halted);
+            assert_eq!(
+                cpu.registers.read_gpr(RegAddr::from_i128(5)).to_i128(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_jalr_links_and_jumps_to_register() {
+        let mut cpu = cpu_for(
+            "
+            addi x5, x0, 3
+            jalr x5, x5, 1      ; rd == rs1: the target uses the old value
+            halt
+            halt
+            addi x6, x0, 1
+            halt
+            ",
+        );
+
+        cpu.run(10);
+        assert_eq!(cpu.registers.read_pc().to_i128(), 5);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(5)).to_i128(), 2);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(6)).to_i128(), 1);
+    }
+
+    #[test]
+    fn test_nested_calls_return_to_their_callers() {
+        let mut cpu = cpu_for(
+            "
+                   addi x2, x0, 1000   ; stack pointer
+                   addi x10, x0, 5
+                   call square_plus_one
+                   addi x11, x10, 0
+                   halt
+
+            ; x10 = x10 * x10 + 1, saving ra around the inner call
+            square_plus_one:
+                   addi x2, x2, -1
+                   sw   ra, 0(x2)
+                   call square
+                   addi x10, x10, 1
+                   lw   ra, 0(x2)
+                   addi x2, x2, 1
+                   ret
+
+            square:
+                   mul  x10, x10, x10
+                   ret
+            ",
+        );
+
+        assert_eq!(cpu.run(100).reason, StopReason::Halted);
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        assert_eq!(reg(11), 26);
+        assert_eq!(reg(2), 1000, "stack is balanced");
+    }
+
+    #[test]
+    fn test_status_register_and_multi_word_add() {
+        // Two-word numbers: (x2:x1) + (x4:x3) into (x6:x5)
+        let mut cpu = cpu_for(
+            "
+            lw   x1, 100(x0)
+            lw   x2, 101(x0)
+            lw   x3, 102(x0)
+            lw   x4, 103(x0)
+            add  x5, x1, x3
+            addc x6, x2, x4
+            halt
+            ",
+        );
+        for (offset, value) in [TRYTE_MAX, 7, TRYTE_MAX - 1, -2].into_iter().enumerate() {
+            cpu.bus.write(
+                Tryte::from_i128(100 + offset as i128),
+                Tryte::from_i128(value),
+            );
+        }
+
+        cpu.run(10);
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        let word = 3_i128.pow(27);
+        assert_eq!(
+            reg(6) * word + reg(5),
+            (7 * word + TRYTE_MAX) + (-2 * word + TRYTE_MAX - 1)
+        );
+
+        // The ADD overflowed; ADDC did not, and its flags are what is left in the register
+        let status = Status::from_tryte(cpu.registers.read_status());
+        assert_eq!(
+            status,
+            Status {
+                carry: Trit::Zero,
+                overflow: false,
+                sign: Trit::Positive,
+                zero: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_overflow_checked_add_traps() {
+        let mut cpu = cpu_for(
+            "
+            addi x1, x0, 1
+            shli x1, x1, 26
+            addv x2, x1, x1
+            halt
+            ",
+        );
+        cpu.registers
+            .write_gpr(RegAddr::from_i128(2), Tryte::from_i128(-9));
+
+        let outcome = cpu.run(10);
+        assert_eq!(
+            outcome.reason,
+            StopReason::Fault(Trap {
+                cause: TrapCause::Overflow,
+                pc: Tryte::from_i128(2),
+            })
+        );
+        assert_eq!(
+            cpu.registers.read_gpr(RegAddr::from_i128(2)).to_i128(),
+            -9,
+            "rd is untouched"
+        );
+
+        // Without overflow ADDV behaves like ADD and sets the status flags
+        let mut cpu = cpu_for(
+            "addi x1, x0, -4
+subv x2, x1, x1
+halt",
+        );
+        assert_eq!(cpu.run(10).reason, StopReason::Halted);
+        assert!(Status::from_tryte(cpu.registers.read_status()).zero);
+    }
+
+    #[test]
+    fn test_halt_stops_and_stays_stopped() {
+        let mut cpu = cpu_for("addi x1, x0, 7\nhalt\naddi x1, x0, 9");
+
+        let outcome = cpu.run(10);
+        assert_eq!(outcome.reason, StopReason::Halted);
+        assert_eq!(outcome.retired, 2);
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.registers.read_pc().to_i128(), 1, "PC stays on HALT");
+
+        // Further cycles and runs are no-ops
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.run(10).retired, 0);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(1)).to_i128(), 7);
+    }
+
+    #[test]
+    fn test_run_stops_at_cycle_limit() {
+        let mut cpu = cpu_for("loop: addi x1, x1, 1\njal x0, loop");
+
+        let outcome = cpu.run(7);
+        assert_eq!(outcome.reason, StopReason::CycleLimit);
+        assert_eq!(outcome.retired, 7);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(1)).to_i128(), 4);
+    }
+
+    #[test]
+    fn test_run_stops_at_breakpoint_and_resumes() {
+        let mut cpu = cpu_for(
+            "
+                  addi x1, x0, 3
+            loop: addi x1, x1, -1
+                  beq  x1, x0, end
+                  jal  x0, loop
+            end:  halt
+            ",
+        );
+        cpu.add_breakpoint(Tryte::from_i128(1));
+
+        let outcome = cpu.run(100);
+        assert_eq!(outcome.reason, StopReason::Breakpoint(Tryte::from_i128(1)));
+        assert_eq!(outcome.retired, 1);
+
+        // Resuming executes the breakpointed instruction and stops on the next pass
+        let outcome = cpu.run(100);
+        assert_eq!(outcome.reason, StopReason::Breakpoint(Tryte::from_i128(1)));
+        assert_eq!(outcome.retired, 3);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(1)).to_i128(), 2);
+
+        assert!(cpu.remove_breakpoint(Tryte::from_i128(1)));
+        assert_eq!(cpu.run(100).reason, StopReason::Halted);
+    }
+
+    #[test]
+    fn test_run_faults_on_unknown_opcode() {
+        let mut cpu = cpu_for("addi x1, x0, 1\n.word -1");
+
+        let outcome = cpu.run(10);
+        assert_eq!(
+            outcome.reason,
+            StopReason::Fault(Trap {
+                cause: TrapCause::IllegalInstruction(Tryte::from_i128(-1)),
+                pc: Tryte::from_i128(1),
+            })
+        );
+        assert_eq!(outcome.retired, 1);
+        assert_eq!(cpu.registers.read_pc().to_i128(), 1);
+    }
+
+    #[test]
+    fn test_cycle_returns_trap_without_handler() {
+        let mut cpu = cpu_for(".word -1");
+
+        let trap = cpu.cycle().unwrap_err();
+        assert_eq!(
+            trap.cause,
+            TrapCause::IllegalInstruction(Tryte::from_i128(-1))
+        );
+        assert_eq!(trap.pc.to_i128(), 0);
+        assert_eq!(
+            cpu.registers.read_pc().to_i128(),
+            0,
+            "PC stays on the fault"
+        );
+    }
+
+    #[test]
+    fn test_trap_vector_handles_illegal_instruction() {
+        let mut cpu = cpu_for(
+            "
+                     addi x1, x0, 1
+                     .word 0t1T1T1       ; unknown opcode
+                     addi x1, x0, 2
+                     halt
+            .org 50
+            handler: addi x5, x5, 1
+                     halt
+            ",
+        );
+        cpu.registers.write_trap_vector(Some(&Tryte::from_i128(50)));
+
+        let outcome = cpu.run(10);
+        assert_eq!(outcome.reason, StopReason::Halted);
+        // addi, addi in handler, halt; the trapping word does not retire
+        assert_eq!(outcome.retired, 3);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(5)).to_i128(), 1);
+        assert_eq!(cpu.registers.read_trap_pc().to_i128(), 1);
+        assert_eq!(
+            cpu.registers.read_trap_cause().to_i128(),
+            TrapCause::IllegalInstruction(Tryte::default()).code()
+        );
+    }
+
+    #[test]
+    fn test_access_faults_leave_state_untouched_and_tret_returns() {
+        let program = crate::asm::assembler::assemble(
+            "
+                     addi x1, x0, 7
+                     sw   x1, 500(x0)    ; beyond installed memory
+                     lw   x2, -1(x0)     ; negative addresses are unmapped
+                     halt
+            .org 20
+            handler: addi x9, x9, 1
+                     tret
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::with_size(100);
+        program.load_into(&mut mem);
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), mem, ArithmeticLogicUnit::default());
+
+        cpu.cycle().unwrap();
+        let trap = cpu.cycle().unwrap_err();
+        assert_eq!(
+            trap.cause,
+            TrapCause::StoreAccessFault(Tryte::from_i128(500))
+        );
+        assert_eq!(cpu.bus.read(Tryte::from_i128(500)).to_i128(), 0);
+
+        // With a handler installed, TRET returns to the faulting instruction,
+        // so the store keeps trapping until the cycle budget runs out
+        cpu.registers.write_trap_vector(Some(&Tryte::from_i128(20)));
+        let outcome = cpu.run(9);
+        assert_eq!(outcome.reason, StopReason::CycleLimit);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(9)).to_i128(), 3);
+        assert_eq!(cpu.registers.read_trap_pc().to_i128(), 1);
+        assert_eq!(
+            cpu.registers.read_trap_cause().to_i128(),
+            TrapCause::StoreAccessFault(Tryte::default()).code()
+        );
+
+        // Fetching outside installed memory faults too
+        cpu.registers.write_trap_vector(None);
+        cpu.registers.write_pc(&Tryte::from_i128(-5));
+        assert_eq!(
+            cpu.cycle().unwrap_err().cause,
+            TrapCause::InstructionAccessFault(Tryte::from_i128(-5))
+        );
+    }
+
+    #[test]
+    fn test_program_reaches_devices_and_rom_through_the_bus() {
+        use crate::core::bus::{Bus, Device};
+
+        /// Reads back the number of cycles it has seen
+        struct CycleCounter(i128);
+
+        impl Device for CycleCounter {
+            fn read(&mut self, _offset: i128) -> Tryte {
+                Tryte::from_i128(self.0)
+            }
+
+            fn write(&mut self, _offset: i128, value: Tryte) {
+                self.0 = value.to_i128();
+            }
+
+            fn tick(&mut self) {
+                self.0 += 1;
+            }
+        }
+
+        let program = crate::asm::assembler::assemble(
+            "
+            addi x1, x0, 100
+            sw   x1, 1000(x0)    ; counter = 100, then ticks to 101
+            nop
+            lw   x2, 1000(x0)    ; 102 when read
+            lw   x3, 2000(x0)
+            sw   x3, 2000(x0)    ; ROM
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut bus = Bus::from(mem);
+        bus.attach(1000, 1, CycleCounter(0)).unwrap();
+        bus.map_rom(2000, vec![Tryte::from_i128(-9)]).unwrap();
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+
+        let outcome = cpu.run(10);
+        assert_eq!(
+            outcome.reason,
+            StopReason::Fault(Trap {
+                cause: TrapCause::StoreAccessFault(Tryte::from_i128(2000)),
+                pc: Tryte::from_i128(5),
+            })
+        );
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(2)).to_i128(), 102);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(3)).to_i128(), -9);
+        assert_eq!(cpu.bus.read(Tryte::from_i128(2000)).to_i128(), -9);
+    }
+
+    #[test]
+    fn test_interrupts_wait_for_ei_and_iret_resumes() {
+        use crate::core::bus::{Bus, Device};
+
+        /// Raises its line until any word is written to it
+        struct Doorbell(bool);
+
+        impl Device for Doorbell {
+            fn read(&mut self, _offset: i128) -> Tryte {
+                Tryte::default()
+            }
+
+            fn write(&mut self, _offset: i128, _value: Tryte) {
+                self.0 = false;
+            }
+
+            fn interrupt(&self) -> bool {
+                self.0
+            }
+        }
+
+        let program = crate::asm::assembler::assemble(
+            "
+                     addi x1, x0, 1
+                     addi x1, x1, 1      ; interrupts start disabled
+                     ei
+                     addi x1, x1, 1      ; interrupted before this runs
+                     di
+                     halt
+
+            .org 20
+            handler: addi x2, x1, 0      ; x1 as the handler saw it
+                     sw   x0, 500(x0)    ; ring off
+                     iret
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut bus = Bus::from(mem);
+        bus.attach_interrupting(500, 1, 4, Doorbell(true)).unwrap();
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+        cpu.interrupts_mut()
+            .write_vector(Some(&Tryte::from_i128(20)));
+
+        cpu.run(3);
+        assert_eq!(cpu.registers.read_pc().to_i128(), 3);
+        assert!(cpu.interrupts().is_enabled());
+
+        assert_eq!(cpu.run(20).reason, StopReason::Halted);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(2)).to_i128(), 2);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(1)).to_i128(), 3);
+        assert_eq!(cpu.interrupts().read_saved_pc().to_i128(), 3);
+        assert!(!cpu.interrupts().is_enabled());
+    }
+
+    #[test]
+    fn test_csr_instructions_swap_set_and_clear() {
+        let mut cpu = cpu_for(
+            "
+            addi  x1, x0, 42
+            csrrw x2, scratch, x1    ; x2 = 0, scratch = 42
+            csrrw x3, scratch, x0    ; x3 = 42, scratch = 0
+            csrrs x4, cycle, x0      ; 3 cycles have run
+            csrrw x0, instret, x1    ; read-only, ignored
+            addi  x5, x0, 4          ; 0t11
+            addi  x6, x0, 3          ; 0t10
+            csrrs x0, scratch, x5    ; set trits 0 and 1
+            csrrc x7, scratch, x6    ; clear trit 0, keep trit 1
+            csrrs x8, scratch, x0
+            csrrs x9, hartid, x0
+            csrrs x10, instret, x0
+            csrrs x0, 500, x0
+            ",
+        );
+
+        let outcome = cpu.run(20);
+        let StopReason::Fault(trap) = outcome.reason else {
+            panic!("expected a trap, got {:?}", outcome.reason);
+        };
+        assert_eq!(trap.pc.to_i128(), 12);
+        // The trap carries the word that was fetched
+        let fetched = cpu.bus.read(trap.pc);
+        assert_eq!(trap.cause, TrapCause::IllegalInstruction(fetched));
+
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        assert_eq!(reg(2), 0);
+        assert_eq!(reg(3), 42);
+        assert_eq!(reg(4), 3);
+        assert_eq!(reg(7), 4);
+        assert_eq!(reg(8), 3);
+        assert_eq!(reg(9), 0);
+        assert_eq!(reg(10), 11);
+        assert_eq!(cpu.read_csr(Csr::Scratch).to_i128(), 3);
+    }
+
+    #[test]
+    fn test_guest_installs_its_own_interrupt_handler() {
+        use crate::core::bus::Bus;
+        use crate::devices::timer::{TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer};
+
+        let program = crate::asm::assembler::assemble(
+            "
+                     addi  x1, x0, handler
+                     csrrw x0, ivec, x1
+                     addi  x1, x0, 1
+                     csrrs x0, ie, x1
+                     addi  x1, x0, 5
+                     sw    x1, -719(x0)     ; compare
+                     addi  x1, x0, 1
+                     sw    x1, -718(x0)     ; one-shot
+            idle:    jal   x0, idle
+            handler: csrrs x9, ipc, x0
+                     csrrs x10, ipend, x0
+                     sw    x0, -717(x0)
+                     csrrs x11, ie, x0
+                     halt
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut bus = Bus::from(mem);
+        bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+            .unwrap();
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+
+        assert_eq!(cpu.run(50).reason, StopReason::Halted);
+        let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+        assert_eq!(reg(9), 8);
+        assert_eq!(reg(10), 1);
+        assert_eq!(reg(11), 0);
+    }
+
+    #[test]
+    fn test_traps_and_interrupts_keep_separate_return_pcs() {
+        use crate::core::bus::Bus;
+        use crate::devices::timer::{TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer};
+
+        // Installs both handlers and starts a one-shot timer that fires 12 cycles later
+        const SETUP: &str = "
+                     addi  x1, x0, trap
+                     csrrw x0, tvec, x1
+                     addi  x1, x0, irq
+                     csrrw x0, ivec, x1
+                     addi  x1, x0, 12
+                     sw    x1, -719(x0)     ; compare
+                     addi  x1, x0, 1
+                     sw    x1, -718(x0)     ; one-shot
+                     ei
+        ";
+        let run = |source: &str| {
+            let program = crate::asm::assembler::assemble(&format!("{}{}", SETUP, source));
+            let program = program.unwrap();
+            let mut mem = AddressSpace::default();
+            program.load_into(&mut mem);
+            let mut bus = Bus::from(mem);
+            bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+                .unwrap();
+            let mut cpu = CentralProcessingUnit::from(
+                Registers::default(),
+                bus,
+                ArithmeticLogicUnit::default(),
+            );
+            assert_eq!(cpu.run(200).reason, StopReason::Halted);
+            let reg = |index| cpu.registers.read_gpr(RegAddr::from_i128(index)).to_i128();
+            let symbol = |name| program.symbol(name).unwrap();
+            (
+                reg(7),
+                reg(8),
+                [symbol("trap"), symbol("irq"), symbol("fault")],
+            )
+        };
+
+        // A fault inside the interrupt handler leaves the interrupted PC alone
+        let (resume, ipc, [_, irq, fault]) = run("
+            idle:    beq   x4, x0, idle
+                     halt
+            irq:     sw    x0, -717(x0)     ; acknowledge
+            fault:   .word 0t1T1T1T1T1T1T1T1T1T1T1T1T1
+                     csrrs x8, ipc, x0
+                     addi  x4, x0, 1
+                     iret
+            trap:    csrrs x7, tpc, x0
+                     addi  x7, x7, 1
+                     csrrw x0, tpc, x7
+                     tret
+            ");
+        // The idle loop sits two words before the interrupt handler
+        assert_eq!(ipc, irq - 2);
+        assert_eq!(resume, fault + 1);
+
+        // The timer fires inside the trap handler without touching the trap PC
+        let (resume, ipc, [trap, irq, fault]) = run("
+            fault:   .word 0t1T1T1T1T1T1T1T1T1T1T1T1T1
+                     halt
+            trap:    addi  x6, x0, 8          ; long enough for the timer to fire
+            wait:    addi  x6, x6, -1
+                     bne   x6, x0, wait
+                     csrrs x7, tpc, x0
+                     addi  x7, x7, 1
+                     csrrw x0, tpc, x7
+                     tret
+            irq:     sw    x0, -717(x0)     ; acknowledge
+                     csrrs x8, ipc, x0
+                     iret
+            ");
+        assert!((trap..irq).contains(&ipc), "interrupted at {}", ipc);
+        assert_eq!(resume, fault + 1);
+    }
+
+    #[test]
+    fn test_breakpoint_on_interrupt_handler_fires() {
+        use crate::core::bus::Bus;
+        use crate::debugger::{DebugStop, Debugger};
+        use crate::devices::timer::{TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer};
+
+        let program = crate::asm::assembler::assemble(
+            "
+                     addi  x1, x0, 4
+                     sw    x1, -719(x0)     ; compare
+                     addi  x1, x0, 1
+                     sw    x1, -718(x0)     ; one-shot
+                     ei
+            idle:    jal   x0, idle
+            handler: addi  x2, x0, 1
+                     halt
+            ",
+        )
+        .unwrap();
+        let handler = program.symbol("handler").unwrap();
+        let machine = || {
+            let mut mem = AddressSpace::default();
+            program.load_into(&mut mem);
+            let mut bus = Bus::from(mem);
+            bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+                .unwrap();
+            let mut cpu = CentralProcessingUnit::from(
+                Registers::default(),
+                bus,
+                ArithmeticLogicUnit::default(),
+            );
+            cpu.interrupts_mut()
+                .write_vector(Some(&Tryte::from_i128(handler)));
+            cpu
+        };
+
+        let mut cpu = machine();
+        cpu.add_breakpoint(Tryte::from_i128(handler));
+        let outcome = cpu.run(50);
+        assert_eq!(
+            outcome.reason,
+            StopReason::Breakpoint(Tryte::from_i128(handler))
+        );
+        // Entering the handler is a cycle of its own that retires nothing
+        assert_eq!(
+            cpu.read_csr(Csr::Cycle).to_i128(),
+            outcome.retired as i128 + 1
+        );
+        assert_eq!(cpu.interrupts().read_saved_pc().to_i128(), handler - 1);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(2)).to_i128(), 0);
+
+        assert_eq!(cpu.run(50).reason, StopReason::Halted);
+        assert_eq!(cpu.registers.read_gpr(RegAddr::from_i128(2)).to_i128(), 1);
+
+        let mut debugger = Debugger::new(machine());
+        debugger.add_breakpoint(handler);
+        assert_eq!(debugger.continue_for(50), DebugStop::Breakpoint(handler));
+        assert_eq!(debugger.continue_for(50), DebugStop::Halted);
+    }
+
+    #[test]
+    fn test_step_back_undoes_every_cycle() {
+        use crate::core::bus::Bus;
+        use crate::devices::timer::{TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer};
+        use crate::snapshot::Snapshot;
+
+        let program = crate::asm::assembler::assemble(
+            "
+                     addi  x1, x0, handler
+                     csrrw x0, ivec, x1
+                     addi  x1, x0, 6
+                     sw    x1, -719(x0)     ; compare
+                     addi  x1, x0, 4
+                     sw    x1, -718(x0)     ; periodic
+                     ei
+            loop:    addi  x2, x2, 1
+                     sw    x2, 100(x0)
+                     jal   x0, loop
+            handler: csrrw x3, scratch, x2
+                     sw    x0, -717(x0)
+                     iret
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut bus = Bus::from(mem);
+        bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+            .unwrap();
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+        cpu.set_history_depth(100);
+
+        let mut states = Vec::new();
+        for _ in 0..60 {
+            states.push(Snapshot::capture(&cpu));
+            cpu.cycle().unwrap();
+        }
+        assert!(cpu.read_csr(Csr::Scratch).to_i128() > 0, "the timer fired");
+
+        while let Some(expected) = states.pop() {
+            assert!(cpu.step_back());
+            assert_eq!(Snapshot::capture(&cpu), expected);
+        }
+        assert!(!cpu.step_back());
+    }
+
+    #[test]
+    fn test_rewind_and_step_back_to_write() {
+        let mut cpu = cpu_for(
+            "
+                  addi x1, x0, 4
+            loop: sw   x1, 100(x0)
+                  addi x1, x1, -1
+                  bne  x1, x0, loop
+                  sw   x0, 101(x0)
+                  halt
+            ",
+        );
+        cpu.set_history_depth(8);
+        assert_eq!(cpu.run(100).reason, StopReason::Halted);
+        assert_eq!(cpu.history().len(), 8);
+
+        // The last store to 100 wrote 1, at cycle 10
+        assert_eq!(cpu.step_back_to_write(100), Some(10));
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.registers().read_pc().to_i128(), 1);
+        assert_eq!(cpu.bus().read(Tryte::from_i128(100)).to_i128(), 2);
+
+        assert_eq!(
+            cpu.rewind_to(11),
+            Err(HistoryError::InFuture {
+                cycle: 11,
+                current: 10
+            })
+        );
+        assert_eq!(
+            cpu.rewind_to(2),
+            Err(HistoryError::NotRecorded {
+                cycle: 2,
+                oldest: 7
+            })
+        );
+        assert_eq!(cpu.rewind_to(8), Ok(()));
+        assert_eq!(cpu.read_csr(Csr::Cycle).to_i128(), 8);
+        assert_eq!(cpu.step_back_to_write(101), None);
+        assert_eq!(cpu.read_csr(Csr::Cycle).to_i128(), 7);
+
+        // Running forward again replays the same cycles
+        assert_eq!(cpu.run(100).reason, StopReason::Halted);
+        assert_eq!(cpu.read_csr(Csr::Cycle).to_i128(), 15);
+    }
+
     // --- Test Helper ---
 
     /// Encodes instruction fields into a single Tryte (Machine Code)
@@ -0,0 +1,737 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter, Write};
+
+use crate::{
+    arch::trit::Tryte,
+    asm::{
+        assembler::parse_register,
+        disassembler::{DisassembledWord, Disassembler},
+        lexer::parse_number,
+    },
+    core::{
+        bus::Bus,
+        csr::Csr,
+        registers::{RegAddr, Registers},
+        status::Status,
+        trap::Trap,
+    },
+    cpu::CentralProcessingUnit,
+};
+
+/// Cycles `continue` runs before giving control back
+pub const CONTINUE_LIMIT: u64 = 10_000_000;
+
+/// Why the debugger handed control back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugStop {
+    /// One cycle ran and nothing else happened
+    Stepped,
+    Halted,
+    /// The PC reached a breakpoint; the instruction there has not executed yet
+    Breakpoint(i128),
+    MemoryChanged {
+        address: i128,
+        old: Tryte,
+        new: Tryte,
+    },
+    RegisterChanged {
+        register: usize,
+        old: Tryte,
+        new: Tryte,
+    },
+    /// An instruction trapped with no trap vector installed
+    Trap(Trap),
+    CycleLimit,
+}
+
+impl Display for DebugStop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugStop::Stepped => Ok(()),
+            DebugStop::Halted => write!(f, "halted"),
+            DebugStop::Breakpoint(address) => write!(f, "breakpoint at {}", address),
+            DebugStop::MemoryChanged { address, old, new } => write!(
+                f,
+                "watch [{}]: {} -> {}",
+                address,
+                old.to_i128(),
+                new.to_i128()
+            ),
+            DebugStop::RegisterChanged { register, old, new } => write!(
+                f,
+                "watch x{}: {} -> {}",
+                register,
+                old.to_i128(),
+                new.to_i128()
+            ),
+            DebugStop::Trap(trap) => write!(f, "unhandled trap: {}", trap),
+            DebugStop::CycleLimit => write!(f, "stopped after {} cycles", CONTINUE_LIMIT),
+        }
+    }
+}
+
+/// What a watchpoint observes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    Memory(i128),
+    Register(usize),
+}
+
+/// A parsed debugger command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Step(u64),
+    /// Undo this many cycles
+    StepBack(u64),
+    /// Go back to just before the last store to an address
+    BackToWrite(i128),
+    /// Go back to a cycle number
+    Rewind(u64),
+    Continue,
+    Break(i128),
+    Delete(i128),
+    Watch(WatchTarget),
+    Unwatch(WatchTarget),
+    Registers,
+    Memory {
+        address: i128,
+        count: usize,
+    },
+    /// Disassemble this many words either side of the PC
+    Disassemble(i128),
+    /// List breakpoints and watchpoints
+    Info,
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "\
+s, step [N]            Execute N instructions (default 1)
+c, continue            Run until a breakpoint, watchpoint, HALT or trap
+rs, rstep [N]          Undo N cycles (default 1)
+rw, rwrite <ADDR>      Go back to just before the last store to ADDR
+rewind <CYCLE>         Go back to cycle number CYCLE
+b, break <ADDR>        Stop before the instruction at ADDR
+d, delete <ADDR>       Remove the breakpoint at ADDR
+w, watch <ADDR | xN>   Stop when a memory word or register changes
+unwatch <ADDR | xN>    Remove a watchpoint
+r, regs                Print the registers
+x, mem <ADDR> [N]      Print N memory words from ADDR (default 1)
+l, disas [N]           Disassemble N words either side of the PC (default 4)
+i, info                List breakpoints and watchpoints
+h, help                Show this text
+q, quit                Leave the debugger
+
+Numbers are decimal or balanced ternary (0t1T0). Press enter to repeat a command.";
+
+/// A command line the debugger could not parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Unknown(String),
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "unknown command '{}', try 'help'", name),
+            CommandError::MissingArgument(what) => write!(f, "missing {}", what),
+            CommandError::InvalidArgument(text) => write!(f, "invalid argument '{}'", text),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+pub fn parse_command(line: &str) -> Result<Command, CommandError> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err(CommandError::MissingArgument("command"));
+    };
+    let argument = words.next();
+    let second = words.next();
+    if let Some(extra) = words.next() {
+        return Err(CommandError::InvalidArgument(extra.to_string()));
+    }
+
+    let number = |text: Option<&str>, what| {
+        let text = text.ok_or(CommandError::MissingArgument(what))?;
+        parse_number(text).ok_or_else(|| CommandError::InvalidArgument(text.to_string()))
+    };
+    let count = |text: Option<&str>, default: i128| match text {
+        Some(text) => number(Some(text), "count").and_then(|count| {
+            u32::try_from(count)
+                .map(i128::from)
+                .map_err(|_| CommandError::InvalidArgument(text.to_string()))
+        }),
+        None => Ok(default),
+    };
+    let watch_target = |text: Option<&str>| {
+        let text = text.ok_or(CommandError::MissingArgument("address or register"))?;
+        match parse_register(text) {
+            Some(Some(register)) => Ok(WatchTarget::Register(register)),
+            Some(None) => Err(CommandError::InvalidArgument(text.to_string())),
+            None => number(Some(text), "address").map(WatchTarget::Memory),
+        }
+    };
+
+    // Only `mem` takes a second argument
+    if let (Some(extra), false) = (second, matches!(name, "x" | "mem")) {
+        return Err(CommandError::InvalidArgument(extra.to_string()));
+    }
+
+    Ok(match name {
+        "s" | "step" => Command::Step(count(argument, 1)? as u64),
+        "rs" | "rstep" => Command::StepBack(count(argument, 1)? as u64),
+        "rw" | "rwrite" => Command::BackToWrite(number(argument, "address")?),
+        "rewind" => {
+            let cycle = number(argument, "cycle")?;
+            Command::Rewind(u64::try_from(cycle).map_err(|_| {
+                CommandError::InvalidArgument(argument.unwrap_or_default().to_string())
+            })?)
+        }
+        "c" | "continue" => Command::Continue,
+        "b" | "break" => Command::Break(number(argument, "address")?),
+        "d" | "delete" => Command::Delete(number(argument, "address")?),
+        "w" | "watch" => Command::Watch(watch_target(argument)?),
+        "unwatch" => Command::Unwatch(watch_target(argument)?),
+        "r" | "regs" => Command::Registers,
+        "x" | "mem" => Command::Memory {
+            address: number(argument, "address")?,
+            count: count(second, 1)? as usize,
+        },
+        "l" | "disas" => Command::Disassemble(count(argument, 4)?),
+        "i" | "info" => Command::Info,
+        "h" | "help" => Command::Help,
+        "q" | "quit" => Command::Quit,
+        other => return Err(CommandError::Unknown(other.to_string())),
+    })
+}
+
+/// Drives a CPU one `cycle` at a time, stopping on breakpoints and watchpoints
+pub struct Debugger {
+    cpu: CentralProcessingUnit,
+    breakpoints: BTreeSet<i128>,
+    /// Watched words with the value last seen
+    memory_watches: BTreeMap<i128, Tryte>,
+    register_watches: BTreeMap<usize, Tryte>,
+    disassembler: Disassembler,
+}
+
+impl Debugger {
+    pub fn new(cpu: CentralProcessingUnit) -> Self {
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            memory_watches: BTreeMap::new(),
+            register_watches: BTreeMap::new(),
+            disassembler: Disassembler::new(),
+        }
+    }
+
+    /// Disassembler used for listings, e.g. one that knows the program's labels
+    pub fn with_disassembler(mut self, disassembler: Disassembler) -> Self {
+        self.disassembler = disassembler;
+        self
+    }
+
+    pub fn cpu(&self) -> &CentralProcessingUnit {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut CentralProcessingUnit {
+        &mut self.cpu
+    }
+
+    pub fn pc(&self) -> i128 {
+        self.cpu.registers().read_pc().to_i128()
+    }
+
+    pub fn add_breakpoint(&mut self, address: i128) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: i128) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn watch(&mut self, target: WatchTarget) {
+        match target {
+            WatchTarget::Memory(address) => {
+                let value = self.cpu.bus().read(Tryte::from_i128(address));
+                self.memory_watches.insert(address, value);
+            }
+            WatchTarget::Register(register) => {
+                let value = self.read_register(register);
+                self.register_watches.insert(register, value);
+            }
+        }
+    }
+
+    pub fn unwatch(&mut self, target: WatchTarget) -> bool {
+        match target {
+            WatchTarget::Memory(address) => self.memory_watches.remove(&address).is_some(),
+            WatchTarget::Register(register) => self.register_watches.remove(&register).is_some(),
+        }
+    }
+
+    fn read_register(&self, register: usize) -> Tryte {
+        self.cpu
+            .registers()
+            .read_gpr(RegAddr::from_i128(register as i128))
+    }
+
+    /// Executes one cycle
+    pub fn step(&mut self) -> DebugStop {
+        if self.cpu.is_halted() {
+            return DebugStop::Halted;
+        }
+        if let Err(trap) = self.cpu.cycle() {
+            return DebugStop::Trap(trap);
+        }
+
+        // Refresh every watch so the next stop only reports newer changes
+        let mut stop = None;
+        for (&address, seen) in self.memory_watches.iter_mut() {
+            let value = self.cpu.bus().read(Tryte::from_i128(address));
+            if value != *seen {
+                stop.get_or_insert(DebugStop::MemoryChanged {
+                    address,
+                    old: *seen,
+                    new: value,
+                });
+                *seen = value;
+            }
+        }
+        for (&register, seen) in self.register_watches.iter_mut() {
+            let value = self
+                .cpu
+                .registers()
+                .read_gpr(RegAddr::from_i128(register as i128));
+            if value != *seen {
+                stop.get_or_insert(DebugStop::RegisterChanged {
+                    register,
+                    old: *seen,
+                    new: value,
+                });
+                *seen = value;
+            }
+        }
+
+        match stop {
+            Some(stop) => stop,
+            None if self.cpu.is_halted() => DebugStop::Halted,
+            None => DebugStop::Stepped,
+        }
+    }
+
+    /// Runs for at most `max_cycles`. A breakpoint at the current PC does not
+    /// stop it, so continuing from a breakpoint moves past it.
+    pub fn continue_for(&mut self, max_cycles: u64) -> DebugStop {
+        for _ in 0..max_cycles {
+            let stop = self.step();
+            if stop != DebugStop::Stepped {
+                return stop;
+            }
+            if self.breakpoints.contains(&self.pc()) {
+                return DebugStop::Breakpoint(self.pc());
+            }
+        }
+        DebugStop::CycleLimit
+    }
+
+    /// Words from `radius` before to `radius` after the PC
+    pub fn disassemble_around(&self, radius: i128) -> Vec<DisassembledWord> {
+        let pc = self.pc();
+        (pc - radius..=pc + radius)
+            .map(|address| {
+                let raw = self.cpu.bus().read(Tryte::from_i128(address));
+                self.disassembler.disassemble_word(address, raw)
+            })
+            .collect()
+    }
+
+    fn current_instruction(&self) -> String {
+        let raw = self.cpu.bus().read(Tryte::from_i128(self.pc()));
+        self.disassembler
+            .disassemble_word(self.pc(), raw)
+            .to_string()
+    }
+
+    /// Runs a command and returns the text to show. `Quit` is left to the caller.
+    pub fn execute(&mut self, command: Command) -> String {
+        match command {
+            Command::Step(count) => {
+                let mut stop = DebugStop::Stepped;
+                for _ in 0..count {
+                    stop = self.step();
+                    if stop != DebugStop::Stepped {
+                        break;
+                    }
+                }
+                self.report(stop)
+            }
+            Command::StepBack(count) => {
+                let undone = (0..count).take_while(|_| self.cpu.step_back()).count() as u64;
+                self.refresh_watches();
+                let note = if undone < count {
+                    format!("reached the start of the history after {} cycles\n", undone)
+                } else {
+                    String::new()
+                };
+                format!("{}{}", note, self.report_position())
+            }
+            Command::BackToWrite(address) => {
+                let found = self.cpu.step_back_to_write(address);
+                self.refresh_watches();
+                match found {
+                    Some(_) => format!("last store to [{}]\n{}", address, self.report_position()),
+                    None => format!(
+                        "no recorded store to [{}]; reached the start of the history\n{}",
+                        address,
+                        self.report_position()
+                    ),
+                }
+            }
+            Command::Rewind(cycle) => {
+                let rewound = self.cpu.rewind_to(cycle);
+                self.refresh_watches();
+                match rewound {
+                    Ok(()) => self.report_position(),
+                    Err(err) => err.to_string(),
+                }
+            }
+            Command::Continue => {
+                let stop = self.continue_for(CONTINUE_LIMIT);
+                self.report(stop)
+            }
+            Command::Break(address) => {
+                self.add_breakpoint(address);
+                format!("breakpoint at {}", address)
+            }
+            Command::Delete(address) => {
+                if self.remove_breakpoint(address) {
+                    format!("deleted breakpoint at {}", address)
+                } else {
+                    format!("no breakpoint at {}", address)
+                }
+            }
+            Command::Watch(target) => {
+                self.watch(target);
+                format!("watching {}", describe(target))
+            }
+            Command::Unwatch(target) => {
+                if self.unwatch(target) {
+                    format!("stopped watching {}", describe(target))
+                } else {
+                    format!("not watching {}", describe(target))
+                }
+            }
+            Command::Registers => format_registers(self.cpu.registers()),
+            Command::Memory { address, count } => format_memory(self.cpu.bus(), address, count),
+            Command::Disassemble(radius) => {
+                let pc = self.pc();
+                let mut text = String::new();
+                for word in self.disassemble_around(radius) {
+                    let marker = if word.address == pc { "=>" } else { "  " };
+                    // Labels print on their own line, so mark the instruction line
+                    let rendered = word.to_string();
+                    let (label, line) = match rendered.rsplit_once('\n') {
+                        Some((label, line)) => (Some(label), line),
+                        None => (None, rendered.as_str()),
+                    };
+                    if let Some(label) = label {
+                        writeln!(text, "{}", label).unwrap();
+                    }
+                    writeln!(text, "{} {}", marker, line).unwrap();
+                }
+                text.trim_end().to_string()
+            }
+            Command::Info => {
+                let mut lines = Vec::new();
+                for address in &self.breakpoints {
+                    lines.push(format!("breakpoint at {}", address));
+                }
+                for address in self.memory_watches.keys() {
+                    lines.push(format!(
+                        "watching {}",
+                        describe(WatchTarget::Memory(*address))
+                    ));
+                }
+                for register in self.register_watches.keys() {
+                    lines.push(format!(
+                        "watching {}",
+                        describe(WatchTarget::Register(*register))
+                    ));
+                }
+                if lines.is_empty() {
+                    "no breakpoints or watchpoints".to_string()
+                } else {
+                    lines.join("\n")
+                }
+            }
+            Command::Help => HELP.to_string(),
+            Command::Quit => String::new(),
+        }
+    }
+
+    /// After going back in time, so the next step only reports newer changes
+    fn refresh_watches(&mut self) {
+        for (&address, seen) in self.memory_watches.iter_mut() {
+            *seen = self.cpu.bus().read(Tryte::from_i128(address));
+        }
+        for (&register, seen) in self.register_watches.iter_mut() {
+            *seen = self
+                .cpu
+                .registers()
+                .read_gpr(RegAddr::from_i128(register as i128));
+        }
+    }
+
+    /// The cycle number, then the instruction that runs next
+    fn report_position(&self) -> String {
+        format!(
+            "cycle {}\n{}",
+            self.cpu.read_csr(Csr::Cycle).to_i128(),
+            self.current_instruction()
+        )
+    }
+
+    /// Why execution stopped, then the instruction that runs next
+    fn report(&self, stop: DebugStop) -> String {
+        let next = self.current_instruction();
+        match stop {
+            DebugStop::Stepped => next,
+            stop => format!("{}\n{}", stop, next),
+        }
+    }
+}
+
+fn describe(target: WatchTarget) -> String {
+    match target {
+        WatchTarget::Memory(address) => format!("[{}]", address),
+        WatchTarget::Register(register) => format!("x{}", register),
+    }
+}
+
+/// PC, status flags and every register in decimal and trit form
+pub fn format_registers(registers: &Registers) -> String {
+    let mut text = String::new();
+    let pc = registers.read_pc();
+    writeln!(text, "pc  = {:>14}  {}", pc.to_i128(), pc.to_trit_string()).unwrap();
+    let status = Status::from_tryte(registers.read_status());
+    writeln!(
+        text,
+        "st  = carry {} overflow {} sign {} zero {}",
+        status.carry, status.overflow, status.sign, status.zero
+    )
+    .unwrap();
+
+    for index in 0..27 {
+        let value = registers.read_gpr(RegAddr::from_i128(index));
+        writeln!(
+            text,
+            "x{:<2} = {:>14}  {}",
+            index,
+            value.to_i128(),
+            value.to_trit_string()
+        )
+        .unwrap();
+    }
+    text.trim_end().to_string()
+}
+
+/// `count` words from `start` in decimal and trit form. Device registers read as zero.
+pub fn format_memory(bus: &Bus, start: i128, count: usize) -> String {
+    (start..start + count as i128)
+        .map(|address| {
+            let value = bus.read(Tryte::from_i128(address));
+            format!(
+                "[{}] = {:>14}  {}",
+                address,
+                value.to_i128(),
+                value.to_trit_string()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{address_space::AddressSpace, alu::ArithmeticLogicUnit};
+
+    fn debugger_for(source: &str) -> Debugger {
+        let program = crate::asm::assembler::assemble(source).unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let cpu =
+            CentralProcessingUnit::from(Registers::default(), mem, ArithmeticLogicUnit::default());
+        Debugger::new(cpu).with_disassembler(Disassembler::new().with_symbols(&program))
+    }
+
+    const COUNTDOWN: &str = "
+              addi x1, x0, 3
+        loop: addi x1, x1, -1
+              sw   x1, 100(x0)
+              bne  x1, x0, loop
+              halt
+        ";
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_command("s"), Ok(Command::Step(1)));
+        assert_eq!(parse_command("step 0t1T"), Ok(Command::Step(2)));
+        assert_eq!(parse_command("b 12"), Ok(Command::Break(12)));
+        assert_eq!(
+            parse_command("watch x5"),
+            Ok(Command::Watch(WatchTarget::Register(5)))
+        );
+        assert_eq!(
+            parse_command("w -3"),
+            Ok(Command::Watch(WatchTarget::Memory(-3)))
+        );
+        assert_eq!(
+            parse_command("x 100 4"),
+            Ok(Command::Memory {
+                address: 100,
+                count: 4
+            })
+        );
+        assert_eq!(parse_command("l"), Ok(Command::Disassemble(4)));
+        assert_eq!(parse_command("rs 3"), Ok(Command::StepBack(3)));
+        assert_eq!(parse_command("rw 100"), Ok(Command::BackToWrite(100)));
+        assert_eq!(parse_command("rewind 0"), Ok(Command::Rewind(0)));
+
+        assert_eq!(
+            parse_command("frobnicate"),
+            Err(CommandError::Unknown("frobnicate".to_string()))
+        );
+        assert_eq!(
+            parse_command("break"),
+            Err(CommandError::MissingArgument("address"))
+        );
+        assert_eq!(
+            parse_command("watch x99"),
+            Err(CommandError::InvalidArgument("x99".to_string()))
+        );
+        assert_eq!(
+            parse_command("step -1"),
+            Err(CommandError::InvalidArgument("-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_breakpoints_stop_before_the_instruction() {
+        let mut debugger = debugger_for(COUNTDOWN);
+        debugger.add_breakpoint(3);
+
+        assert_eq!(debugger.continue_for(100), DebugStop::Breakpoint(3));
+        // Continuing from the breakpoint moves past it and loops back round
+        assert_eq!(debugger.continue_for(100), DebugStop::Breakpoint(3));
+        assert_eq!(
+            debugger
+                .cpu()
+                .registers()
+                .read_gpr(RegAddr::from_i128(1))
+                .to_i128(),
+            1
+        );
+
+        debugger.remove_breakpoint(3);
+        assert_eq!(debugger.continue_for(100), DebugStop::Halted);
+        assert_eq!(debugger.step(), DebugStop::Halted);
+    }
+
+    #[test]
+    fn test_watchpoints_report_old_and_new_values() {
+        let mut debugger = debugger_for(COUNTDOWN);
+        debugger.watch(WatchTarget::Memory(100));
+        debugger.watch(WatchTarget::Register(1));
+
+        assert_eq!(
+            debugger.continue_for(100),
+            DebugStop::RegisterChanged {
+                register: 1,
+                old: Tryte::from_i128(0),
+                new: Tryte::from_i128(3),
+            }
+        );
+        debugger.continue_for(100);
+        assert_eq!(
+            debugger.continue_for(100),
+            DebugStop::MemoryChanged {
+                address: 100,
+                old: Tryte::from_i128(0),
+                new: Tryte::from_i128(2),
+            }
+        );
+
+        assert!(debugger.unwatch(WatchTarget::Register(1)));
+        assert!(!debugger.unwatch(WatchTarget::Register(1)));
+    }
+
+    #[test]
+    fn test_command_output() {
+        let mut debugger = debugger_for(COUNTDOWN);
+
+        let stepped = debugger.execute(Command::Step(2));
+        assert!(stepped.ends_with("SW x1, 100(x0)"), "{}", stepped);
+
+        let registers = debugger.execute(Command::Registers);
+        let two = Tryte::from_i128(2).to_trit_string();
+        assert!(registers.contains(&format!("x1  =              2  {}", two)));
+
+        debugger.execute(Command::Step(1));
+        assert_eq!(
+            debugger.execute(Command::Memory {
+                address: 100,
+                count: 1
+            }),
+            format!("[100] =              2  {}", two)
+        );
+
+        let listing = debugger.execute(Command::Disassemble(1));
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("=> ") && lines[1].ends_with("BNE x1, x0, -2  ; -> 1 <loop>"));
+
+        let halted = debugger.execute(Command::Continue);
+        assert!(halted.starts_with("halted\n         4  "), "{}", halted);
+        assert!(halted.ends_with("HALT"));
+    }
+
+    #[test]
+    fn test_reverse_commands() {
+        let mut debugger = debugger_for(COUNTDOWN);
+        debugger.cpu_mut().set_history_depth(100);
+        debugger.watch(WatchTarget::Memory(100));
+        while debugger.continue_for(100) != DebugStop::Halted {}
+
+        let found = debugger.execute(Command::BackToWrite(100));
+        assert!(
+            found.starts_with("last store to [100]\ncycle 8\n"),
+            "{}",
+            found
+        );
+        assert!(found.ends_with("SW x1, 100(x0)"), "{}", found);
+        // The watch now holds the value from before that store
+        assert!(matches!(
+            debugger.step(),
+            DebugStop::MemoryChanged { address: 100, .. }
+        ));
+
+        let back = debugger.execute(Command::StepBack(100));
+        assert!(
+            back.starts_with("reached the start of the history after 9 cycles\ncycle 0\n"),
+            "{}",
+            back
+        );
+        assert_eq!(
+            debugger.execute(Command::Rewind(5)),
+            "cycle 5 has not run yet; now at cycle 0"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::{
+    arch::trit::{Trit, Tryte},
+    core::bus::Device,
+};
+
+/// Address of the console's first register
+pub const CONSOLE_BASE: i128 = -729;
+/// Number of words the console occupies
+pub const CONSOLE_LEN: i128 = 2;
+
+/// Writes transmit a character; reads take the next received one, or 0
+pub const DATA: i128 = 0;
+/// Read-only. Trit 0 is the receiver: positive when a character is waiting,
+/// zero when none is yet, negative once the input has closed and drained.
+/// Trit 1 is positive when the transmitter is ready, which it always is.
+pub const STATUS: i128 = 1;
+
+/// Character device bridging guest trytes and host text.
+///
+/// A tryte's integer value is a Unicode scalar value, so `'A'` is 65. Written
+/// values that are not scalar values (negative, surrogates, above U+10FFFF)
+/// are transmitted as U+FFFD.
+pub struct Console {
+    output: Box<dyn Write>,
+    input: Receiver<char>,
+    received: VecDeque<char>,
+    closed: bool,
+}
+
+impl Console {
+    pub fn new(output: impl Write + 'static, input: Receiver<char>) -> Self {
+        Self {
+            output: Box::new(output),
+            input,
+            received: VecDeque::new(),
+            closed: false,
+        }
+    }
+
+    /// Console on the host's stdout and stdin. Stdin is read line by line on a
+    /// background thread so the guest never blocks on it.
+    pub fn stdio() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                for c in line.chars().chain(['\n']) {
+                    if sender.send(c).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Self::new(io::stdout(), receiver)
+    }
+
+    fn poll_input(&mut self) {
+        loop {
+            match self.input.try_recv() {
+                Ok(c) => self.received.push_back(c),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn status(&self) -> Tryte {
+        let mut status = Tryte::default();
+        let ready = if !self.received.is_empty() {
+            Trit::Positive
+        } else if self.closed {
+            Trit::Negative
+        } else {
+            Trit::Zero
+        };
+        status.set_trit(0, ready);
+        status.set_trit(1, Trit::Positive);
+        status
+    }
+}
+
+/// Character for a transmitted tryte
+pub fn tryte_to_char(value: Tryte) -> char {
+    u32::try_from(value.to_i128())
+        .ok()
+        .and_then(char::from_u32)
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// Tryte for a received character
+pub fn char_to_tryte(c: char) -> Tryte {
+    Tryte::from_i128(c as i128)
+}
+
+impl Device for Console {
+    fn read(&mut self, offset: i128) -> Tryte {
+        self.poll_input();
+        match offset {
+            DATA => self
+                .received
+                .pop_front()
+                .map(char_to_tryte)
+                .unwrap_or_default(),
+            STATUS => self.status(),
+            _ => Tryte::default(),
+        }
+    }
+
+    fn write(&mut self, offset: i128, value: Tryte) {
+        if offset == DATA {
+            let mut buffer = [0; 4];
+            let text = tryte_to_char(value).encode_utf8(&mut buffer);
+            // The guest has no way to observe a failed host write
+            let _ = self.output.write_all(text.as_bytes());
+            let _ = self.output.flush();
+        }
+    }
+
+    /// Characters received but not yet read; the host streams are not saved
+    fn save(&self) -> Vec<Tryte> {
+        self.received.iter().copied().map(char_to_tryte).collect()
+    }
+
+    fn restore(&mut self, state: &[Tryte]) {
+        self.received = state.iter().copied().map(tryte_to_char).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        core::{
+            address_space::AddressSpace, alu::ArithmeticLogicUnit, bus::Bus, registers::Registers,
+        },
+        cpu::{CentralProcessingUnit, StopReason},
+    };
+
+    /// Output buffer the test can inspect after handing it to the console
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_character_mapping() {
+        assert_eq!(tryte_to_char(Tryte::from_i128(65)), 'A');
+        assert_eq!(tryte_to_char(Tryte::from_i128(0x1F600)), '😀');
+        for invalid in [-1, 0xD800, 0x110000] {
+            assert_eq!(
+                tryte_to_char(Tryte::from_i128(invalid)),
+                char::REPLACEMENT_CHARACTER
+            );
+        }
+        assert_eq!(char_to_tryte('é').to_i128(), 0xE9);
+    }
+
+    #[test]
+    fn test_receive_status_and_end_of_input() {
+        let (sender, receiver) = mpsc::channel();
+        let mut console = Console::new(io::sink(), receiver);
+
+        assert_eq!(console.read(STATUS).trit(0), Trit::Zero);
+        assert_eq!(console.read(STATUS).trit(1), Trit::Positive);
+
+        sender.send('h').unwrap();
+        sender.send('i').unwrap();
+        drop(sender);
+
+        assert_eq!(console.read(STATUS).trit(0), Trit::Positive);
+        assert_eq!(console.read(DATA).to_i128(), 'h' as i128);
+        assert_eq!(console.read(DATA).to_i128(), 'i' as i128);
+        assert_eq!(console.read(DATA).to_i128(), 0);
+        assert_eq!(console.read(STATUS).trit(0), Trit::Negative);
+    }
+
+    #[test]
+    fn test_program_echoes_input_in_upper_case() {
+        let program = crate::asm::assembler::assemble(
+            "
+            wait: lw   x1, -728(x0)     ; status
+                  mini x1, x1, 1        ; keep the receiver trit
+                  beq  x1, x0, wait
+                  blt  x1, x0, done     ; input closed
+                  lw   x2, -729(x0)
+                  addi x2, x2, -32
+                  sw   x2, -729(x0)
+                  jal  x0, wait
+            done: halt
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+
+        let output = SharedOutput::default();
+        let (sender, receiver) = mpsc::channel();
+        for c in "ternary".chars() {
+            sender.send(c).unwrap();
+        }
+        drop(sender);
+
+        let mut bus = Bus::from(mem);
+        bus.attach(
+            CONSOLE_BASE,
+            CONSOLE_LEN,
+            Console::new(output.clone(), receiver),
+        )
+        .unwrap();
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+
+        assert_eq!(cpu.run(1000).reason, StopReason::Halted);
+        assert_eq!(String::from_utf8(output.0.take()).unwrap(), "TERNARY");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    arch::trit::{Trit, Tryte},
+    core::bus::Device,
+};
+
+/// Address of the timer's first register
+pub const TIMER_BASE: i128 = -720;
+/// Number of words the timer occupies
+pub const TIMER_LEN: i128 = 4;
+/// Interrupt line the timer is wired to, the highest priority
+pub const TIMER_LINE: usize = 0;
+
+/// Cycle counter, incremented every tick; writable
+pub const COUNT: i128 = 0;
+/// The interrupt fires on the tick the counter reaches this value
+pub const COMPARE: i128 = 1;
+/// Trit 0 positive enables the timer. Trit 1 positive makes it periodic:
+/// the counter restarts at 0 each time it reaches the compare value.
+pub const CONTROL: i128 = 2;
+/// Reads 1 while the interrupt is pending; any write acknowledges it
+pub const PENDING: i128 = 3;
+
+/// Programmable timer that raises its interrupt line when the cycle counter
+/// reaches the compare value. The line stays raised until acknowledged.
+#[derive(Debug, Clone, Default)]
+pub struct Timer {
+    count: Tryte,
+    compare: Tryte,
+    control: Tryte,
+    pending: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn enabled(&self) -> bool {
+        self.control.trit(0) == Trit::Positive
+    }
+
+    fn periodic(&self) -> bool {
+        self.control.trit(1) == Trit::Positive
+    }
+}
+
+impl Device for Timer {
+    fn read(&mut self, offset: i128) -> Tryte {
+        match offset {
+            COUNT => self.count,
+            COMPARE => self.compare,
+            CONTROL => self.control,
+            PENDING => Tryte::from_i128(self.pending as i128),
+            _ => Tryte::default(),
+        }
+    }
+
+    fn write(&mut self, offset: i128, value: Tryte) {
+        match offset {
+            COUNT => self.count = value,
+            COMPARE => self.compare = value,
+            CONTROL => self.control = value,
+            PENDING => self.pending = false,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        if !self.enabled() {
+            return;
+        }
+
+        self.count = Tryte::from_i128(self.count.to_i128() + 1);
+        if self.count == self.compare {
+            self.pending = true;
+            if self.periodic() {
+                self.count = Tryte::default();
+            }
+        }
+    }
+
+    fn interrupt(&self) -> bool {
+        self.pending
+    }
+
+    /// The four registers, in address order
+    fn save(&self) -> Vec<Tryte> {
+        vec![
+            self.count,
+            self.compare,
+            self.control,
+            Tryte::from_i128(self.pending as i128),
+        ]
+    }
+
+    fn restore(&mut self, state: &[Tryte]) {
+        let register = |i: usize| state.get(i).copied().unwrap_or_default();
+        self.count = register(0);
+        self.compare = register(1);
+        self.control = register(2);
+        self.pending = register(3) != Tryte::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            address_space::AddressSpace, alu::ArithmeticLogicUnit, bus::Bus, registers::Registers,
+        },
+        cpu::{CentralProcessingUnit, StopReason},
+    };
+
+    #[test]
+    fn test_one_shot_and_periodic_modes() {
+        let mut timer = Timer::new();
+        timer.write(COMPARE, Tryte::from_i128(3));
+        timer.tick();
+        assert_eq!(timer.read(COUNT).to_i128(), 0, "disabled timers hold still");
+
+        timer.write(CONTROL, Tryte::from_i128(1));
+        for _ in 0..3 {
+            assert!(!timer.interrupt());
+            timer.tick();
+        }
+        assert!(timer.interrupt());
+        assert_eq!(timer.read(PENDING).to_i128(), 1);
+
+        timer.write(PENDING, Tryte::default());
+        assert!(!timer.interrupt());
+
+        // One-shot: the counter runs on past the compare value
+        timer.tick();
+        assert_eq!(timer.read(COUNT).to_i128(), 4);
+
+        timer.write(COUNT, Tryte::default());
+        timer.write(CONTROL, Tryte::from_i128(4));
+        for _ in 0..3 {
+            timer.tick();
+        }
+        assert!(timer.interrupt());
+        assert_eq!(timer.read(COUNT).to_i128(), 0);
+    }
+
+    #[test]
+    fn test_guest_handler_counts_timer_interrupts() {
+        let program = crate::asm::assembler::assemble(
+            "
+                     addi x1, x0, 50
+                     sw   x1, -719(x0)     ; compare
+                     addi x1, x0, 4        ; enabled, periodic
+                     sw   x1, -718(x0)
+                     ei
+            idle:    jal  x0, idle
+
+            .org 40
+            handler: lw   x9, 100(x0)
+                     addi x9, x9, 1
+                     sw   x9, 100(x0)
+                     sw   x0, -717(x0)     ; acknowledge
+                     iret
+            ",
+        )
+        .unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut bus = Bus::from(mem);
+        bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+            .unwrap();
+
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+        cpu.interrupts_mut()
+            .write_vector(Some(&Tryte::from_i128(40)));
+
+        // Enabled by the 4th cycle, the timer fires after cycles 53, 103, ..., 1003;
+        // the last handler finishes its store by cycle 1006
+        assert_eq!(cpu.run(1010).reason, StopReason::CycleLimit);
+        assert_eq!(cpu.bus().read(Tryte::from_i128(100)).to_i128(), 20);
+        assert_eq!(cpu.registers().read_pc().to_i128(), 5);
+    }
+}
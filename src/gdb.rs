@@ -0,0 +1,768 @@
+//! GDB remote serial protocol stub.
+//!
+//! GDB only knows byte-addressed targets, so the stub presents each 27-trit
+//! word as 8 bytes: the word's integer value as a little-endian two's-complement
+//! `i64`, the same encoding `.bst` images use. Word `n` lives at byte address
+//! `8 * n` (negative words wrap around the top of GDB's 64-bit address space),
+//! and the PC register holds a byte address too. Registers and memory are both
+//! shown in this encoding; writes of values outside the 27-trit range are
+//! rejected with `E01`.
+//!
+//! Registers are numbered x0..x26, then `pc` (27) and `status` (28), as
+//! described by the `target.xml` served through `qXfer:features:read`.
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::{
+    arch::trit::{TRYTE_MAX, Tryte},
+    core::registers::RegAddr,
+    debugger::{DebugStop, Debugger},
+};
+
+/// GDB number of the PC register
+pub const PC_REGISTER: usize = 27;
+/// GDB number of the status register
+pub const STATUS_REGISTER: usize = 28;
+pub const REGISTER_COUNT: usize = 29;
+/// Bytes per word and per register
+pub const WORD_BYTES: usize = 8;
+
+/// Largest packet GDB may send, advertised in `qSupported`
+const PACKET_SIZE: usize = 4096;
+/// Cycles `continue` runs between checks for a GDB interrupt
+const CONTINUE_SLICE: u64 = 10_000;
+/// Byte GDB sends to interrupt a running target (Ctrl-C)
+const INTERRUPT: u8 = 0x03;
+
+/// The 8 bytes GDB sees for a word
+pub fn encode_tryte(value: &Tryte) -> [u8; WORD_BYTES] {
+    (value.to_i128() as i64).to_le_bytes()
+}
+
+/// The word for 8 bytes from GDB, if the value fits in 27 trits
+pub fn decode_tryte(bytes: [u8; WORD_BYTES]) -> Option<Tryte> {
+    let value = i64::from_le_bytes(bytes) as i128;
+    (-TRYTE_MAX..=TRYTE_MAX)
+        .contains(&value)
+        .then(|| Tryte::from_i128(value))
+}
+
+/// GDB byte address of a word address
+pub fn byte_address(word: i128) -> u64 {
+    (word as i64).wrapping_mul(WORD_BYTES as i64) as u64
+}
+
+/// Word address and byte offset within it of a GDB byte address
+fn split_address(address: u64) -> (i128, usize) {
+    let address = address as i64;
+    (
+        address.div_euclid(WORD_BYTES as i64) as i128,
+        address.rem_euclid(WORD_BYTES as i64) as usize,
+    )
+}
+
+/// Target description naming every register
+pub fn target_xml() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n\
+         <!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n\
+         <feature name=\"org.eris.bst27i.core\">\n",
+    );
+    for index in 0..27 {
+        writeln!(
+            xml,
+            "<reg name=\"x{}\" bitsize=\"64\" type=\"int64\" regnum=\"{}\"/>",
+            index, index
+        )
+        .unwrap();
+    }
+    writeln!(
+        xml,
+        "<reg name=\"pc\" bitsize=\"64\" type=\"code_ptr\" regnum=\"{}\"/>",
+        PC_REGISTER
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "<reg name=\"status\" bitsize=\"64\" type=\"int64\" regnum=\"{}\"/>",
+        STATUS_REGISTER
+    )
+    .unwrap();
+    xml.push_str("</feature>\n</target>\n");
+    xml
+}
+
+/// What the stub does with a packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Reply(String),
+    Step,
+    Continue,
+    Detach,
+    Kill,
+}
+
+/// What arrived from GDB
+enum Incoming {
+    Packet(String),
+    Interrupt,
+    Closed,
+}
+
+/// Framing and acknowledgement of packets on one connection
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Set once GDB agrees to `QStartNoAckMode`
+    no_ack: bool,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        // Packets are small and answered one at a time
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            no_ack: false,
+        })
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.reader.fill_buf()?.first().copied();
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    fn receive(&mut self) -> io::Result<Incoming> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(Incoming::Closed),
+                Some(INTERRUPT) => return Ok(Incoming::Interrupt),
+                Some(b'$') => {}
+                // Acks, and noise between packets
+                Some(_) => continue,
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(Incoming::Closed),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|text| u8::from_str_radix(text, 16).ok())
+                == Some(checksum_of(&data));
+            if !self.no_ack {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Incoming::Packet(
+                    String::from_utf8_lossy(&data).into_owned(),
+                ));
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let mut escaped = Vec::with_capacity(data.len());
+        for byte in data.bytes() {
+            if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+                escaped.extend([b'}', byte ^ 0x20]);
+            } else {
+                escaped.push(byte);
+            }
+        }
+
+        let mut packet = vec![b'$'];
+        packet.extend(&escaped);
+        packet.extend(format!("#{:02x}", checksum_of(&escaped)).bytes());
+        self.writer.write_all(&packet)?;
+        self.writer.flush()
+    }
+
+    /// Whether GDB sent an interrupt, without blocking. A closed
+    /// connection counts as one so a running guest stops.
+    fn interrupted(&mut self) -> io::Result<bool> {
+        if self.reader.buffer().is_empty() {
+            self.reader.get_ref().set_nonblocking(true)?;
+            let filled = self.reader.fill_buf().map(|buf| buf.is_empty());
+            self.reader.get_ref().set_nonblocking(false)?;
+            match filled {
+                Ok(true) => return Ok(true),
+                Ok(false) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(err) => return Err(err),
+            }
+        }
+
+        if self.reader.buffer().first() == Some(&INTERRUPT) {
+            self.reader.consume(1);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    u64::from_str_radix(text, 16).ok()
+}
+
+/// Stop reply for a debugger stop, with Unix signal numbers for traps
+fn stop_reply(stop: DebugStop) -> String {
+    use crate::core::trap::TrapCause;
+
+    match stop {
+        DebugStop::Halted => "W00".to_string(),
+        DebugStop::Trap(trap) => {
+            let signal = match trap.cause {
+                TrapCause::IllegalInstruction(_) => 4,
+                TrapCause::Overflow => 8,
+                _ => 11,
+            };
+            format!("S{:02x}", signal)
+        }
+        _ => "S05".to_string(),
+    }
+}
+
+/// Serves GDB's remote protocol over TCP, driving a `Debugger`
+pub struct GdbStub {
+    debugger: Debugger,
+}
+
+impl GdbStub {
+    pub fn new(debugger: Debugger) -> Self {
+        Self { debugger }
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    /// Accepts one connection and serves it until GDB detaches, kills the
+    /// target or disconnects
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (stream, _) = listener.accept()?;
+        self.serve_connection(stream)
+    }
+
+    pub fn serve_connection(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut connection = Connection::new(stream)?;
+        loop {
+            let packet = match connection.receive()? {
+                Incoming::Packet(packet) => packet,
+                Incoming::Interrupt => {
+                    connection.send("S02")?;
+                    continue;
+                }
+                Incoming::Closed => return Ok(()),
+            };
+
+            if packet == "QStartNoAckMode" {
+                connection.send("OK")?;
+                connection.no_ack = true;
+                continue;
+            }
+
+            match self.handle(&packet) {
+                Action::Reply(reply) => connection.send(&reply)?,
+                Action::Step => connection.send(&stop_reply(self.debugger.step()))?,
+                Action::Continue => {
+                    let reply = loop {
+                        match self.debugger.continue_for(CONTINUE_SLICE) {
+                            DebugStop::CycleLimit if connection.interrupted()? => {
+                                break "S02".to_string();
+                            }
+                            DebugStop::CycleLimit => {}
+                            stop => break stop_reply(stop),
+                        }
+                    };
+                    connection.send(&reply)?;
+                }
+                Action::Detach => return connection.send("OK"),
+                Action::Kill => return Ok(()),
+            }
+        }
+    }
+
+    fn handle(&mut self, packet: &str) -> Action {
+        let reply = |text: &str| Action::Reply(text.to_string());
+        let error = || reply("E01");
+
+        let Some(kind) = packet.chars().next() else {
+            return reply("");
+        };
+        // `receive` decodes lossily, so the kind may be a multi-byte U+FFFD
+        let body = &packet[kind.len_utf8()..];
+        match kind {
+            '?' => reply("S05"),
+            'g' => Action::Reply(
+                (0..REGISTER_COUNT)
+                    .map(|register| to_hex(&self.read_register(register)))
+                    .collect(),
+            ),
+            'G' => match from_hex(body) {
+                Some(bytes) if bytes.len() == REGISTER_COUNT * WORD_BYTES => {
+                    let values: Option<Vec<_>> = bytes
+                        .chunks(WORD_BYTES)
+                        .enumerate()
+                        .map(|(register, chunk)| {
+                            self.decode_register(register, chunk.try_into().unwrap())
+                        })
+                        .collect();
+                    match values {
+                        Some(values) => {
+                            for (register, value) in values.iter().enumerate() {
+                                self.write_register(register, value);
+                            }
+                            reply("OK")
+                        }
+                        None => error(),
+                    }
+                }
+                _ => error(),
+            },
+            'p' => match parse_hex(body) {
+                Some(register) if (register as usize) < REGISTER_COUNT => {
+                    Action::Reply(to_hex(&self.read_register(register as usize)))
+                }
+                _ => error(),
+            },
+            'P' => {
+                let parsed = body.split_once('=').and_then(|(register, value)| {
+                    let register = parse_hex(register)? as usize;
+                    let bytes = from_hex(value)?.try_into().ok()?;
+                    (register < REGISTER_COUNT).then_some((register, bytes))
+                });
+                match parsed.and_then(|(register, bytes)| {
+                    Some((register, self.decode_register(register, bytes)?))
+                }) {
+                    Some((register, value)) => {
+                        self.write_register(register, &value);
+                        reply("OK")
+                    }
+                    None => error(),
+                }
+            }
+            'm' => match parse_range(body) {
+                Some((address, length)) if length <= PACKET_SIZE / 2 => {
+                    Action::Reply(to_hex(&self.read_memory(address, length)))
+                }
+                _ => error(),
+            },
+            'M' => {
+                let parsed = body.split_once(':').and_then(|(range, data)| {
+                    let (address, length) = parse_range(range)?;
+                    let data = from_hex(data)?;
+                    (data.len() == length).then_some((address, data))
+                });
+                match parsed {
+                    Some((address, data)) if self.write_memory(address, &data) => reply("OK"),
+                    _ => error(),
+                }
+            }
+            's' | 'c' => {
+                if !body.is_empty() {
+                    match parse_hex(body) {
+                        Some(address) => self.resume_at(address),
+                        None => return error(),
+                    }
+                }
+                if kind == 's' {
+                    Action::Step
+                } else {
+                    Action::Continue
+                }
+            }
+            'Z' | 'z' => {
+                let mut fields = body.split(',');
+                let (Some("0"), Some(address), Some(_)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    // Only software breakpoints are supported
+                    return reply("");
+                };
+                let Some(address) = parse_hex(address) else {
+                    return error();
+                };
+                let (word, offset) = split_address(address);
+                if offset != 0 {
+                    return error();
+                }
+                if kind == 'Z' {
+                    self.debugger.add_breakpoint(word);
+                } else {
+                    self.debugger.remove_breakpoint(word);
+                }
+                reply("OK")
+            }
+            'H' | 'T' => reply("OK"),
+            'D' => Action::Detach,
+            'k' => Action::Kill,
+            'v' => match packet {
+                "vCont?" => reply("vCont;c;s"),
+                _ if packet.starts_with("vCont;s") => Action::Step,
+                _ if packet.starts_with("vCont;c") => Action::Continue,
+                _ => reply(""),
+            },
+            'q' => self.query(packet),
+            _ => reply(""),
+        }
+    }
+
+    fn query(&self, packet: &str) -> Action {
+        let reply = |text: &str| Action::Reply(text.to_string());
+
+        if packet.starts_with("qSupported") {
+            return Action::Reply(format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+",
+                PACKET_SIZE
+            ));
+        }
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, length)) = parse_range(range) else {
+                return reply("E01");
+            };
+            let xml = target_xml();
+            let start = (offset as usize).min(xml.len());
+            let end = start.saturating_add(length).min(xml.len());
+            let marker = if end == xml.len() { 'l' } else { 'm' };
+            return Action::Reply(format!("{}{}", marker, &xml[start..end]));
+        }
+
+        match packet {
+            "qAttached" => reply("1"),
+            "qC" => reply("QC1"),
+            "qfThreadInfo" => reply("m1"),
+            "qsThreadInfo" => reply("l"),
+            _ => reply(""),
+        }
+    }
+
+    fn read_register(&self, register: usize) -> [u8; WORD_BYTES] {
+        let registers = self.debugger.cpu().registers();
+        match register {
+            PC_REGISTER => byte_address(registers.read_pc().to_i128()).to_le_bytes(),
+            STATUS_REGISTER => encode_tryte(registers.read_status()),
+            index => encode_tryte(&registers.read_gpr(RegAddr::from_i128(index as i128))),
+        }
+    }
+
+    /// The value to store in `register`; the PC must be word-aligned
+    fn decode_register(&self, register: usize, bytes: [u8; WORD_BYTES]) -> Option<Tryte> {
+        if register == PC_REGISTER {
+            let (word, offset) = split_address(u64::from_le_bytes(bytes));
+            return (offset == 0 && word.abs() <= TRYTE_MAX).then(|| Tryte::from_i128(word));
+        }
+        decode_tryte(bytes)
+    }
+
+    fn write_register(&mut self, register: usize, value: &Tryte) {
+        let registers = self.debugger.cpu_mut().registers_mut();
+        match register {
+            PC_REGISTER => registers.write_pc(value),
+            STATUS_REGISTER => registers.write_status(value),
+            // Writes to x0 are discarded by the register file
+            index => registers.write_gpr(RegAddr::from_i128(index as i128), *value),
+        }
+    }
+
+    fn resume_at(&mut self, address: u64) {
+        let (word, _) = split_address(address);
+        self.debugger
+            .cpu_mut()
+            .registers_mut()
+            .write_pc(&Tryte::from_i128(word));
+    }
+
+    /// Device registers read as zero, as in the debugger
+    fn read_memory(&self, address: u64, length: usize) -> Vec<u8> {
+        (0..length as u64)
+            .map(|i| {
+                let (word, offset) = split_address(address.wrapping_add(i));
+                encode_tryte(&self.debugger.cpu().bus().read(Tryte::from_i128(word)))[offset]
+            })
+            .collect()
+    }
+
+    /// Patches the bytes of every word touched, writing nothing if any
+    /// resulting value is out of range
+    fn write_memory(&mut self, address: u64, data: &[u8]) -> bool {
+        let mut words: Vec<(i128, [u8; WORD_BYTES])> = Vec::new();
+        for (i, &byte) in data.iter().enumerate() {
+            let (word, offset) = split_address(address.wrapping_add(i as u64));
+            if words.last().map(|(last, _)| *last) != Some(word) {
+                let current = self.debugger.cpu().bus().read(Tryte::from_i128(word));
+                words.push((word, encode_tryte(&current)));
+            }
+            words.last_mut().unwrap().1[offset] = byte;
+        }
+
+        let Some(values) = words
+            .iter()
+            .map(|&(word, bytes)| Some((word, decode_tryte(bytes)?)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        for (word, value) in values {
+            self.debugger
+                .cpu_mut()
+                .bus_mut()
+                .write(Tryte::from_i128(word), value);
+        }
+        true
+    }
+}
+
+/// Parses `ADDR,LENGTH` in hex
+fn parse_range(text: &str) -> Option<(u64, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((parse_hex(address)?, parse_hex(length)? as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        core::{address_space::AddressSpace, alu::ArithmeticLogicUnit, registers::Registers},
+        cpu::CentralProcessingUnit,
+    };
+
+    const COUNTDOWN: &str = "
+              addi x1, x0, 3
+        loop: addi x1, x1, -1
+              sw   x1, 100(x0)
+              bne  x1, x0, loop
+              halt
+        ";
+
+    /// Client end of a connection to a stub serving `source` on another thread
+    struct Client {
+        stream: TcpStream,
+        server: thread::JoinHandle<()>,
+        no_ack: bool,
+    }
+
+    impl Client {
+        fn connect(source: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            // The CPU is built on the server thread: devices are not `Send`
+            let server = thread::spawn(move || {
+                let program = crate::asm::assembler::assemble(source).unwrap();
+                let mut mem = AddressSpace::default();
+                program.load_into(&mut mem);
+                let cpu = CentralProcessingUnit::from(
+                    Registers::default(),
+                    mem,
+                    ArithmeticLogicUnit::default(),
+                );
+                GdbStub::new(Debugger::new(cpu)).serve(&listener).unwrap();
+            });
+            let stream = TcpStream::connect(address).unwrap();
+            stream.set_nodelay(true).unwrap();
+            Self {
+                stream,
+                server,
+                no_ack: false,
+            }
+        }
+
+        fn read_byte(&mut self) -> u8 {
+            let mut byte = [0];
+            self.stream.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+
+        fn send(&mut self, data: &str) {
+            self.send_bytes(data.as_bytes());
+        }
+
+        fn send_bytes(&mut self, data: &[u8]) {
+            let mut packet = vec![b'$'];
+            packet.extend_from_slice(data);
+            packet.extend_from_slice(format!("#{:02x}", checksum_of(data)).as_bytes());
+            self.stream.write_all(&packet).unwrap();
+        }
+
+        /// Sends a packet and returns the reply, checking the ack
+        fn request(&mut self, data: &str) -> String {
+            self.send(data);
+            if !self.no_ack {
+                assert_eq!(self.read_byte(), b'+');
+            }
+            self.reply()
+        }
+
+        /// Reads a packet, checking its checksum and undoing escapes
+        fn reply(&mut self) -> String {
+            assert_eq!(self.read_byte(), b'$');
+            let mut raw = Vec::new();
+            loop {
+                match self.read_byte() {
+                    b'#' => break,
+                    byte => raw.push(byte),
+                }
+            }
+            let checksum = [self.read_byte(), self.read_byte()];
+            let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16);
+            assert_eq!(checksum, Ok(checksum_of(&raw)));
+
+            let mut data = Vec::new();
+            let mut bytes = raw.into_iter();
+            while let Some(byte) = bytes.next() {
+                match byte {
+                    b'}' => data.push(bytes.next().unwrap() ^ 0x20),
+                    byte => data.push(byte),
+                }
+            }
+            String::from_utf8(data).unwrap()
+        }
+
+        fn finish(mut self) {
+            assert_eq!(self.request("D"), "OK");
+            self.server.join().unwrap();
+        }
+    }
+
+    fn word_hex(value: i128) -> String {
+        to_hex(&encode_tryte(&Tryte::from_i128(value)))
+    }
+
+    #[test]
+    fn test_tryte_encoding_and_addresses() {
+        let value = Tryte::from_i128(-TRYTE_MAX);
+        assert_eq!(decode_tryte(encode_tryte(&value)), Some(value));
+        assert_eq!(
+            encode_tryte(&Tryte::from_i128(-2)),
+            [0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(decode_tryte((TRYTE_MAX as i64 + 1).to_le_bytes()), None);
+
+        assert_eq!(byte_address(3), 24);
+        assert_eq!(split_address(byte_address(-2)), (-2, 0));
+        assert_eq!(split_address(byte_address(-2) + 9), (-1, 1));
+    }
+
+    #[test]
+    fn test_registers_and_memory() {
+        let mut client = Client::connect(COUNTDOWN);
+        assert!(
+            client
+                .request("qSupported:swbreak+")
+                .contains("qXfer:features:read+")
+        );
+        assert_eq!(client.request("?"), "S05");
+
+        let xml = client.request("qXfer:features:read:target.xml:0,fff");
+        assert!(
+            xml.starts_with('l') && xml.contains("name=\"pc\""),
+            "{}",
+            xml
+        );
+
+        assert_eq!(client.request("s"), "S05");
+        let registers = client.request("g");
+        assert_eq!(registers.len(), REGISTER_COUNT * WORD_BYTES * 2);
+        assert_eq!(&registers[16..32], word_hex(3));
+        assert_eq!(client.request("p1b"), to_hex(&8u64.to_le_bytes()));
+
+        assert_eq!(client.request(&format!("P1={}", word_hex(-7))), "OK");
+        assert_eq!(client.request("p1"), word_hex(-7));
+        let too_big = to_hex(&i64::MAX.to_le_bytes());
+        assert_eq!(client.request(&format!("P1={}", too_big)), "E01");
+
+        // Word 100 is at byte 800; negative words wrap around
+        assert_eq!(client.request(&format!("M320,8:{}", word_hex(42))), "OK");
+        assert_eq!(client.request("m320,8"), word_hex(42));
+        let minus_one = format!("{:x}", byte_address(-1));
+        assert_eq!(
+            client.request(&format!("M{},8:{}", minus_one, word_hex(-5))),
+            "OK"
+        );
+        assert_eq!(client.request(&format!("m{},8", minus_one)), word_hex(-5));
+        assert_eq!(
+            client.request(&format!("M320,8:{}", too_big)),
+            "E01",
+            "out-of-range writes are refused"
+        );
+        assert_eq!(client.request("m320,8"), word_hex(42));
+
+        client.finish();
+    }
+
+    #[test]
+    fn test_breakpoints_continue_and_exit() {
+        let mut client = Client::connect(COUNTDOWN);
+        assert_eq!(client.request("QStartNoAckMode"), "OK");
+        client.no_ack = true;
+
+        assert_eq!(client.request("Z0,18,8"), "OK");
+        assert_eq!(client.request("c"), "S05");
+        assert_eq!(client.request("p1b"), to_hex(&24u64.to_le_bytes()));
+        assert_eq!(client.request("p1"), word_hex(2));
+        assert_eq!(client.request("c"), "S05");
+        assert_eq!(client.request("p1"), word_hex(1));
+
+        assert_eq!(client.request("z0,18,8"), "OK");
+        assert_eq!(client.request("c"), "W00");
+
+        // Kill has no reply; the stub just closes the connection
+        client.send("k");
+        client.server.join().unwrap();
+    }
+
+    #[test]
+    fn test_interrupt_stops_a_running_guest() {
+        let mut client = Client::connect("spin: jal x0, spin");
+        client.send("c");
+        assert_eq!(client.read_byte(), b'+');
+        client.stream.write_all(&[INTERRUPT]).unwrap();
+        assert_eq!(client.reply(), "S02");
+        assert_eq!(client.request("p1b"), to_hex(&0u64.to_le_bytes()));
+        client.finish();
+    }
+
+    #[test]
+    fn test_non_ascii_packets_are_unsupported() {
+        let mut client = Client::connect(COUNTDOWN);
+        client.send_bytes(&[0xff, b'1']);
+        assert_eq!(client.read_byte(), b'+');
+        assert_eq!(client.reply(), "");
+        assert_eq!(client.request("?"), "S05");
+        client.finish();
+    }
+}
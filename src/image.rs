@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    arch::trit::{TRYTE_MAX, Tryte},
+    asm::assembler::Program,
+    core::address_space::AddressSpace,
+};
+
+/// File signature of a `.bst` image
+pub const IMAGE_MAGIC: &[u8; 6] = b"BST27I";
+pub const IMAGE_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = IMAGE_MAGIC.len() + 2;
+const RECORD_LEN: usize = 16;
+
+/// A memory image as stored in `.bst` files.
+///
+/// Layout (all integers little-endian):
+/// - `BST27I` magic, then the format version as a `u16`
+/// - one 16-byte record per word: the address as an `i64`, then the word's
+///   balanced-ternary value as an `i64` (every 27-trit value fits)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Image {
+    words: BTreeMap<i128, Tryte>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The file ends in the middle of a record
+    Truncated,
+    /// A record holds a value outside the 27-trit range
+    ValueOutOfRange {
+        address: i128,
+        value: i128,
+    },
+    /// A word's address is outside the 27-trit address space
+    AddressOutOfRange(i128),
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::BadMagic => write!(f, "not a BST-27I image"),
+            ImageError::UnsupportedVersion(version) => {
+                write!(f, "unsupported image version {}", version)
+            }
+            ImageError::Truncated => write!(f, "image is truncated"),
+            ImageError::ValueOutOfRange { address, value } => write!(
+                f,
+                "word {} at address {} does not fit in 27 trits",
+                value, address
+            ),
+            ImageError::AddressOutOfRange(address) => write!(
+                f,
+                "address {} is outside the 27-trit address space",
+                address
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl Image {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, address: i128, word: Tryte) {
+        self.words.insert(address, word);
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = (i128, Tryte)> + '_ {
+        self.words.iter().map(|(address, word)| (*address, *word))
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// True if `bytes` starts with the image signature
+    pub fn is_image(bytes: &[u8]) -> bool {
+        bytes.starts_with(IMAGE_MAGIC)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ImageError> {
+        if !Self::is_image(bytes) {
+            return Err(ImageError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(ImageError::Truncated);
+        }
+
+        let version = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if version != IMAGE_VERSION {
+            return Err(ImageError::UnsupportedVersion(version));
+        }
+
+        let records = &bytes[HEADER_LEN..];
+        if !records.len().is_multiple_of(RECORD_LEN) {
+            return Err(ImageError::Truncated);
+        }
+
+        let mut image = Image::new();
+        for record in records.chunks_exact(RECORD_LEN) {
+            let address = i64::from_le_bytes(record[0..8].try_into().unwrap()) as i128;
+            let value = i64::from_le_bytes(record[8..16].try_into().unwrap()) as i128;
+
+            if !(-TRYTE_MAX..=TRYTE_MAX).contains(&address) {
+                return Err(ImageError::AddressOutOfRange(address));
+            }
+            if !(-TRYTE_MAX..=TRYTE_MAX).contains(&value) {
+                return Err(ImageError::ValueOutOfRange { address, value });
+            }
+            image.insert(address, Tryte::from_i128(value));
+        }
+
+        Ok(image)
+    }
+
+    /// Fails if a word was inserted at an address no `Tryte` can hold
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ImageError> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.words.len() * RECORD_LEN);
+        bytes.extend_from_slice(IMAGE_MAGIC);
+        bytes.extend_from_slice(&IMAGE_VERSION.to_le_bytes());
+
+        for (address, word) in self.words() {
+            if !(-TRYTE_MAX..=TRYTE_MAX).contains(&address) {
+                return Err(ImageError::AddressOutOfRange(address));
+            }
+            bytes.extend_from_slice(&(address as i64).to_le_bytes());
+            bytes.extend_from_slice(&(word.to_i128() as i64).to_le_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    /// Copies every word of the image into the address space.
+    pub fn load_into(&self, address_space: &mut AddressSpace) {
+        for (address, word) in self.words() {
+            address_space.write(Tryte::from_i128(address), word);
+        }
+    }
+}
+
+impl From<&Program> for Image {
+    fn from(program: &Program) -> Self {
+        Image {
+            words: program.words().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assembler::assemble;
+
+    #[test]
+    fn test_image_round_trips_through_bytes() {
+        let program = assemble(
+            "
+            addi x1, x0, -5
+            .org -3
+            .word 3812798742493, -3812798742493
+            ",
+        )
+        .unwrap();
+
+        let image = Image::from(&program);
+        let bytes = image.to_bytes().unwrap();
+
+        assert!(Image::is_image(&bytes));
+        assert_eq!(bytes.len(), HEADER_LEN + 3 * RECORD_LEN);
+        assert_eq!(Image::from_bytes(&bytes), Ok(image));
+    }
+
+    #[test]
+    fn test_image_loads_into_address_space() {
+        let mut image = Image::new();
+        image.insert(-1, Tryte::from_i128(7));
+        image.insert(40, Tryte::from_i128(-9));
+
+        let mut space = AddressSpace::default();
+        image.load_into(&mut space);
+
+        assert_eq!(space.read(Tryte::from_i128(-1)).to_i128(), 7);
+        assert_eq!(space.read(Tryte::from_i128(40)).to_i128(), -9);
+    }
+
+    #[test]
+    fn test_image_rejects_malformed_input() {
+        assert_eq!(Image::from_bytes(b"addi x1"), Err(ImageError::BadMagic));
+
+        let mut bytes = Image::new().to_bytes().unwrap();
+        bytes[6] = 9;
+        assert_eq!(
+            Image::from_bytes(&bytes),
+            Err(ImageError::UnsupportedVersion(9))
+        );
+
+        let mut bytes = Image::new().to_bytes().unwrap();
+        bytes.extend_from_slice(&[0; 10]);
+        assert_eq!(Image::from_bytes(&bytes), Err(ImageError::Truncated));
+
+        let mut bytes = Image::new().to_bytes().unwrap();
+        bytes.extend_from_slice(&0i64.to_le_bytes());
+        bytes.extend_from_slice(&i64::MAX.to_le_bytes());
+        assert!(matches!(
+            Image::from_bytes(&bytes),
+            Err(ImageError::ValueOutOfRange { address: 0, .. })
+        ));
+
+        let mut bytes = Image::new().to_bytes().unwrap();
+        bytes.extend_from_slice(&(TRYTE_MAX as i64 + 1).to_le_bytes());
+        bytes.extend_from_slice(&0i64.to_le_bytes());
+        assert_eq!(
+            Image::from_bytes(&bytes),
+            Err(ImageError::AddressOutOfRange(TRYTE_MAX + 1))
+        );
+
+        // Addresses are checked on the way out too, rather than truncated
+        let mut image = Image::new();
+        image.insert(i64::MAX as i128 + 1, Tryte::from_i128(1));
+        assert_eq!(
+            image.to_bytes(),
+            Err(ImageError::AddressOutOfRange(i64::MAX as i128 + 1))
+        );
+    }
+}
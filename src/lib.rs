@@ -1,3 +1,12 @@
 pub mod arch;
+pub mod asm;
 pub mod core;
 pub mod cpu;
+pub mod debugger;
+pub mod devices;
+pub mod gdb;
+pub mod image;
+pub mod snapshot;
+#[cfg(test)]
+pub mod test_util;
+pub mod trace;
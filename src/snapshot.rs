@@ -0,0 +1,552 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use crate::{
+    arch::trit::{TRYTE_MAX, Trit, Tryte},
+    core::{
+        address_space::AddressSpace, csr::Csr, interrupts::INTERRUPT_LINES, registers::RegAddr,
+    },
+    cpu::CentralProcessingUnit,
+};
+
+/// File signature of a machine snapshot
+pub const SNAPSHOT_MAGIC: &[u8; 6] = b"BST27S";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = SNAPSHOT_MAGIC.len() + 2;
+const FIELD_LEN: usize = 8;
+
+/// The complete state of a machine, enough to resume it cycle for cycle.
+///
+/// Host-side state is not included: breakpoints, tracers, and the host
+/// streams behind devices. A snapshot restores onto a machine whose bus has
+/// the same ROM and device ranges, attached in the same order.
+///
+/// Layout: `BST27S` magic, the format version as a little-endian `u16`, then
+/// a sequence of 8-byte little-endian `i64` fields. Trytes are stored by
+/// value, trits as -1, 0 or 1, flags as 0 or 1, and optional values as a flag
+/// followed by the value (0 when absent). In order:
+/// - machine: halted, cycles, instructions retired
+/// - registers: pc, x1..x26, status, scratch, trap cause, trap pc, trap vector
+/// - ALU: result, zero flag, sign flag, carry flag
+/// - interrupts: mask, pending, enabled, saved pc, vector, active line
+/// - memory: size, word count, then (address, value) for each nonzero word
+/// - bus: region count, then start, end, state length and state per region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    halted: bool,
+    cycles: u64,
+    retired: u64,
+    pc: Tryte,
+    gprs: Vec<Tryte>,
+    status: Tryte,
+    scratch: Tryte,
+    trap_cause: Tryte,
+    trap_pc: Tryte,
+    trap_vector: Option<Tryte>,
+    alu_result: Tryte,
+    alu_flags: [Trit; 3],
+    interrupt_mask: Tryte,
+    interrupt_pending: Tryte,
+    interrupts_enabled: bool,
+    interrupt_pc: Tryte,
+    interrupt_vector: Option<Tryte>,
+    active_line: Option<usize>,
+    memory_size: Option<i128>,
+    memory: Vec<(i128, Tryte)>,
+    regions: Vec<(Range<i128>, Vec<Tryte>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The file ends in the middle of a field, or before the last section
+    Truncated,
+    /// Bytes follow the last section
+    TrailingData,
+    /// A field holds a value its position does not allow
+    InvalidField(&'static str),
+    /// The machine's ROM and device ranges differ from the snapshot's
+    BusMismatch,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a BST-27I snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::TrailingData => write!(f, "unexpected data after the snapshot"),
+            SnapshotError::InvalidField(field) => write!(f, "invalid {} in snapshot", field),
+            SnapshotError::BusMismatch => write!(
+                f,
+                "the machine's ROM and device ranges differ from the snapshot's"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    pub fn capture(cpu: &CentralProcessingUnit) -> Self {
+        let registers = cpu.registers();
+        let interrupts = cpu.interrupts();
+        let alu = cpu.alu();
+        let bus = cpu.bus();
+
+        Self {
+            halted: cpu.is_halted(),
+            cycles: cpu.read_csr(Csr::Cycle).to_i128() as u64,
+            retired: cpu.read_csr(Csr::Instret).to_i128() as u64,
+            pc: *registers.read_pc(),
+            gprs: (1..27)
+                .map(|index| registers.read_gpr(RegAddr::from_i128(index)))
+                .collect(),
+            status: *registers.read_status(),
+            scratch: *registers.read_scratch(),
+            trap_cause: *registers.read_trap_cause(),
+            trap_pc: *registers.read_trap_pc(),
+            trap_vector: registers.read_trap_vector().copied(),
+            alu_result: alu.result,
+            alu_flags: [alu.zero_flag, alu.sign_flag, alu.carry_flag],
+            interrupt_mask: *interrupts.read_mask(),
+            interrupt_pending: *interrupts.read_pending(),
+            interrupts_enabled: interrupts.is_enabled(),
+            interrupt_pc: *interrupts.read_saved_pc(),
+            interrupt_vector: interrupts.read_vector().copied(),
+            active_line: interrupts.active_line(),
+            memory_size: bus.ram().size(),
+            memory: bus.ram().words(),
+            regions: bus
+                .region_ranges()
+                .into_iter()
+                .zip(bus.save_regions())
+                .collect(),
+        }
+    }
+
+    /// Puts `cpu` in the captured state. Nothing is changed if the bus layout
+    /// does not match.
+    pub fn restore(&self, cpu: &mut CentralProcessingUnit) -> Result<(), SnapshotError> {
+        let ranges: Vec<_> = self
+            .regions
+            .iter()
+            .map(|(range, _)| range.clone())
+            .collect();
+        if cpu.bus().region_ranges() != ranges {
+            return Err(SnapshotError::BusMismatch);
+        }
+
+        cpu.set_halted(self.halted);
+        cpu.write_csr(Csr::Cycle, &Tryte::from_i128(self.cycles as i128));
+        cpu.write_csr(Csr::Instret, &Tryte::from_i128(self.retired as i128));
+
+        let registers = cpu.registers_mut();
+        registers.write_pc(&self.pc);
+        for (index, value) in (1..27).zip(&self.gprs) {
+            registers.write_gpr(RegAddr::from_i128(index), *value);
+        }
+        registers.write_status(&self.status);
+        registers.write_scratch(&self.scratch);
+        registers.write_trap_cause(&self.trap_cause);
+        registers.write_trap_pc(&self.trap_pc);
+        registers.write_trap_vector(self.trap_vector.as_ref());
+
+        let alu = cpu.alu_mut();
+        alu.result = self.alu_result;
+        [alu.zero_flag, alu.sign_flag, alu.carry_flag] = self.alu_flags;
+
+        let interrupts = cpu.interrupts_mut();
+        interrupts.write_mask(&self.interrupt_mask);
+        interrupts.update(lines_of(&self.interrupt_pending));
+        interrupts.set_enabled(self.interrupts_enabled);
+        interrupts.write_saved_pc(&self.interrupt_pc);
+        interrupts.write_vector(self.interrupt_vector.as_ref());
+        interrupts.set_active_line(self.active_line);
+
+        let mut memory = match self.memory_size {
+            Some(size) => AddressSpace::with_size(size),
+            None => AddressSpace::default(),
+        };
+        for &(address, value) in &self.memory {
+            memory.write(Tryte::from_i128(address), value);
+        }
+        let bus = cpu.bus_mut();
+        *bus.ram_mut() = memory;
+        let states: Vec<_> = self
+            .regions
+            .iter()
+            .map(|(_, state)| state.clone())
+            .collect();
+        bus.restore_regions(&states);
+        // Recorded cycles would undo into the replaced state
+        cpu.clear_history();
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut fields = Vec::new();
+        let tryte = |fields: &mut Vec<i64>, value: &Tryte| fields.push(value.to_i128() as i64);
+
+        fields.extend([self.halted as i64, self.cycles as i64, self.retired as i64]);
+
+        tryte(&mut fields, &self.pc);
+        for value in &self.gprs {
+            tryte(&mut fields, value);
+        }
+        for value in [&self.status, &self.scratch, &self.trap_cause, &self.trap_pc] {
+            tryte(&mut fields, value);
+        }
+        push_optional(&mut fields, self.trap_vector.map(|v| v.to_i128() as i64));
+
+        tryte(&mut fields, &self.alu_result);
+        fields.extend(self.alu_flags.map(|trit| trit.to_i8() as i64));
+
+        tryte(&mut fields, &self.interrupt_mask);
+        tryte(&mut fields, &self.interrupt_pending);
+        fields.push(self.interrupts_enabled as i64);
+        tryte(&mut fields, &self.interrupt_pc);
+        push_optional(
+            &mut fields,
+            self.interrupt_vector.map(|v| v.to_i128() as i64),
+        );
+        push_optional(&mut fields, self.active_line.map(|line| line as i64));
+
+        push_optional(&mut fields, self.memory_size.map(|size| size as i64));
+        fields.push(self.memory.len() as i64);
+        for (address, value) in &self.memory {
+            fields.push(*address as i64);
+            tryte(&mut fields, value);
+        }
+
+        fields.push(self.regions.len() as i64);
+        for (range, state) in &self.regions {
+            fields.extend([range.start as i64, range.end as i64, state.len() as i64]);
+            for value in state {
+                tryte(&mut fields, value);
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + fields.len() * FIELD_LEN);
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        for field in fields {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if !bytes.starts_with(SNAPSHOT_MAGIC) {
+            return Err(SnapshotError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(SnapshotError::Truncated);
+        }
+        let version = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let body = &bytes[HEADER_LEN..];
+        if !body.len().is_multiple_of(FIELD_LEN) {
+            return Err(SnapshotError::Truncated);
+        }
+        let mut reader = FieldReader {
+            fields: body
+                .chunks_exact(FIELD_LEN)
+                .map(|field| i64::from_le_bytes(field.try_into().unwrap())),
+        };
+
+        let halted = reader.flag("halted flag")?;
+        let cycles = reader.next()? as u64;
+        let retired = reader.next()? as u64;
+
+        let pc = reader.tryte("pc")?;
+        let gprs = (1..27)
+            .map(|_| reader.tryte("register"))
+            .collect::<Result<_, _>>()?;
+        let status = reader.tryte("status")?;
+        let scratch = reader.tryte("scratch")?;
+        let trap_cause = reader.tryte("trap cause")?;
+        let trap_pc = reader.tryte("trap pc")?;
+        let trap_vector = reader.optional_tryte("trap vector")?;
+
+        let alu_result = reader.tryte("ALU result")?;
+        let alu_flags = [
+            reader.trit("zero flag")?,
+            reader.trit("sign flag")?,
+            reader.trit("carry flag")?,
+        ];
+
+        let interrupt_mask = reader.tryte("interrupt mask")?;
+        let interrupt_pending = reader.tryte("pending interrupts")?;
+        let interrupts_enabled = reader.flag("interrupt enable")?;
+        let interrupt_pc = reader.tryte("interrupt pc")?;
+        let interrupt_vector = reader.optional_tryte("interrupt vector")?;
+        let active_line = reader
+            .optional("active interrupt line")?
+            .map(|line| {
+                usize::try_from(line)
+                    .ok()
+                    .filter(|&line| line < INTERRUPT_LINES)
+                    .ok_or(SnapshotError::InvalidField("active interrupt line"))
+            })
+            .transpose()?;
+
+        let memory_size = reader.optional("memory size")?.map(i128::from);
+        let words = reader.count("word count")?;
+        let mut memory = Vec::new();
+        for _ in 0..words {
+            let address = reader.tryte("memory address")?.to_i128();
+            memory.push((address, reader.tryte("memory word")?));
+        }
+
+        let region_count = reader.count("region count")?;
+        let mut regions = Vec::new();
+        for _ in 0..region_count {
+            let range = reader.next()? as i128..reader.next()? as i128;
+            let len = reader.count("region state length")?;
+            let state = (0..len)
+                .map(|_| reader.tryte("region state"))
+                .collect::<Result<_, _>>()?;
+            regions.push((range, state));
+        }
+
+        if reader.fields.next().is_some() {
+            return Err(SnapshotError::TrailingData);
+        }
+
+        Ok(Self {
+            halted,
+            cycles,
+            retired,
+            pc,
+            gprs,
+            status,
+            scratch,
+            trap_cause,
+            trap_pc,
+            trap_vector,
+            alu_result,
+            alu_flags,
+            interrupt_mask,
+            interrupt_pending,
+            interrupts_enabled,
+            interrupt_pc,
+            interrupt_vector,
+            active_line,
+            memory_size,
+            memory,
+            regions,
+        })
+    }
+}
+
+fn push_optional(fields: &mut Vec<i64>, value: Option<i64>) {
+    fields.extend([value.is_some() as i64, value.unwrap_or(0)]);
+}
+
+/// Interrupt lines raised in a pending register, bit `i` being line `i`
+fn lines_of(pending: &Tryte) -> u32 {
+    pending
+        .trits()
+        .enumerate()
+        .filter(|(_, trit)| *trit == Trit::Positive)
+        .fold(0, |lines, (line, _)| lines | 1 << line)
+}
+
+struct FieldReader<I: Iterator<Item = i64>> {
+    fields: I,
+}
+
+impl<I: Iterator<Item = i64>> FieldReader<I> {
+    fn next(&mut self) -> Result<i64, SnapshotError> {
+        self.fields.next().ok_or(SnapshotError::Truncated)
+    }
+
+    fn tryte(&mut self, field: &'static str) -> Result<Tryte, SnapshotError> {
+        let value = self.next()? as i128;
+        if !(-TRYTE_MAX..=TRYTE_MAX).contains(&value) {
+            return Err(SnapshotError::InvalidField(field));
+        }
+        Ok(Tryte::from_i128(value))
+    }
+
+    fn trit(&mut self, field: &'static str) -> Result<Trit, SnapshotError> {
+        match self.next()? {
+            value @ -1..=1 => Ok(Trit::from_i8(value as i8)),
+            _ => Err(SnapshotError::InvalidField(field)),
+        }
+    }
+
+    fn flag(&mut self, field: &'static str) -> Result<bool, SnapshotError> {
+        match self.next()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::InvalidField(field)),
+        }
+    }
+
+    fn count(&mut self, field: &'static str) -> Result<usize, SnapshotError> {
+        usize::try_from(self.next()?).map_err(|_| SnapshotError::InvalidField(field))
+    }
+
+    fn optional(&mut self, field: &'static str) -> Result<Option<i64>, SnapshotError> {
+        let present = self.flag(field)?;
+        let value = self.next()?;
+        Ok(present.then_some(value))
+    }
+
+    fn optional_tryte(&mut self, field: &'static str) -> Result<Option<Tryte>, SnapshotError> {
+        if self.flag(field)? {
+            self.tryte(field).map(Some)
+        } else {
+            self.next()?;
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{alu::ArithmeticLogicUnit, bus::Bus, registers::Registers},
+        cpu::StopReason,
+        devices::timer::{TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer},
+    };
+
+    /// Counts timer interrupts at 100 while the main loop sums into x2
+    const TICKING: &str = "
+                 addi x1, x0, 7
+                 sw   x1, -719(x0)     ; compare
+                 addi x1, x0, 4        ; enabled, periodic
+                 sw   x1, -718(x0)
+                 ei
+        loop:    addi x2, x2, 3
+                 csrrw x3, scratch, x2
+                 jal  x0, loop
+
+        .org 40
+        handler: lw   x9, 100(x0)
+                 addi x9, x9, 1
+                 sw   x9, 100(x0)
+                 sw   x0, -717(x0)     ; acknowledge
+                 iret
+        ";
+
+    fn machine(with_timer: bool) -> CentralProcessingUnit {
+        let program = crate::asm::assembler::assemble(TICKING).unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        let mut bus = Bus::from(mem);
+        if with_timer {
+            bus.attach_interrupting(TIMER_BASE, TIMER_LEN, TIMER_LINE, Timer::new())
+                .unwrap();
+        }
+
+        let mut cpu =
+            CentralProcessingUnit::from(Registers::default(), bus, ArithmeticLogicUnit::default());
+        cpu.interrupts_mut()
+            .write_vector(Some(&Tryte::from_i128(40)));
+        cpu
+    }
+
+    #[test]
+    fn test_restored_machine_runs_identically() {
+        let mut original = machine(true);
+        assert_eq!(original.run(503).reason, StopReason::CycleLimit);
+        let bytes = Snapshot::capture(&original).to_bytes();
+
+        let mut restored = machine(true);
+        Snapshot::from_bytes(&bytes)
+            .unwrap()
+            .restore(&mut restored)
+            .unwrap();
+        assert_eq!(Snapshot::capture(&restored), Snapshot::capture(&original));
+
+        // The interrupt count and the timer phase carry over
+        for cpu in [&mut original, &mut restored] {
+            assert_eq!(cpu.run(1000).reason, StopReason::CycleLimit);
+        }
+        assert_eq!(
+            Snapshot::capture(&restored).to_bytes(),
+            Snapshot::capture(&original).to_bytes()
+        );
+        assert!(original.bus().read(Tryte::from_i128(100)).to_i128() > 100);
+    }
+
+    #[test]
+    fn test_restore_needs_the_same_bus_layout() {
+        let mut original = machine(true);
+        original.run(50);
+        let snapshot = Snapshot::capture(&original);
+
+        let mut without_timer = machine(false);
+        assert_eq!(
+            snapshot.restore(&mut without_timer),
+            Err(SnapshotError::BusMismatch)
+        );
+        assert_eq!(without_timer.registers().read_pc().to_i128(), 0);
+    }
+
+    #[test]
+    fn test_rejects_malformed_snapshots() {
+        let bytes = Snapshot::capture(&machine(true)).to_bytes();
+        assert_eq!(
+            Snapshot::from_bytes(b"BST27I\x01\x00"),
+            Err(SnapshotError::BadMagic)
+        );
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[6] = 2;
+        assert_eq!(
+            Snapshot::from_bytes(&wrong_version),
+            Err(SnapshotError::UnsupportedVersion(2))
+        );
+
+        assert_eq!(
+            Snapshot::from_bytes(&bytes[..bytes.len() - 8]),
+            Err(SnapshotError::Truncated)
+        );
+        assert_eq!(
+            Snapshot::from_bytes(&bytes[..bytes.len() - 3]),
+            Err(SnapshotError::Truncated)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.extend_from_slice(&[0; 8]);
+        assert_eq!(
+            Snapshot::from_bytes(&trailing),
+            Err(SnapshotError::TrailingData)
+        );
+
+        let mut bad_flag = bytes.clone();
+        bad_flag[HEADER_LEN] = 2;
+        assert_eq!(
+            Snapshot::from_bytes(&bad_flag),
+            Err(SnapshotError::InvalidField("halted flag"))
+        );
+
+        let mut bad_line = Snapshot::capture(&machine(true));
+        bad_line.active_line = Some(INTERRUPT_LINES);
+        assert_eq!(
+            Snapshot::from_bytes(&bad_line.to_bytes()),
+            Err(SnapshotError::InvalidField("active interrupt line"))
+        );
+
+        let mut bad_address = Snapshot::capture(&machine(true));
+        bad_address
+            .memory
+            .push((TRYTE_MAX + 1, Tryte::from_i128(1)));
+        assert_eq!(
+            Snapshot::from_bytes(&bad_address.to_bytes()),
+            Err(SnapshotError::InvalidField("memory address"))
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the unit tests
+
+use crate::arch::trit::{Trit, TritField};
+
+/// Small xorshift generator so randomised tests stay deterministic
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Value in `0..bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    pub fn trit(&mut self) -> Trit {
+        [Trit::Negative, Trit::Zero, Trit::Positive][self.below(3) as usize]
+    }
+
+    /// Field with every trit drawn uniformly
+    pub fn field<const N: usize>(&mut self) -> TritField<N> {
+        TritField::from_trits(std::array::from_fn(|_| self.trit()))
+    }
+}
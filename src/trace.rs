@@ -0,0 +1,387 @@
+use std::fmt::{Display, Formatter, Write as _};
+use std::io::{self, Write};
+
+use crate::{
+    arch::{
+        instructions::{ControlSignals, Instruction},
+        trit::Tryte,
+    },
+    asm::disassembler::Disassembler,
+    core::csr::Csr,
+};
+
+/// A register an instruction wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TracedRegister {
+    Gpr(usize),
+    Csr(Csr),
+}
+
+impl Display for TracedRegister {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TracedRegister::Gpr(index) => write!(f, "x{}", index),
+            TracedRegister::Csr(csr) => write!(f, "{}", csr.name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWrite {
+    pub register: TracedRegister,
+    pub value: Tryte,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Load,
+    Store,
+}
+
+impl AccessKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccessKind::Load => "load",
+            AccessKind::Store => "store",
+        }
+    }
+}
+
+/// A data access; instruction fetches are not recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub address: i128,
+    /// Value loaded or stored
+    pub value: Tryte,
+}
+
+/// Everything one retired instruction did
+#[derive(Debug, Clone)]
+pub struct TraceRecord {
+    /// Cycles executed before this instruction
+    pub cycle: u64,
+    pub pc: i128,
+    pub raw: Tryte,
+    pub instruction: Instruction,
+    pub signals: ControlSignals,
+    /// Register writes in the order they happened. Writes to x0 are left out.
+    pub register_writes: Vec<RegisterWrite>,
+    pub memory_accesses: Vec<MemoryAccess>,
+    pub next_pc: i128,
+}
+
+impl TraceRecord {
+    pub fn new(cycle: u64, pc: i128) -> Self {
+        Self {
+            cycle,
+            pc,
+            raw: Tryte::default(),
+            instruction: Instruction::Nop,
+            signals: ControlSignals::default(),
+            register_writes: Vec::new(),
+            memory_accesses: Vec::new(),
+            next_pc: pc,
+        }
+    }
+
+    /// One JSON object, without a trailing newline. Trytes are integers,
+    /// except `raw` which is also given as a trit string, most significant first.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"cycle\":{},\"pc\":{},\"raw\":{},\"raw_trits\":\"{}\",\"instruction\":\"{}\",",
+            self.cycle,
+            self.pc,
+            self.raw.to_i128(),
+            self.raw.to_trit_string(),
+            Disassembler::new().format_instruction(&self.instruction)
+        )
+        .unwrap();
+
+        let signals = &self.signals;
+        write!(
+            json,
+            "\"signals\":{{\"alu_op\":\"{:?}\",\"branch_condition\":\"{:?}\"",
+            signals.alu_op, signals.branch_condition
+        )
+        .unwrap();
+        for (name, set) in signal_flags(signals) {
+            write!(json, ",\"{}\":{}", name, set).unwrap();
+        }
+
+        json.push_str("},\"registers\":[");
+        for (i, write) in self.register_writes.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"register\":\"{}\",\"value\":{}}}",
+                write.register,
+                write.value.to_i128()
+            )
+            .unwrap();
+        }
+
+        json.push_str("],\"memory\":[");
+        for (i, access) in self.memory_accesses.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"access\":\"{}\",\"address\":{},\"value\":{}}}",
+                access.kind.name(),
+                access.address,
+                access.value.to_i128()
+            )
+            .unwrap();
+        }
+        write!(json, "],\"next_pc\":{}}}", self.next_pc).unwrap();
+        json
+    }
+}
+
+impl Display for TraceRecord {
+    /// `cycle pc raw instruction | signals | effects -> next pc`
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>8} {:>10}  {}  {:<24} | {:?}",
+            self.cycle,
+            self.pc,
+            self.raw.to_heptavintimal(),
+            Disassembler::new().format_instruction(&self.instruction),
+            self.signals.alu_op
+        )?;
+        for (name, set) in signal_flags(&self.signals) {
+            if set {
+                write!(f, " {}", name)?;
+            }
+        }
+
+        f.write_str(" |")?;
+        for write in &self.register_writes {
+            write!(f, " {}={}", write.register, write.value.to_i128())?;
+        }
+        for access in &self.memory_accesses {
+            write!(
+                f,
+                " {}[{}]={}",
+                access.kind.name(),
+                access.address,
+                access.value.to_i128()
+            )?;
+        }
+        write!(f, " -> {}", self.next_pc)
+    }
+}
+
+/// Every boolean control signal by name
+fn signal_flags(signals: &ControlSignals) -> [(&'static str, bool); 17] {
+    [
+        ("alu_src", signals.alu_src),
+        ("reg_write", signals.reg_write),
+        ("mem_read", signals.mem_read),
+        ("mem_write", signals.mem_write),
+        ("mem_to_reg", signals.mem_to_reg),
+        ("branch", signals.branch),
+        ("jump", signals.jump),
+        ("jump_register", signals.jump_register),
+        ("halt", signals.halt),
+        ("trap_return", signals.trap_return),
+        ("enable_interrupts", signals.enable_interrupts),
+        ("disable_interrupts", signals.disable_interrupts),
+        ("interrupt_return", signals.interrupt_return),
+        ("csr", signals.csr),
+        ("set_status", signals.set_status),
+        ("carry_in", signals.carry_in),
+        ("trap_on_overflow", signals.trap_on_overflow),
+    ]
+}
+
+/// Receives a record for every retired instruction
+pub trait Tracer {
+    fn trace(&mut self, record: &TraceRecord);
+}
+
+impl<F: FnMut(&TraceRecord)> Tracer for F {
+    fn trace(&mut self, record: &TraceRecord) {
+        self(record)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One aligned line per instruction, see `TraceRecord`'s `Display`
+    Text,
+    /// One JSON object per line, see `TraceRecord::to_json`
+    JsonLines,
+}
+
+/// Writes records to `output` in one of the `TraceFormat`s
+pub struct TraceWriter<W: Write> {
+    output: W,
+    format: TraceFormat,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(output: W, format: TraceFormat) -> Self {
+        Self { output, format }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn write_record(&mut self, record: &TraceRecord) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Text => writeln!(self.output, "{}", record),
+            TraceFormat::JsonLines => writeln!(self.output, "{}", record.to_json()),
+        }
+    }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+    fn trace(&mut self, record: &TraceRecord) {
+        // A failing trace sink must not change how the guest runs
+        let _ = self.write_record(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        core::{
+            address_space::AddressSpace, alu::ArithmeticLogicUnit, registers::Registers,
+            status::Status,
+        },
+        cpu::{CentralProcessingUnit, StopReason},
+    };
+
+    fn cpu_for(source: &str) -> CentralProcessingUnit {
+        let program = crate::asm::assembler::assemble(source).unwrap();
+        let mut mem = AddressSpace::default();
+        program.load_into(&mut mem);
+        CentralProcessingUnit::from(Registers::default(), mem, ArithmeticLogicUnit::default())
+    }
+
+    fn record(source: &str, cycles: u64) -> Vec<TraceRecord> {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let mut cpu = cpu_for(source);
+        let sink = records.clone();
+        cpu.set_tracer(move |record: &TraceRecord| sink.borrow_mut().push(record.clone()));
+        cpu.run(cycles);
+        records.take()
+    }
+
+    #[test]
+    fn test_records_effects_of_each_instruction() {
+        let records = record(
+            "
+            addi x1, x0, 5
+            sw   x1, 100(x0)
+            lw   x2, 100(x0)
+            addi x0, x1, 1
+            halt
+            ",
+            10,
+        );
+        assert_eq!(records.len(), 5);
+
+        let addi = &records[0];
+        assert_eq!((addi.cycle, addi.pc, addi.next_pc), (0, 0, 1));
+        assert!(matches!(addi.instruction, Instruction::Addi { .. }));
+        assert!(addi.signals.reg_write && addi.signals.set_status);
+        let status = Status {
+            carry: crate::arch::trit::Trit::Zero,
+            overflow: false,
+            sign: crate::arch::trit::Trit::Positive,
+            zero: false,
+        };
+        assert_eq!(
+            addi.register_writes,
+            [
+                RegisterWrite {
+                    register: TracedRegister::Csr(Csr::Status),
+                    value: status.to_tryte(),
+                },
+                RegisterWrite {
+                    register: TracedRegister::Gpr(1),
+                    value: Tryte::from_i128(5),
+                },
+            ]
+        );
+
+        let five = Tryte::from_i128(5);
+        assert_eq!(
+            records[1].memory_accesses,
+            [MemoryAccess {
+                kind: AccessKind::Store,
+                address: 100,
+                value: five
+            }]
+        );
+        assert_eq!(records[2].memory_accesses[0].kind, AccessKind::Load);
+        assert_eq!(
+            records[2].register_writes.last().unwrap().register,
+            TracedRegister::Gpr(2)
+        );
+
+        // Writes to x0 are discarded, so they are not traced
+        assert!(
+            records[3]
+                .register_writes
+                .iter()
+                .all(|write| write.register != TracedRegister::Gpr(0))
+        );
+        assert!(records[4].signals.halt);
+    }
+
+    #[test]
+    fn test_trapped_instructions_are_not_retired() {
+        let mut cpu = cpu_for("addi x1, x0, 1\n.word 0t1T1T1T1T1T1T1T1T1T1T1T1T1\n");
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let sink = records.clone();
+        cpu.set_tracer(move |record: &TraceRecord| sink.borrow_mut().push(record.pc));
+
+        assert!(matches!(cpu.run(10).reason, StopReason::Fault(_)));
+        assert_eq!(*records.borrow(), [0]);
+        assert!(cpu.take_tracer().is_some());
+    }
+
+    #[test]
+    fn test_text_and_json_lines() {
+        let records = record("addi x1, x0, 5\nsw x1, -3(x0)\n", 2);
+
+        let mut text = TraceWriter::new(Vec::new(), TraceFormat::Text);
+        let mut json = TraceWriter::new(Vec::new(), TraceFormat::JsonLines);
+        for record in &records {
+            text.trace(record);
+            json.trace(record);
+        }
+
+        let text = String::from_utf8(text.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].contains("ADDI x1, x0, 5"), "{}", lines[0]);
+        assert!(lines[0].ends_with("x1=5 -> 1"), "{}", lines[0]);
+        assert!(lines[1].ends_with("| store[-3]=5 -> 2"), "{}", lines[1]);
+
+        let json = String::from_utf8(json.into_inner()).unwrap();
+        let lines: Vec<&str> = json.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("{\"cycle\":0,\"pc\":0,\"raw\":"));
+        assert!(lines[0].contains("\"instruction\":\"ADDI x1, x0, 5\""));
+        assert!(lines[0].contains("\"alu_op\":\"Add\""));
+        assert!(lines[0].contains("{\"register\":\"x1\",\"value\":5}"));
+        assert_eq!(
+            lines[1].split_once("\"memory\":").unwrap().1,
+            "[{\"access\":\"store\",\"address\":-3,\"value\":5}],\"next_pc\":2}"
+        );
+    }
+}